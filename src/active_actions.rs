@@ -1,21 +1,190 @@
 use eframe::egui;
 use egui::{Color32, Frame, Layout, RichText, Vec2};
 use egui_phosphor::regular::*;
+use std::time::Duration;
+
+use crate::battery;
+use crate::caffeine::Caffeine;
+use crate::notify::{self, Urgency};
+use crate::palette::Palette;
+use crate::quick_settings::DetailPage;
+use crate::screenshot::{Screenshot, ScreenshotMode, Screenshotter};
+use crate::task::BackgroundTask;
+
+/// Caffeine durations offered on right-click
+const CAFFEINE_DURATIONS: [(&str, Option<Duration>); 4] = [
+    ("Until turned off", None),
+    ("For 30 minutes", Some(Duration::from_secs(30 * 60))),
+    ("For 1 hour", Some(Duration::from_secs(60 * 60))),
+    ("For 2 hours", Some(Duration::from_secs(2 * 60 * 60))),
+];
 
 pub struct ActiveActions {
-    colors: super::Colors,
+    colors: Palette,
+    caffeine: Caffeine,
+    /// How long a plain click on the caffeine button keeps the screen awake
+    caffeine_timeout: Option<Duration>,
+    screenshotter: Screenshotter,
+    /// Capture running while the panel is hidden
+    screenshot: Option<BackgroundTask<Result<Screenshot, String>>>,
 }
 
 impl ActiveActions {
-    pub fn new(colors: super::Colors) -> Self {
-        Self { colors }
+    pub fn new(
+        colors: Palette,
+        caffeine: Caffeine,
+        caffeine_timeout: Option<Duration>,
+        screenshotter: Screenshotter,
+    ) -> Self {
+        Self {
+            colors,
+            caffeine,
+            caffeine_timeout,
+            screenshotter,
+            screenshot: None,
+        }
+    }
+
+    /// Keeps timers running while the main page isn't drawn
+    pub fn tick(&mut self, ctx: &egui::Context) {
+        ctx.request_repaint_after(self.caffeine.tick());
+
+        if let Some(result) = self.screenshot.as_ref().and_then(BackgroundTask::poll) {
+            self.screenshot = None;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+            match result {
+                Ok(screenshot) => {
+                    let icon = screenshot
+                        .thumbnail
+                        .as_ref()
+                        .map(|thumbnail| thumbnail.to_string_lossy().into_owned());
+                    let name = screenshot
+                        .path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    notify::send(
+                        "Screenshot saved",
+                        &format!("{}\nCopied to the clipboard", name),
+                        icon.as_deref(),
+                        Urgency::Normal,
+                    );
+                }
+                Err(e) if e == "cancelled" => {}
+                Err(e) => notify::send("Screenshot failed", &e, None, Urgency::Normal),
+            }
+        }
+    }
+
+    /// Hides the panel and captures once it's out of the way. The panel comes
+    /// back when the capture is done.
+    fn take_screenshot(&mut self, ctx: &egui::Context, mode: ScreenshotMode) {
+        if self.screenshot.is_some() {
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        let screenshotter = self.screenshotter.clone();
+        let ctx_clone = ctx.clone();
+        self.screenshot = Some(BackgroundTask::spawn(ctx, move || {
+            std::thread::sleep(screenshotter.hide_delay());
+            let result = screenshotter.capture(mode);
+            // Ask for the panel back from here too, in case frames stop
+            // while it's hidden
+            ctx_clone.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+            result
+        }));
+    }
+
+    /// Click takes a screenshot in `mode`, right-click offers the others
+    fn screenshot_controls(
+        &mut self,
+        ui: &egui::Ui,
+        response: egui::Response,
+        mode: ScreenshotMode,
+    ) {
+        let response = response.on_hover_text(format!("Screenshot: {}", mode.label()));
+        if response.clicked() {
+            self.take_screenshot(ui.ctx(), mode);
+        }
+        response.context_menu(|ui| {
+            for other in ScreenshotMode::ALL {
+                if ui.button(other.label()).clicked() {
+                    self.take_screenshot(ui.ctx(), other);
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+
+    /// One of the round buttons in the grid, filled while `active`
+    fn action_button(&self, ui: &mut egui::Ui, icon: &str, active: bool) -> egui::Response {
+        let (fill, color) = if active {
+            (self.colors.primary, self.colors.on_primary)
+        } else {
+            (self.colors.surface, self.colors.on_surface)
+        };
+        egui::Frame::new()
+            .fill(fill)
+            .corner_radius(18.0)
+            .inner_margin(6.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::Button::new(RichText::new(icon).size(20.0).color(color))
+                        .fill(Color32::TRANSPARENT)
+                        .min_size(Vec2::new(36.0, 36.0)),
+                )
+            })
+            .inner
+    }
+
+    /// Click toggles caffeine, right-click picks how long, and the tooltip
+    /// lists what else is keeping the screen awake
+    fn caffeine_controls(&mut self, response: egui::Response) {
+        let mut tooltip = match (self.caffeine.is_active(), self.caffeine.remaining()) {
+            (true, Some(left)) => format!(
+                "Keeping the screen awake, {} left",
+                battery::format_duration(left + Duration::from_secs(59))
+            ),
+            (true, None) => "Keeping the screen awake".to_string(),
+            (false, _) => "Keep the screen awake".to_string(),
+        };
+        let others = self.caffeine.others();
+        if !others.is_empty() {
+            tooltip.push_str("\n\nAlso keeping it awake:");
+            for title in others {
+                tooltip.push_str(&format!("\n• {}", title));
+            }
+        }
+
+        let response = response.on_hover_text(tooltip);
+        if response.clicked() {
+            if self.caffeine.is_active() {
+                self.caffeine.stop();
+            } else {
+                self.caffeine.start(self.caffeine_timeout);
+            }
+        }
+        response.context_menu(|ui| {
+            for (label, timeout) in CAFFEINE_DURATIONS {
+                if ui.button(label).clicked() {
+                    self.caffeine.start(timeout);
+                    ui.close_menu();
+                }
+            }
+            if self.caffeine.is_active() && ui.button("Turn off").clicked() {
+                self.caffeine.stop();
+                ui.close_menu();
+            }
+        });
     }
 
-    pub fn update_colors(&mut self, colors: super::Colors) {
+    pub fn update_colors(&mut self, colors: Palette) {
         self.colors = colors;
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui) {
+    /// Returns the detail page to open when one of the buttons asks for it
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<DetailPage> {
+        let mut page = None;
         ui.vertical(|ui| {
             // Music Player Block
             Frame::new()
@@ -28,7 +197,7 @@ impl ActiveActions {
                         ui.add_space(12.0);
                         // Album art placeholder
                         Frame::new()
-                            .fill(Color32::from_gray(50))
+                            .fill(self.colors.surface_container_high)
                             .corner_radius(8.0)
                             .show(ui, |ui| {
                                 ui.set_min_width(ui.available_width());
@@ -103,52 +272,17 @@ impl ActiveActions {
                 ui.set_min_width(ui.available_width());
 
                 ui.with_layout(Layout::left_to_right(egui::Align::Center), |ui| {
-                    egui::Frame::new()
-                        .fill(self.colors.surface)
-                        .corner_radius(18.0)
-                        .inner_margin(6.0)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::Button::new(
-                                    RichText::new(CORNERS_OUT)
-                                        .size(20.0)
-                                        .color(self.colors.on_surface),
-                                )
-                                .fill(Color32::TRANSPARENT)
-                                .min_size(Vec2::new(36.0, 36.0)),
-                            );
-                        });
+                    let response = self.action_button(ui, CORNERS_OUT, false);
+                    self.screenshot_controls(ui, response, ScreenshotMode::Region);
 
                     ui.add_space(4.0);
-                    egui::Frame::new()
-                        .fill(self.colors.surface)
-                        .corner_radius(18.0)
-                        .inner_margin(6.0)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::Button::new(
-                                    RichText::new(EYE).size(20.0).color(self.colors.on_surface),
-                                )
-                                .fill(Color32::TRANSPARENT)
-                                .min_size(Vec2::new(36.0, 36.0)),
-                            );
-                        });
+                    let caffeine_active = self.caffeine.is_active();
+                    let eye = if caffeine_active { EYE } else { EYE_CLOSED };
+                    let response = self.action_button(ui, eye, caffeine_active);
+                    self.caffeine_controls(response);
+
                     ui.add_space(4.0);
-                    egui::Frame::new()
-                        .fill(self.colors.surface)
-                        .corner_radius(18.0)
-                        .inner_margin(6.0)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::Button::new(
-                                    RichText::new(SPEAKER_HIGH)
-                                        .size(20.0)
-                                        .color(self.colors.on_surface),
-                                )
-                                .fill(Color32::TRANSPARENT)
-                                .min_size(Vec2::new(36.0, 36.0)),
-                            );
-                        });
+                    self.action_button(ui, SPEAKER_HIGH, false);
                 });
             });
             ui.add_space(8.0);
@@ -156,54 +290,26 @@ impl ActiveActions {
             ui.horizontal(|ui| {
                 ui.set_min_width(ui.available_width());
                 ui.with_layout(Layout::left_to_right(egui::Align::Center), |ui| {
-                    egui::Frame::new()
-                        .fill(self.colors.surface)
-                        .corner_radius(18.0)
-                        .inner_margin(6.0)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::Button::new(
-                                    RichText::new(FOLDER_OPEN)
-                                        .size(20.0)
-                                        .color(self.colors.on_surface),
-                                )
-                                .fill(Color32::TRANSPARENT)
-                                .min_size(Vec2::new(36.0, 36.0)),
-                            );
-                        });
+                    if self
+                        .action_button(ui, FOLDER_OPEN, false)
+                        .on_hover_text("Files")
+                        .clicked()
+                    {
+                        page = Some(DetailPage::Places);
+                    }
 
                     ui.add_space(4.0);
-                    egui::Frame::new()
-                        .fill(self.colors.surface)
-                        .corner_radius(18.0)
-                        .inner_margin(6.0)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::Button::new(
-                                    RichText::new(DOWNLOAD_SIMPLE)
-                                        .size(20.0)
-                                        .color(self.colors.on_surface),
-                                )
-                                .fill(Color32::TRANSPARENT)
-                                .min_size(Vec2::new(36.0, 36.0)),
-                            );
-                        });
+                    if self
+                        .action_button(ui, DOWNLOAD_SIMPLE, false)
+                        .on_hover_text("Downloads")
+                        .clicked()
+                    {
+                        page = Some(DetailPage::Downloads);
+                    }
+
                     ui.add_space(4.0);
-                    egui::Frame::new()
-                        .fill(self.colors.surface)
-                        .corner_radius(18.0)
-                        .inner_margin(6.0)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::Button::new(
-                                    RichText::new(IMAGES_SQUARE)
-                                        .size(20.0)
-                                        .color(self.colors.on_surface),
-                                )
-                                .fill(Color32::TRANSPARENT)
-                                .min_size(Vec2::new(36.0, 36.0)),
-                            );
-                        });
+                    let response = self.action_button(ui, IMAGES_SQUARE, false);
+                    self.screenshot_controls(ui, response, ScreenshotMode::Screen);
                 });
 
                 // Frame::new()
@@ -216,5 +322,6 @@ impl ActiveActions {
                 //     });
             });
         });
+        page
     }
 }
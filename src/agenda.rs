@@ -0,0 +1,153 @@
+use eframe::egui;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::config::CalendarConfig;
+use crate::ics::{self, Calendar, Event};
+use crate::local_time::{self, LocalTime};
+use crate::notify::{self, Urgency};
+use crate::task::BackgroundTask;
+
+/// Events from the configured `.ics` files: which days have any, what's
+/// left of today, and reminders before they start
+pub struct Agenda {
+    config: CalendarConfig,
+    calendars: Vec<Calendar>,
+    loading: Option<BackgroundTask<(Vec<Calendar>, Vec<String>)>>,
+    last_load: Option<Instant>,
+    /// Days with events in the range last asked for, and that range
+    marked: HashSet<i64>,
+    marked_range: Option<(i64, i64)>,
+    /// Events from the start of today to the end of tomorrow, so reminders
+    /// work across midnight
+    upcoming: Vec<Event>,
+    upcoming_day: Option<i64>,
+    /// (start, summary) of events already reminded about
+    notified: HashSet<(i64, String)>,
+}
+
+impl Agenda {
+    pub fn new(config: CalendarConfig) -> Self {
+        Self {
+            config,
+            calendars: Vec::new(),
+            loading: None,
+            last_load: None,
+            marked: HashSet::new(),
+            marked_range: None,
+            upcoming: Vec::new(),
+            upcoming_day: None,
+            notified: HashSet::new(),
+        }
+    }
+
+    /// Reloads the files now and then and sends due reminders
+    pub fn tick(&mut self, ctx: &egui::Context) {
+        if self.config.paths.is_empty() {
+            return;
+        }
+
+        if let Some((calendars, errors)) = self.loading.as_ref().and_then(BackgroundTask::poll) {
+            self.loading = None;
+            for error in errors {
+                eprintln!("Failed to read calendar: {}", error);
+            }
+            self.calendars = calendars;
+            self.marked_range = None;
+            self.upcoming_day = None;
+        }
+        let refresh = Duration::from_secs(self.config.refresh_minutes.max(1) * 60);
+        if self.loading.is_none() && self.last_load.is_none_or(|last| last.elapsed() >= refresh) {
+            self.last_load = Some(Instant::now());
+            let paths = self.config.paths.clone();
+            self.loading = Some(BackgroundTask::spawn(ctx, move || ics::load(&paths)));
+        }
+
+        let now = LocalTime::now();
+        if self.upcoming_day != Some(now.days()) {
+            self.upcoming_day = Some(now.days());
+            let from = local_time::local_to_unix(now.days() * 86400);
+            let to = local_time::local_to_unix((now.days() + 2) * 86400);
+            self.upcoming = self.events_between(from, to);
+        }
+        self.remind(ctx, now.timestamp);
+    }
+
+    fn remind(&mut self, ctx: &egui::Context, now: i64) {
+        if self.config.notify_minutes == 0 {
+            return;
+        }
+        let lead = self.config.notify_minutes as i64 * 60;
+        let mut next_check = None;
+        for event in self.upcoming.iter().filter(|event| !event.all_day) {
+            let remind_at = event.start - lead;
+            if now < remind_at {
+                next_check = Some(next_check.map_or(remind_at, |at: i64| at.min(remind_at)));
+                continue;
+            }
+            if now >= event.start || !self.notified.insert((event.start, event.summary.clone())) {
+                continue;
+            }
+            let starts = LocalTime::from_unix(event.start).format("%H:%M");
+            let body = match &event.location {
+                Some(location) => format!("{} · {}", starts, location),
+                None => starts,
+            };
+            notify::send(
+                &event.summary,
+                &body,
+                Some("x-office-calendar"),
+                Urgency::Normal,
+            );
+        }
+        if let Some(at) = next_check {
+            ctx.request_repaint_after(Duration::from_secs((at - now).max(1) as u64));
+        }
+    }
+
+    fn events_between(&self, from: i64, to: i64) -> Vec<Event> {
+        let mut events: Vec<Event> = self
+            .calendars
+            .iter()
+            .flat_map(|calendar| calendar.events_between(from, to))
+            .collect();
+        events.sort_by_key(|event| (event.start, event.end));
+        events
+    }
+
+    /// Local days between `first` and `last` (days since 1970-01-01) that
+    /// have an event
+    pub fn marked_days(&mut self, first: i64, last: i64) -> &HashSet<i64> {
+        if self.marked_range != Some((first, last)) {
+            self.marked_range = Some((first, last));
+            let from = local_time::local_to_unix(first * 86400);
+            let to = local_time::local_to_unix((last + 1) * 86400);
+            self.marked = self
+                .events_between(from, to)
+                .iter()
+                .flat_map(|event| {
+                    let start = LocalTime::from_unix(event.start).days();
+                    // The end is exclusive, midnight belongs to the day before
+                    let end = LocalTime::from_unix((event.end - 1).max(event.start)).days();
+                    start.max(first)..=end.min(last)
+                })
+                .collect();
+        }
+        &self.marked
+    }
+
+    /// What's left of today: all-day events and ones that haven't ended
+    pub fn today(&self, now: &LocalTime) -> impl Iterator<Item = &Event> {
+        let tomorrow = local_time::local_to_unix((now.days() + 1) * 86400);
+        let timestamp = now.timestamp;
+        self.upcoming
+            .iter()
+            .filter(move |event| event.start < tomorrow && event.end > timestamp)
+    }
+
+    /// The next timed event starting later today
+    pub fn next(&self, now: &LocalTime) -> Option<&Event> {
+        self.today(now)
+            .find(|event| !event.all_day && event.start > now.timestamp)
+    }
+}
@@ -0,0 +1,376 @@
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::command_runner::CommandRunner;
+
+/// An application currently playing audio (a PulseAudio sink input)
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaybackStream {
+    pub index: u32,
+    pub app_name: String,
+    pub icon_name: Option<String>,
+    pub media_title: String,
+    /// Average volume over all channels, in percent
+    pub volume: f32,
+    pub muted: bool,
+}
+
+/// Whether a device plays (sink) or records (source) audio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    Sink,
+    Source,
+}
+
+impl DeviceKind {
+    /// The pactl object name, as in `list sinks` or `get-default-source`
+    fn noun(self) -> &'static str {
+        match self {
+            DeviceKind::Sink => "sink",
+            DeviceKind::Source => "source",
+        }
+    }
+
+    /// The pactl name for streams attached to this kind of device
+    fn stream_noun(self) -> &'static str {
+        match self {
+            DeviceKind::Sink => "sink-input",
+            DeviceKind::Source => "source-output",
+        }
+    }
+}
+
+/// An output or input device, e.g. headphones or an HDMI port
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioDevice {
+    pub kind: DeviceKind,
+    /// The pactl name used to address the device
+    pub name: String,
+    /// Human readable name, e.g. "Built-in Audio Analog Stereo"
+    pub description: String,
+    pub is_default: bool,
+}
+
+/// Audio control through `pactl`, which talks to PulseAudio and to PipeWire
+/// via pipewire-pulse alike. Cheap to clone.
+#[derive(Clone)]
+pub struct AudioBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl AudioBackend {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    pub fn playback_streams(&self) -> Result<Vec<PlaybackStream>, String> {
+        let output = self
+            .runner
+            .run("pactl", &["-f", "json", "list", "sink-inputs"])?;
+        parse_sink_inputs(&output)
+    }
+
+    pub fn set_stream_volume(&self, index: u32, percent: f32) -> Result<(), String> {
+        let index = index.to_string();
+        let volume = format!("{:.0}%", percent);
+        self.runner
+            .run("pactl", &["set-sink-input-volume", &index, &volume])
+            .map(|_| ())
+    }
+
+    pub fn set_stream_mute(&self, index: u32, muted: bool) -> Result<(), String> {
+        let index = index.to_string();
+        self.runner
+            .run("pactl", &["set-sink-input-mute", &index, bool_arg(muted)])
+            .map(|_| ())
+    }
+
+    /// Volume (percent) and mute state of the default sink
+    pub fn sink_volume(&self) -> Result<(f32, bool), String> {
+        let volume = self
+            .runner
+            .run("pactl", &["get-sink-volume", "@DEFAULT_SINK@"])?;
+        let mute = self
+            .runner
+            .run("pactl", &["get-sink-mute", "@DEFAULT_SINK@"])?;
+        Ok((parse_volume_line(&volume), parse_mute_line(&mute)))
+    }
+
+    pub fn set_sink_volume(&self, percent: f32) -> Result<(), String> {
+        let volume = format!("{:.0}%", percent);
+        self.runner
+            .run("pactl", &["set-sink-volume", "@DEFAULT_SINK@", &volume])
+            .map(|_| ())
+    }
+
+    pub fn set_sink_mute(&self, muted: bool) -> Result<(), String> {
+        self.runner
+            .run(
+                "pactl",
+                &["set-sink-mute", "@DEFAULT_SINK@", bool_arg(muted)],
+            )
+            .map(|_| ())
+    }
+
+    /// Volume (percent) and mute state of the default source
+    pub fn source_volume(&self) -> Result<(f32, bool), String> {
+        let volume = self
+            .runner
+            .run("pactl", &["get-source-volume", "@DEFAULT_SOURCE@"])?;
+        let mute = self
+            .runner
+            .run("pactl", &["get-source-mute", "@DEFAULT_SOURCE@"])?;
+        Ok((parse_volume_line(&volume), parse_mute_line(&mute)))
+    }
+
+    pub fn set_source_volume(&self, percent: f32) -> Result<(), String> {
+        let volume = format!("{:.0}%", percent);
+        self.runner
+            .run("pactl", &["set-source-volume", "@DEFAULT_SOURCE@", &volume])
+            .map(|_| ())
+    }
+
+    pub fn set_source_mute(&self, muted: bool) -> Result<(), String> {
+        self.runner
+            .run(
+                "pactl",
+                &["set-source-mute", "@DEFAULT_SOURCE@", bool_arg(muted)],
+            )
+            .map(|_| ())
+    }
+
+    /// Every sink or source, with the current default marked. Monitor sources
+    /// are left out, they aren't something you'd pick as a microphone.
+    pub fn devices(&self, kind: DeviceKind) -> Result<Vec<AudioDevice>, String> {
+        let plural = format!("{}s", kind.noun());
+        let output = self.runner.run("pactl", &["-f", "json", "list", &plural])?;
+        let default = self
+            .runner
+            .run("pactl", &[&format!("get-default-{}", kind.noun())])?;
+        parse_devices(&output, kind, default.trim())
+    }
+
+    pub fn set_default_device(&self, kind: DeviceKind, name: &str) -> Result<(), String> {
+        self.runner
+            .run("pactl", &[&format!("set-default-{}", kind.noun()), name])
+            .map(|_| ())
+    }
+
+    /// Moves every stream currently attached to a device of `kind` over to
+    /// device `name`. Streams that refuse to move are skipped.
+    pub fn move_streams(&self, kind: DeviceKind, name: &str) -> Result<(), String> {
+        let plural = format!("{}s", kind.stream_noun());
+        let output = self.runner.run("pactl", &["-f", "json", "list", &plural])?;
+        let move_command = format!("move-{}", kind.stream_noun());
+        for index in parse_stream_indices(&output)? {
+            if let Err(e) = self
+                .runner
+                .run("pactl", &[&move_command, &index.to_string(), name])
+            {
+                eprintln!("Failed to move {} {}: {}", kind.stream_noun(), index, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bool_arg(value: bool) -> &'static str {
+    if value { "1" } else { "0" }
+}
+
+/// Parses `pactl -f json list sink-inputs`
+pub fn parse_sink_inputs(json: &str) -> Result<Vec<PlaybackStream>, String> {
+    let entries: Vec<Value> =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse sink inputs: {}", e))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let index = entry.get("index")?.as_u64()? as u32;
+            let property = |key: &str| {
+                entry
+                    .get("properties")
+                    .and_then(|props| props.get(key))
+                    .and_then(Value::as_str)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+
+            let binary = property("application.process.binary");
+            Some(PlaybackStream {
+                index,
+                app_name: property("application.name")
+                    .or_else(|| binary.clone())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                icon_name: property("application.icon_name").or(binary),
+                media_title: property("media.title")
+                    .or_else(|| property("media.name"))
+                    .unwrap_or_default(),
+                volume: parse_volume(entry.get("volume")),
+                muted: entry.get("mute").and_then(Value::as_bool).unwrap_or(false),
+            })
+        })
+        .collect())
+}
+
+/// Averages the `value_percent` of every channel in a pactl volume object
+pub fn parse_volume(volume: Option<&Value>) -> f32 {
+    let Some(channels) = volume.and_then(Value::as_object) else {
+        return 0.0;
+    };
+
+    let percents: Vec<f32> = channels
+        .values()
+        .filter_map(|channel| channel.get("value_percent")?.as_str())
+        .filter_map(|percent| percent.trim().trim_end_matches('%').parse().ok())
+        .collect();
+
+    if percents.is_empty() {
+        0.0
+    } else {
+        percents.iter().sum::<f32>() / percents.len() as f32
+    }
+}
+
+/// Averages the percentages in `pactl get-*-volume` output, e.g.
+/// `Volume: front-left: 39321 /  60% / -13.31 dB,   front-right: ...`
+pub fn parse_volume_line(output: &str) -> f32 {
+    let percents: Vec<f32> = output
+        .split('/')
+        .filter_map(|part| part.trim().strip_suffix('%')?.trim().parse().ok())
+        .collect();
+
+    if percents.is_empty() {
+        0.0
+    } else {
+        percents.iter().sum::<f32>() / percents.len() as f32
+    }
+}
+
+/// Parses `pactl get-*-mute` output, `Mute: yes` or `Mute: no`
+pub fn parse_mute_line(output: &str) -> bool {
+    output.trim().strip_prefix("Mute:").map(str::trim) == Some("yes")
+}
+
+/// Parses `pactl -f json list sinks` or `list sources`
+pub fn parse_devices(
+    json: &str,
+    kind: DeviceKind,
+    default_name: &str,
+) -> Result<Vec<AudioDevice>, String> {
+    let entries: Vec<Value> =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse devices: {}", e))?;
+
+    Ok(entries
+        .iter()
+        .filter_map(|entry| {
+            let name = entry.get("name")?.as_str()?.to_string();
+            let is_monitor = entry
+                .get("monitor_of_sink")
+                .and_then(Value::as_str)
+                .is_some_and(|sink| !sink.is_empty() && sink != "n/a");
+            if kind == DeviceKind::Source && (is_monitor || name.ends_with(".monitor")) {
+                return None;
+            }
+
+            let description = entry
+                .get("description")
+                .and_then(Value::as_str)
+                .filter(|description| !description.is_empty())
+                .unwrap_or(&name)
+                .to_string();
+            Some(AudioDevice {
+                kind,
+                is_default: name == default_name,
+                name,
+                description,
+            })
+        })
+        .collect())
+}
+
+/// The indexes from `pactl -f json list sink-inputs` or `list source-outputs`
+pub fn parse_stream_indices(json: &str) -> Result<Vec<u32>, String> {
+    let entries: Vec<Value> =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse streams: {}", e))?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| Some(entry.get("index")?.as_u64()? as u32))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        std::fs::read_to_string(format!(
+            "{}/tests/fixtures/audio/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn sinks_fall_back_to_name() {
+        let sinks = parse_devices(
+            &fixture("sinks.json"),
+            DeviceKind::Sink,
+            "bluez_output.AC_80_0A_12_34_56.1",
+        )
+        .unwrap();
+        let descriptions: Vec<&str> = sinks.iter().map(|s| s.description.as_str()).collect();
+        assert_eq!(
+            descriptions,
+            [
+                "Built-in Audio Analog Stereo",
+                "WH-1000XM4",
+                "alsa_output.pci-0000_01_00.1.hdmi-stereo",
+            ]
+        );
+        assert!(!sinks[0].is_default);
+        assert!(sinks[1].is_default);
+    }
+
+    #[test]
+    fn sources_skip_monitors() {
+        let sources = parse_devices(&fixture("sources.json"), DeviceKind::Source, "").unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].name, "alsa_input.pci-0000_00_1f.3.analog-stereo");
+    }
+
+    #[test]
+    fn sink_inputs() {
+        let streams = parse_sink_inputs(&fixture("sink_inputs.json")).unwrap();
+        assert_eq!(
+            streams,
+            [
+                PlaybackStream {
+                    index: 131,
+                    app_name: "Firefox".to_string(),
+                    icon_name: Some("firefox".to_string()),
+                    media_title: "Lo-fi beats".to_string(),
+                    volume: 50.0,
+                    muted: false,
+                },
+                PlaybackStream {
+                    index: 140,
+                    app_name: "mpv".to_string(),
+                    icon_name: Some("mpv".to_string()),
+                    media_title: "episode.mkv".to_string(),
+                    volume: 100.0,
+                    muted: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn volume_and_mute_lines() {
+        let output = "Volume: front-left: 39322 /  60% / -13.31 dB,   front-right: 45875 /  70% / -9.29 dB\n        balance 0.00\n";
+        assert_eq!(parse_volume_line(output), 65.0);
+        assert!(parse_mute_line("Mute: yes\n"));
+        assert!(!parse_mute_line("Mute: no\n"));
+    }
+}
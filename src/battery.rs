@@ -0,0 +1,292 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChargeState {
+    Charging,
+    Discharging,
+    Full,
+    /// Plugged in but held back, e.g. by a charge limit
+    NotCharging,
+    Unknown,
+}
+
+impl ChargeState {
+    fn from_sysfs(status: &str) -> Self {
+        match status {
+            "Charging" => ChargeState::Charging,
+            "Discharging" => ChargeState::Discharging,
+            "Full" => ChargeState::Full,
+            "Not charging" => ChargeState::NotCharging,
+            _ => ChargeState::Unknown,
+        }
+    }
+}
+
+/// One battery from `/sys/class/power_supply`, or several added together
+#[derive(Clone, Debug, PartialEq)]
+pub struct Battery {
+    pub name: String,
+    /// Charge level, 0-100
+    pub capacity: u8,
+    pub state: ChargeState,
+    /// Remaining and full charge plus the current draw, in whatever unit the
+    /// driver reports (µWh/µW or µAh/µA). Only their ratios are used.
+    pub now: Option<f64>,
+    pub full: Option<f64>,
+    pub rate: Option<f64>,
+}
+
+impl Battery {
+    /// Time until empty when discharging or until full when charging, if the
+    /// driver reports enough to work it out
+    pub fn time_remaining(&self) -> Option<Duration> {
+        let rate = self.rate.filter(|rate| *rate > 0.0)?;
+        let hours = match self.state {
+            ChargeState::Discharging => self.now? / rate,
+            ChargeState::Charging => (self.full? - self.now?).max(0.0) / rate,
+            _ => return None,
+        };
+        Some(Duration::from_secs_f64(hours * 3600.0))
+    }
+
+    /// Adds laptops with two batteries up into one
+    pub fn combine(batteries: &[Battery]) -> Option<Battery> {
+        let (first, rest) = batteries.split_first()?;
+        if rest.is_empty() {
+            return Some(first.clone());
+        }
+
+        let sum = |field: fn(&Battery) -> Option<f64>| -> Option<f64> {
+            batteries.iter().map(field).sum::<Option<f64>>()
+        };
+        let now = sum(|b| b.now);
+        let full = sum(|b| b.full);
+        let capacity = match (now, full) {
+            (Some(now), Some(full)) if full > 0.0 => (now / full * 100.0).round() as u8,
+            _ => {
+                let total: u32 = batteries.iter().map(|b| b.capacity as u32).sum();
+                (total / batteries.len() as u32) as u8
+            }
+        };
+        // Any battery charging or draining decides what the whole pack does
+        let state = [ChargeState::Charging, ChargeState::Discharging]
+            .into_iter()
+            .find(|state| batteries.iter().any(|b| b.state == *state))
+            .unwrap_or(first.state);
+
+        Some(Battery {
+            name: first.name.clone(),
+            capacity,
+            state,
+            now,
+            full,
+            rate: sum(|b| b.rate),
+        })
+    }
+}
+
+fn read_value(dir: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dir.join(file))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+fn read_number(dir: &Path, file: &str) -> Option<f64> {
+    read_value(dir, file)?.parse().ok()
+}
+
+/// Reads one power supply directory, or `None` if it isn't a system battery.
+/// Mice and headsets also show up as batteries but with scope "Device".
+pub fn read_battery(dir: &Path) -> Option<Battery> {
+    if read_value(dir, "type")? != "Battery"
+        || read_value(dir, "scope").as_deref() == Some("Device")
+    {
+        return None;
+    }
+    if read_value(dir, "present").as_deref() == Some("0") {
+        return None;
+    }
+
+    // Drivers report either energy (µWh) or charge (µAh), never both
+    let (now, full, rate) = if dir.join("energy_now").exists() {
+        ("energy_now", "energy_full", "power_now")
+    } else {
+        ("charge_now", "charge_full", "current_now")
+    };
+    let now = read_number(dir, now);
+    let full = read_number(dir, full);
+    let capacity = read_number(dir, "capacity")
+        .or_else(|| Some(now? / full? * 100.0))?
+        .clamp(0.0, 100.0) as u8;
+
+    Some(Battery {
+        name: dir.file_name()?.to_string_lossy().into_owned(),
+        capacity,
+        state: ChargeState::from_sysfs(&read_value(dir, "status").unwrap_or_default()),
+        now,
+        full,
+        // Some drivers report a negative current while discharging
+        rate: read_number(dir, rate).map(f64::abs),
+    })
+}
+
+/// All system batteries under `root` (normally `/sys/class/power_supply`),
+/// sorted by name
+pub fn read_batteries(root: &Path) -> Vec<Battery> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut batteries: Vec<Battery> = entries
+        .flatten()
+        .filter_map(|entry| read_battery(&entry.path()))
+        .collect();
+    batteries.sort_by(|a, b| a.name.cmp(&b.name));
+    batteries
+}
+
+/// Whether a mains adapter under `root` is plugged in, `None` without one
+pub fn ac_online(root: &Path) -> Option<bool> {
+    let entries = fs::read_dir(root).ok()?;
+    let mut found = None;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if read_value(&dir, "type").as_deref() == Some("Mains") {
+            let online = read_value(&dir, "online").as_deref() == Some("1");
+            found = Some(found.unwrap_or(false) || online);
+        }
+    }
+    found
+}
+
+/// "2 h 13 min" / "40 min"
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{} min", minutes),
+        (hours, 0) => format!("{} h", hours),
+        (hours, minutes) => format!("{} h {} min", hours, minutes),
+    }
+}
+
+/// Decides when to warn about a draining battery. Each threshold fires once
+/// per discharge; plugging in re-arms them.
+pub struct LowBatteryAlert {
+    /// Highest first
+    thresholds: Vec<u8>,
+    /// The lowest threshold already warned about
+    warned: Option<u8>,
+}
+
+impl LowBatteryAlert {
+    pub fn new(thresholds: &[u8]) -> Self {
+        let mut thresholds = thresholds.to_vec();
+        thresholds.sort_unstable_by(|a, b| b.cmp(a));
+        thresholds.dedup();
+        Self {
+            thresholds,
+            warned: None,
+        }
+    }
+
+    /// Returns the threshold to warn about now, if one was just crossed
+    pub fn check(&mut self, battery: &Battery) -> Option<u8> {
+        if battery.state != ChargeState::Discharging {
+            self.warned = None;
+            return None;
+        }
+
+        let crossed = self
+            .thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| battery.capacity <= *threshold)
+            .min()?;
+        if self.warned.is_some_and(|warned| warned <= crossed) {
+            return None;
+        }
+        self.warned = Some(crossed);
+        Some(crossed)
+    }
+
+    /// The lowest threshold counts as critical
+    pub fn is_critical(&self, threshold: u8) -> bool {
+        self.thresholds.last() == Some(&threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A copy of `/sys/class/power_supply` from a laptop with two batteries,
+    /// one of them removed, and a wireless mouse
+    fn root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/power_supply")
+    }
+
+    #[test]
+    fn reads_system_batteries() {
+        let batteries = read_batteries(&root());
+        assert_eq!(
+            batteries,
+            [
+                Battery {
+                    name: "BAT0".to_string(),
+                    capacity: 50,
+                    state: ChargeState::Discharging,
+                    now: Some(20_000_000.0),
+                    full: Some(40_000_000.0),
+                    rate: Some(10_000_000.0),
+                },
+                // No capacity file, and a negative current
+                Battery {
+                    name: "BAT1".to_string(),
+                    capacity: 75,
+                    state: ChargeState::NotCharging,
+                    now: Some(3_000_000.0),
+                    full: Some(4_000_000.0),
+                    rate: Some(500_000.0),
+                },
+            ]
+        );
+        assert_eq!(ac_online(&root()), Some(false));
+    }
+
+    #[test]
+    fn missing_root() {
+        let root = root().join("missing");
+        assert!(read_batteries(&root).is_empty());
+        assert_eq!(ac_online(&root), None);
+    }
+
+    #[test]
+    fn combined_pack() {
+        let battery = Battery::combine(&read_batteries(&root())).unwrap();
+        assert_eq!(battery.capacity, 52);
+        assert_eq!(battery.state, ChargeState::Discharging);
+        assert_eq!(
+            battery.time_remaining().map(format_duration).as_deref(),
+            Some("2 h 11 min")
+        );
+    }
+
+    #[test]
+    fn alert_fires_once_per_threshold() {
+        let mut alert = LowBatteryAlert::new(&[5, 20, 10]);
+        let mut battery = read_batteries(&root()).remove(0);
+        let mut at = |capacity, state| {
+            battery.capacity = capacity;
+            battery.state = state;
+            alert.check(&battery)
+        };
+        assert_eq!(at(25, ChargeState::Discharging), None);
+        assert_eq!(at(20, ChargeState::Discharging), Some(20));
+        assert_eq!(at(18, ChargeState::Discharging), None);
+        assert_eq!(at(4, ChargeState::Discharging), Some(5));
+        assert_eq!(at(4, ChargeState::Charging), None);
+        assert_eq!(at(9, ChargeState::Discharging), Some(10));
+    }
+}
@@ -0,0 +1,129 @@
+use eframe::egui;
+use egui::RichText;
+use egui_phosphor::regular::*;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::battery::{self, Battery, ChargeState, LowBatteryAlert};
+use crate::config::BatteryConfig;
+use crate::notify::{self, Urgency};
+use crate::palette::Palette;
+
+/// sysfs is cheap to read, but the numbers only move every few seconds
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Charge level and time remaining, shown on machines with a battery
+pub struct BatterySection {
+    root: PathBuf,
+    battery: Option<Battery>,
+    ac_online: Option<bool>,
+    alert: LowBatteryAlert,
+    last_update: Option<Instant>,
+}
+
+impl BatterySection {
+    pub fn new(config: &BatteryConfig) -> Self {
+        Self {
+            root: PathBuf::from(shellexpand::tilde(&config.root).as_ref()),
+            battery: None,
+            ac_online: None,
+            alert: LowBatteryAlert::new(&config.low_thresholds),
+            last_update: None,
+        }
+    }
+
+    /// Re-reads the batteries and warns if the charge just crossed a threshold
+    pub fn refresh(&mut self) {
+        self.battery = Battery::combine(&battery::read_batteries(&self.root));
+        self.ac_online = battery::ac_online(&self.root);
+        self.last_update = Some(Instant::now());
+
+        let Some(battery) = &self.battery else {
+            return;
+        };
+        if let Some(threshold) = self.alert.check(battery) {
+            let urgency = if self.alert.is_critical(threshold) {
+                Urgency::Critical
+            } else {
+                Urgency::Normal
+            };
+            let mut body = format!("{}% remaining", battery.capacity);
+            if let Some(left) = battery.time_remaining() {
+                body.push_str(&format!(", about {}", battery::format_duration(left)));
+            }
+            notify::send("Battery low", &body, Some("battery-caution"), urgency);
+        }
+    }
+
+    /// Keeps the battery state current even while the section isn't drawn,
+    /// so low-battery warnings still fire on a detail page
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        if self
+            .last_update
+            .is_none_or(|last| last.elapsed() >= UPDATE_INTERVAL)
+        {
+            self.refresh();
+        }
+        ctx.request_repaint_after(UPDATE_INTERVAL);
+    }
+
+    /// Draws nothing on machines without a battery
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        let Some(battery) = &self.battery else {
+            return;
+        };
+
+        let icon = match (battery.state, battery.capacity) {
+            (ChargeState::Charging, _) => BATTERY_CHARGING,
+            (_, 0..=10) => BATTERY_WARNING,
+            (_, 11..=30) => BATTERY_LOW,
+            (_, 31..=60) => BATTERY_MEDIUM,
+            (_, 61..=90) => BATTERY_HIGH,
+            _ => BATTERY_FULL,
+        };
+        let status = match battery.state {
+            ChargeState::Charging => match battery.time_remaining() {
+                Some(left) => format!("Charging · {} until full", battery::format_duration(left)),
+                None => "Charging".to_string(),
+            },
+            ChargeState::Discharging => match battery.time_remaining() {
+                Some(left) => format!("{} left", battery::format_duration(left)),
+                None => "On battery".to_string(),
+            },
+            ChargeState::Full => "Fully charged".to_string(),
+            ChargeState::NotCharging => "Plugged in, not charging".to_string(),
+            ChargeState::Unknown if self.ac_online == Some(true) => "Plugged in".to_string(),
+            ChargeState::Unknown => "On battery".to_string(),
+        };
+        let low = battery.state == ChargeState::Discharging && battery.capacity <= 10;
+        let level_color = if low { colors.error } else { colors.primary };
+
+        egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(18.0)
+            .inner_margin(12.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(icon).size(24.0).color(level_color));
+                    ui.vertical(|ui| {
+                        ui.label(
+                            RichText::new(format!("Battery · {}%", battery.capacity))
+                                .color(colors.on_surface),
+                        );
+                        ui.label(
+                            RichText::new(status)
+                                .size(12.0)
+                                .color(colors.on_surface.gamma_multiply(0.7)),
+                        );
+                    });
+                });
+                ui.add_space(4.0);
+                ui.add(
+                    egui::ProgressBar::new(battery.capacity as f32 / 100.0)
+                        .desired_height(4.0)
+                        .fill(level_color),
+                );
+            });
+    }
+}
@@ -0,0 +1,535 @@
+use eframe::egui;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use crate::command_runner::CommandRunner;
+
+/// How long a discovery run lasts
+pub const DISCOVERY_SECONDS: u64 = 10;
+
+/// Gives up on pairing when bluetoothctl has said nothing useful for this
+/// long, e.g. the device went out of range
+const PAIR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A device BlueZ knows about, paired or just seen during discovery
+#[derive(Clone, Debug, PartialEq)]
+pub struct BluetoothDevice {
+    pub address: String,
+    pub name: String,
+    /// freedesktop icon name BlueZ derives from the device class, e.g.
+    /// "audio-headset"; empty when unknown
+    pub icon: String,
+    pub paired: bool,
+    pub trusted: bool,
+    pub connected: bool,
+}
+
+/// Device management through `bluetoothctl`. Cheap to clone, so the slow
+/// calls can run on a background thread.
+#[derive(Clone)]
+pub struct BluetoothBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl BluetoothBackend {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// All known devices, connected first, then paired, then by name
+    pub fn devices(&self) -> Result<Vec<BluetoothDevice>, String> {
+        let list = self.runner.run("bluetoothctl", &["devices"])?;
+        let mut devices = Vec::new();
+        for (address, name) in parse_device_list(&list) {
+            let info = self.runner.run("bluetoothctl", &["info", &address])?;
+            devices.push(parse_info(&address, &name, &info));
+        }
+        devices.sort_by(|a, b| {
+            b.connected
+                .cmp(&a.connected)
+                .then(b.paired.cmp(&a.paired))
+                .then(a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        Ok(devices)
+    }
+
+    /// Whether the default controller is powered on
+    pub fn powered(&self) -> Result<bool, String> {
+        let output = self.runner.run("bluetoothctl", &["show"])?;
+        Ok(output.lines().any(|line| line.trim() == "Powered: yes"))
+    }
+
+    pub fn set_powered(&self, on: bool) -> Result<(), String> {
+        self.run(&["power", if on { "on" } else { "off" }])
+    }
+
+    /// (address, name) of each connected device
+    pub fn connected_devices(&self) -> Result<Vec<(String, String)>, String> {
+        let output = self.runner.run("bluetoothctl", &["devices", "Connected"])?;
+        Ok(parse_device_list(&output))
+    }
+
+    /// Scans for nearby devices, blocking for [`DISCOVERY_SECONDS`]
+    pub fn discover(&self) -> Result<(), String> {
+        let timeout = DISCOVERY_SECONDS.to_string();
+        self.run(&["--timeout", &timeout, "scan", "on"])
+    }
+
+    pub fn connect(&self, address: &str) -> Result<(), String> {
+        self.run(&["connect", address])
+    }
+
+    pub fn disconnect(&self, address: &str) -> Result<(), String> {
+        self.run(&["disconnect", address])
+    }
+
+    pub fn set_trusted(&self, address: &str, trusted: bool) -> Result<(), String> {
+        self.run(&[if trusted { "trust" } else { "untrust" }, address])
+    }
+
+    /// Removes the pairing so the device has to be paired again
+    pub fn forget(&self, address: &str) -> Result<(), String> {
+        self.run(&["remove", address])
+    }
+
+    /// bluetoothctl exits with 0 on most failures, so its output is checked
+    /// for an error line as well
+    fn run(&self, args: &[&str]) -> Result<(), String> {
+        let output = self.runner.run("bluetoothctl", args)?;
+        match find_failure(&output) {
+            Some(message) => Err(message),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `Device XX:XX:XX:XX:XX:XX Name` lines from `bluetoothctl devices`, as
+/// (address, name) pairs
+pub fn parse_device_list(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix("Device ")?;
+            let (address, name) = rest.split_once(' ').unwrap_or((rest, rest));
+            Some((address.to_string(), name.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Builds a device from `bluetoothctl info ADDRESS`
+pub fn parse_info(address: &str, name: &str, output: &str) -> BluetoothDevice {
+    let mut device = BluetoothDevice {
+        address: address.to_string(),
+        name: name.to_string(),
+        icon: String::new(),
+        paired: false,
+        trusted: false,
+        connected: false,
+    };
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Alias" if !value.is_empty() => device.name = value.to_string(),
+            "Icon" => device.icon = value.to_string(),
+            "Paired" => device.paired = value == "yes",
+            "Trusted" => device.trusted = value == "yes",
+            "Connected" => device.connected = value == "yes",
+            _ => {}
+        }
+    }
+    device
+}
+
+/// The first error line in bluetoothctl output, like "Failed to connect:
+/// org.bluez.Error.Failed" or "Device XX not available"
+pub fn find_failure(output: &str) -> Option<String> {
+    output
+        .lines()
+        .map(|line| strip_ansi(line).trim().to_string())
+        .find(|line| line.starts_with("Failed") || line.ends_with("not available"))
+}
+
+/// Removes terminal colour codes and readline markers bluetoothctl writes
+/// around its prompt
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // Skip to the end of the escape sequence
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            '\x01' | '\x02' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Something that happened while pairing
+#[derive(Clone, Debug, PartialEq)]
+pub enum PairEvent {
+    /// The device shows this passkey and wants a yes/no
+    Confirm(String),
+    /// Type this passkey on the device
+    Display(String),
+    Paired,
+    Failed(String),
+}
+
+/// Finds the first pairing event in bluetoothctl's output. Returns it with
+/// the length of text it used up, or `None` if more output is needed.
+pub fn next_pair_event(buffer: &str) -> Option<(PairEvent, usize)> {
+    let mut found: Vec<(usize, PairEvent, usize)> = Vec::new();
+
+    for (pattern, confirm) in [("Confirm passkey ", true), ("Passkey: ", false)] {
+        if let Some(start) = buffer.find(pattern) {
+            let rest = &buffer[start + pattern.len()..];
+            let digits = rest.chars().take_while(char::is_ascii_digit).count();
+            // Wait until something follows the number so it's complete
+            if digits > 0 && digits < rest.len() {
+                let passkey = rest[..digits].to_string();
+                let event = if confirm {
+                    PairEvent::Confirm(passkey)
+                } else {
+                    PairEvent::Display(passkey)
+                };
+                found.push((start, event, start + pattern.len() + digits));
+            }
+        }
+    }
+
+    if let Some(start) = buffer.find("Pairing successful") {
+        found.push((start, PairEvent::Paired, start + "Pairing successful".len()));
+    }
+
+    // Complete lines only, so the whole reason is there. The interactive
+    // session never exits, so "Device ... not available" and friends are
+    // all we get to hear.
+    let mut start = 0;
+    while let Some(end) = buffer[start..].find('\n').map(|end| start + end) {
+        if let Some(line) = last_output_line(&buffer[start..end])
+            && find_failure(&line).is_some()
+        {
+            let reason = match line.strip_prefix("Failed to pair: ") {
+                Some(reason) => reason.trim().to_string(),
+                None => line,
+            };
+            found.push((start, PairEvent::Failed(reason), end));
+            break;
+        }
+        start = end + 1;
+    }
+
+    found
+        .into_iter()
+        .min_by_key(|(start, _, _)| *start)
+        .map(|(_, event, used)| (event, used))
+}
+
+/// Feeds bluetoothctl's output to `send` as pairing events until `send`
+/// returns false or the output ends. Output that ends without a result, say
+/// bluetoothctl crashed or the device vanished, is sent as a failure with the
+/// last thing bluetoothctl printed.
+fn read_pair_events(mut output: impl Read, mut send: impl FnMut(PairEvent) -> bool) {
+    // Passkey questions aren't followed by a newline, so read raw chunks
+    // rather than lines
+    let mut buffer = String::new();
+    let mut last_line = None;
+    let mut chunk = [0u8; 1024];
+    while let Ok(read) = output.read(&mut chunk) {
+        if read == 0 {
+            break;
+        }
+        let text = strip_ansi(&String::from_utf8_lossy(&chunk[..read]));
+        if let Some(line) = last_output_line(&text) {
+            last_line = Some(line);
+        }
+        buffer.push_str(&text);
+        while let Some((event, used)) = next_pair_event(&buffer) {
+            buffer.drain(..used);
+            let finished = matches!(event, PairEvent::Paired | PairEvent::Failed(_));
+            if !send(event) || finished {
+                return;
+            }
+        }
+    }
+    let reason = last_line.unwrap_or_else(|| "bluetoothctl exited".to_string());
+    send(PairEvent::Failed(reason));
+}
+
+/// The last line of `text` with something in it, without the
+/// `[bluetooth]#` prompt in front
+fn last_output_line(text: &str) -> Option<String> {
+    text.lines()
+        .rev()
+        .map(|line| {
+            let line = line.trim();
+            match line.find("]#") {
+                Some(end) if line.starts_with('[') => line[end + 2..].trim(),
+                _ => line,
+            }
+        })
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// An interactive bluetoothctl session pairing one device. Pairing needs an
+/// agent to answer passkey questions, which `bluetoothctl pair` on its own
+/// can't do.
+pub struct PairingSession {
+    child: Child,
+    stdin: ChildStdin,
+    events: PairEvents,
+}
+
+/// Events from the reader thread, failing the pairing once none came for
+/// `timeout`
+struct PairEvents {
+    receiver: Receiver<PairEvent>,
+    timeout: Duration,
+    last_event: Instant,
+    timed_out: bool,
+}
+
+impl PairEvents {
+    fn new(receiver: Receiver<PairEvent>, timeout: Duration) -> Self {
+        Self {
+            receiver,
+            timeout,
+            last_event: Instant::now(),
+            timed_out: false,
+        }
+    }
+
+    fn poll(&mut self) -> Option<PairEvent> {
+        if let Ok(event) = self.receiver.try_recv() {
+            self.last_event = Instant::now();
+            return Some(event);
+        }
+        if !self.timed_out && self.last_event.elapsed() >= self.timeout {
+            self.timed_out = true;
+            return Some(PairEvent::Failed(
+                "Timed out waiting for the device".to_string(),
+            ));
+        }
+        None
+    }
+}
+
+impl PairingSession {
+    /// Starts pairing with `address`. `ctx` is repainted for every event.
+    pub fn start(ctx: &egui::Context, address: &str) -> Result<Self, String> {
+        let mut child = Command::new("bluetoothctl")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to run bluetoothctl: {}", e))?;
+        let mut stdin = child.stdin.take().ok_or("bluetoothctl has no stdin")?;
+        let stdout = child.stdout.take().ok_or("bluetoothctl has no stdout")?;
+
+        writeln!(
+            stdin,
+            "agent KeyboardDisplay\ndefault-agent\npair {}",
+            address
+        )
+        .map_err(|e| format!("Failed to write to bluetoothctl: {}", e))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            read_pair_events(stdout, |event| {
+                let sent = sender.send(event).is_ok();
+                ctx.request_repaint();
+                sent
+            });
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            events: PairEvents::new(receiver, PAIR_TIMEOUT),
+        })
+    }
+
+    /// The next event, if any. Call it now and then even without repaints
+    /// from bluetoothctl so the timeout can fire.
+    pub fn poll(&mut self) -> Option<PairEvent> {
+        self.events.poll()
+    }
+
+    /// Answers a [`PairEvent::Confirm`] question
+    pub fn confirm(&mut self, accept: bool) {
+        if let Err(e) = writeln!(self.stdin, "{}", if accept { "yes" } else { "no" }) {
+            eprintln!("Failed to answer bluetoothctl: {}", e);
+        }
+    }
+}
+
+impl Drop for PairingSession {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    fn events(output: &str) -> Vec<PairEvent> {
+        let mut events = Vec::new();
+        read_pair_events(output.as_bytes(), |event| {
+            events.push(event);
+            true
+        });
+        events
+    }
+
+    #[test]
+    fn paired() {
+        let output = "Agent registered\n[bluetooth]# Attempting to pair with AC:80:0A:12:34:56\n\
+            [agent] Confirm passkey 123456 (yes/no): [CHG] Device AC:80:0A:12:34:56 Paired: yes\n\
+            Pairing successful\n";
+        assert_eq!(
+            events(output),
+            [PairEvent::Confirm("123456".to_string()), PairEvent::Paired]
+        );
+    }
+
+    #[test]
+    fn failed_with_reason() {
+        let output = "Attempting to pair with AC:80:0A:12:34:56\n\
+            Failed to pair: org.bluez.Error.AuthenticationFailed\n";
+        assert_eq!(
+            events(output),
+            [PairEvent::Failed(
+                "org.bluez.Error.AuthenticationFailed".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn exit_without_result_is_a_failure() {
+        let output =
+            "Agent registered\n[bluetooth]# Device AC:80:0A:12:34:56 not available\n[bluetooth]# ";
+        assert_eq!(
+            events(output),
+            [PairEvent::Failed(
+                "Device AC:80:0A:12:34:56 not available".to_string()
+            )]
+        );
+        assert_eq!(
+            events(""),
+            [PairEvent::Failed("bluetoothctl exited".to_string())]
+        );
+    }
+
+    /// Runs the reader on a stream that stays open, like a real session
+    fn open_session_events(output: &str) -> Receiver<PairEvent> {
+        use std::os::unix::net::UnixStream;
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        writer.write_all(output.as_bytes()).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            read_pair_events(reader, |event| sender.send(event).is_ok());
+            drop(writer);
+        });
+        receiver
+    }
+
+    #[test]
+    fn unavailable_device_fails_while_session_is_open() {
+        let events = open_session_events(
+            "Agent registered\n[bluetooth]# Default agent request successful\n\
+             [bluetooth]# Device AC:80:0A:12:34:56 not available\n[bluetooth]# ",
+        );
+        assert_eq!(
+            events.recv_timeout(Duration::from_secs(5)),
+            Ok(PairEvent::Failed(
+                "Device AC:80:0A:12:34:56 not available".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn passkey_then_failure_while_session_is_open() {
+        let events = open_session_events(
+            "[agent] Passkey: 004211\n[bluetooth]# Failed to pair: org.bluez.Error.AuthenticationCanceled\n",
+        );
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            events.recv_timeout(timeout),
+            Ok(PairEvent::Display("004211".to_string()))
+        );
+        assert_eq!(
+            events.recv_timeout(timeout),
+            Ok(PairEvent::Failed(
+                "org.bluez.Error.AuthenticationCanceled".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn quiet_session_times_out_once() {
+        let (sender, receiver) = mpsc::channel();
+        let mut events = PairEvents::new(receiver, Duration::from_millis(50));
+        sender
+            .send(PairEvent::Confirm("123456".to_string()))
+            .unwrap();
+        assert_eq!(
+            events.poll(),
+            Some(PairEvent::Confirm("123456".to_string()))
+        );
+        assert_eq!(events.poll(), None);
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(
+            events.poll(),
+            Some(PairEvent::Failed(
+                "Timed out waiting for the device".to_string()
+            ))
+        );
+        assert_eq!(events.poll(), None);
+    }
+
+    #[test]
+    fn reads_power_and_connected_devices() {
+        let runner = FakeRunner::default()
+            .with(
+                "bluetoothctl show",
+                "Controller 00:1A:7D:DA:71:13 (public)\n\tName: laptop\n\tPowered: yes\n\tDiscoverable: no\n",
+            )
+            .with(
+                "bluetoothctl devices Connected",
+                "Device AC:80:0A:12:34:56 WH-1000XM4\n",
+            )
+            .with(
+                "bluetoothctl power off",
+                "Failed to set power off: org.bluez.Error.Busy\n",
+            );
+        let backend = BluetoothBackend::new(Arc::new(runner));
+        assert!(backend.powered().unwrap());
+        assert_eq!(
+            backend.connected_devices().unwrap(),
+            [("AC:80:0A:12:34:56".to_string(), "WH-1000XM4".to_string())]
+        );
+        assert_eq!(
+            backend.set_powered(false).unwrap_err(),
+            "Failed to set power off: org.bluez.Error.Busy"
+        );
+    }
+}
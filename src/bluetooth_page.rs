@@ -0,0 +1,436 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::time::{Duration, Instant};
+
+use crate::bluetooth::{
+    BluetoothBackend, BluetoothDevice, DISCOVERY_SECONDS, PairEvent, PairingSession,
+};
+use crate::palette::Palette;
+use crate::task::BackgroundTask;
+
+/// How often the list is re-read while discovering, so new devices pop in
+const DISCOVERY_REFRESH: Duration = Duration::from_secs(2);
+
+/// Something to do with a device, picked from its row
+#[derive(Clone, Copy)]
+enum DeviceAction {
+    Connect,
+    Disconnect,
+    Pair,
+    Trust(bool),
+    Forget,
+}
+
+/// A pairing in progress and what the device last asked for
+struct Pairing {
+    session: PairingSession,
+    name: String,
+    question: Option<PairEvent>,
+}
+
+/// Detail page listing paired and nearby Bluetooth devices, opened from the
+/// Bluetooth row
+pub struct BluetoothPage {
+    backend: BluetoothBackend,
+    devices: Vec<BluetoothDevice>,
+    refresh: Option<BackgroundTask<Result<Vec<BluetoothDevice>, String>>>,
+    last_refresh: Option<Instant>,
+    /// When the running discovery started
+    discovery: Option<(Instant, BackgroundTask<Result<(), String>>)>,
+    /// Address of the device an action is running for
+    busy: Option<(String, BackgroundTask<Result<(), String>>)>,
+    pairing: Option<Pairing>,
+    error: Option<String>,
+}
+
+impl BluetoothPage {
+    pub fn new(backend: BluetoothBackend) -> Self {
+        Self {
+            backend,
+            devices: Vec::new(),
+            refresh: None,
+            last_refresh: None,
+            discovery: None,
+            busy: None,
+            pairing: None,
+            error: None,
+        }
+    }
+
+    /// Re-reads the device list unless that's already happening
+    pub fn refresh(&mut self, ctx: &egui::Context) {
+        if self.refresh.is_some() {
+            return;
+        }
+        let backend = self.backend.clone();
+        self.refresh = Some(BackgroundTask::spawn(ctx, move || backend.devices()));
+        self.last_refresh = Some(Instant::now());
+    }
+
+    fn discover(&mut self, ctx: &egui::Context) {
+        if self.discovery.is_some() {
+            return;
+        }
+        let backend = self.backend.clone();
+        let task = BackgroundTask::spawn(ctx, move || backend.discover());
+        self.discovery = Some((Instant::now(), task));
+    }
+
+    fn run_action(&mut self, ctx: &egui::Context, device: &BluetoothDevice, action: DeviceAction) {
+        if let DeviceAction::Pair = action {
+            match PairingSession::start(ctx, &device.address) {
+                Ok(session) => {
+                    self.pairing = Some(Pairing {
+                        session,
+                        name: device.name.clone(),
+                        question: None,
+                    })
+                }
+                Err(e) => self.error = Some(e),
+            }
+            return;
+        }
+
+        let backend = self.backend.clone();
+        let address = device.address.clone();
+        let task = BackgroundTask::spawn(ctx, move || match action {
+            DeviceAction::Connect => backend.connect(&address),
+            DeviceAction::Disconnect => backend.disconnect(&address),
+            DeviceAction::Trust(trusted) => backend.set_trusted(&address, trusted),
+            DeviceAction::Forget => backend.forget(&address),
+            DeviceAction::Pair => unreachable!("pairing runs in its own session"),
+        });
+        self.busy = Some((device.address.clone(), task));
+    }
+
+    /// Picks up finished background work and pairing progress
+    fn poll_tasks(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.refresh.as_ref().and_then(BackgroundTask::poll) {
+            self.refresh = None;
+            match result {
+                Ok(devices) => self.devices = devices,
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        if let Some((started, task)) = &self.discovery {
+            if let Some(result) = task.poll() {
+                self.discovery = None;
+                if let Err(e) = result {
+                    self.error = Some(e);
+                }
+                self.refresh(ctx);
+            } else {
+                if started.elapsed() >= DISCOVERY_REFRESH
+                    && self
+                        .last_refresh
+                        .is_none_or(|last| last.elapsed() >= DISCOVERY_REFRESH)
+                {
+                    self.refresh(ctx);
+                }
+                // Keep the progress bar moving
+                ctx.request_repaint_after(Duration::from_millis(250));
+            }
+        }
+
+        if let Some(result) = self.busy.as_ref().and_then(|(_, task)| task.poll()) {
+            self.busy = None;
+            if let Err(e) = result {
+                self.error = Some(e);
+            }
+            self.refresh(ctx);
+        }
+
+        let mut finished = false;
+        if let Some(pairing) = &mut self.pairing {
+            while let Some(event) = pairing.session.poll() {
+                match event {
+                    PairEvent::Paired => finished = true,
+                    PairEvent::Failed(reason) => {
+                        self.error = Some(format!("Failed to pair: {}", reason));
+                        finished = true;
+                    }
+                    question => pairing.question = Some(question),
+                }
+            }
+            // Check the timeout even when bluetoothctl is quiet
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+        if finished {
+            self.pairing = None;
+            self.refresh(ctx);
+        }
+    }
+
+    /// Returns true when the user asked to go back to the main page
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) -> bool {
+        self.poll_tasks(ui.ctx());
+        let mut back = false;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    Button::new(
+                        RichText::new(ARROW_LEFT)
+                            .size(18.0)
+                            .color(colors.on_surface),
+                    )
+                    .fill(Color32::TRANSPARENT),
+                )
+                .on_hover_text("Back")
+                .clicked()
+            {
+                back = true;
+            }
+            ui.label(
+                RichText::new("Bluetooth")
+                    .size(18.0)
+                    .color(colors.on_surface),
+            );
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if self.discovery.is_none()
+                    && ui
+                        .add(
+                            Button::new(
+                                RichText::new(MAGNIFYING_GLASS)
+                                    .size(18.0)
+                                    .color(colors.on_surface),
+                            )
+                            .fill(Color32::TRANSPARENT),
+                        )
+                        .on_hover_text("Look for devices")
+                        .clicked()
+                {
+                    self.discover(ui.ctx());
+                }
+            });
+        });
+
+        if let Some((started, _)) = &self.discovery {
+            let progress = started.elapsed().as_secs_f32() / DISCOVERY_SECONDS as f32;
+            ui.add(
+                egui::ProgressBar::new(progress.min(1.0))
+                    .desired_height(4.0)
+                    .fill(colors.primary),
+            );
+            ui.label(
+                RichText::new("Looking for devices…")
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+        }
+        ui.add_space(8.0);
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).size(12.0).color(colors.error));
+        }
+
+        let mut picked = None;
+        let busy = self.busy.as_ref().map(|(address, _)| address.as_str());
+        for (title, paired) in [("Paired devices", true), ("Nearby devices", false)] {
+            let devices: Vec<&BluetoothDevice> = self
+                .devices
+                .iter()
+                .filter(|device| device.paired == paired)
+                .collect();
+            if devices.is_empty() {
+                continue;
+            }
+            ui.label(
+                RichText::new(title)
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+            for device in devices {
+                let is_busy = busy == Some(device.address.as_str());
+                if let Some(action) = device_row(ui, device, is_busy, colors) {
+                    picked = Some((device.clone(), action));
+                }
+            }
+            ui.add_space(4.0);
+        }
+        if self.devices.is_empty() && self.refresh.is_some() {
+            ui.spinner();
+        }
+
+        if let Some((device, action)) = picked {
+            self.error = None;
+            self.run_action(ui.ctx(), &device, action);
+        }
+
+        self.show_pairing_dialog(ui.ctx(), colors);
+        back
+    }
+
+    fn show_pairing_dialog(&mut self, ctx: &egui::Context, colors: &Palette) {
+        let Some(pairing) = &mut self.pairing else {
+            return;
+        };
+
+        let mut answer = None;
+        let mut cancel = false;
+        let modal = egui::Modal::new(egui::Id::new("bluetooth_pairing"))
+            .frame(
+                egui::Frame::new()
+                    .fill(colors.surface_container_high)
+                    .corner_radius(18.0)
+                    .inner_margin(16.0),
+            )
+            .show(ctx, |ui| {
+                ui.set_width(240.0);
+                ui.label(
+                    RichText::new(format!("Pairing with {}", pairing.name))
+                        .size(16.0)
+                        .color(colors.on_surface),
+                );
+                ui.add_space(8.0);
+                match &pairing.question {
+                    Some(PairEvent::Confirm(passkey)) => {
+                        ui.label(
+                            RichText::new("Check that the device shows this code")
+                                .size(12.0)
+                                .color(colors.on_surface),
+                        );
+                        ui.label(RichText::new(passkey).size(24.0).color(colors.primary));
+                        ui.horizontal(|ui| {
+                            if ui.button("Pair").clicked() {
+                                answer = Some(true);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                answer = Some(false);
+                            }
+                        });
+                    }
+                    Some(PairEvent::Display(passkey)) => {
+                        ui.label(
+                            RichText::new("Type this code on the device, then press Enter")
+                                .size(12.0)
+                                .color(colors.on_surface),
+                        );
+                        ui.label(RichText::new(passkey).size(24.0).color(colors.primary));
+                        cancel = ui.button("Cancel").clicked();
+                    }
+                    _ => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(
+                                RichText::new("Waiting for the device…")
+                                    .size(12.0)
+                                    .color(colors.on_surface),
+                            );
+                        });
+                        cancel = ui.button("Cancel").clicked();
+                    }
+                }
+            });
+
+        if let Some(accept) = answer {
+            pairing.session.confirm(accept);
+            pairing.question = None;
+            if !accept {
+                self.pairing = None;
+            }
+        } else if cancel || modal.should_close() {
+            self.pairing = None;
+        }
+    }
+}
+
+/// Phosphor glyph for a BlueZ device icon name
+fn device_icon(icon: &str) -> &'static str {
+    match icon {
+        "audio-headset" | "audio-headphones" => HEADPHONES,
+        "audio-card" => SPEAKER_HIGH,
+        "input-keyboard" => KEYBOARD,
+        "input-mouse" | "input-tablet" => MOUSE,
+        "input-gaming" => GAME_CONTROLLER,
+        "phone" => DEVICE_MOBILE,
+        "computer" => LAPTOP,
+        "printer" => PRINTER,
+        _ => BLUETOOTH,
+    }
+}
+
+/// Draws one device and returns the action the user picked, if any
+fn device_row(
+    ui: &mut egui::Ui,
+    device: &BluetoothDevice,
+    busy: bool,
+    colors: &Palette,
+) -> Option<DeviceAction> {
+    let (fill, text_color) = if device.connected {
+        (colors.secondary_container, colors.on_secondary_container)
+    } else {
+        (colors.surface, colors.on_surface)
+    };
+
+    let mut action = None;
+    egui::Frame::new()
+        .fill(fill)
+        .corner_radius(12.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(device_icon(&device.icon))
+                        .size(20.0)
+                        .color(text_color),
+                );
+                ui.vertical(|ui| {
+                    ui.label(RichText::new(&device.name).color(text_color));
+                    let status = match (device.connected, device.paired, device.trusted) {
+                        (true, _, _) => "Connected",
+                        (false, true, true) => "Paired · Trusted",
+                        (false, true, false) => "Paired",
+                        (false, false, _) => "Not paired",
+                    };
+                    ui.label(
+                        RichText::new(status)
+                            .size(12.0)
+                            .color(text_color.gamma_multiply(0.7)),
+                    );
+                });
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if busy {
+                        ui.spinner();
+                        return;
+                    }
+                    let mut button = |icon: &str, tooltip: &str, picked: DeviceAction| {
+                        if ui
+                            .add(
+                                Button::new(RichText::new(icon).size(16.0).color(text_color))
+                                    .fill(Color32::TRANSPARENT),
+                            )
+                            .on_hover_text(tooltip)
+                            .clicked()
+                        {
+                            action = Some(picked);
+                        }
+                    };
+
+                    if !device.paired {
+                        button(LINK, "Pair", DeviceAction::Pair);
+                        return;
+                    }
+                    button(TRASH, "Forget", DeviceAction::Forget);
+                    if device.trusted {
+                        button(SHIELD_CHECK, "Stop trusting", DeviceAction::Trust(false));
+                    } else {
+                        button(SHIELD, "Trust", DeviceAction::Trust(true));
+                    }
+                    if device.connected {
+                        button(LINK_BREAK, "Disconnect", DeviceAction::Disconnect);
+                    } else {
+                        button(LINK, "Connect", DeviceAction::Connect);
+                    }
+                });
+            });
+        });
+    ui.add_space(4.0);
+
+    action
+}
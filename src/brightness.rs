@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::command_runner::CommandRunner;
+
+/// Backlight control through `brightnessctl`, which works without root via
+/// logind. Cheap to clone.
+#[derive(Clone)]
+pub struct BrightnessBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl BrightnessBackend {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// Current brightness of the first backlight, in percent
+    pub fn brightness(&self) -> Result<f32, String> {
+        let output = self
+            .runner
+            .run("brightnessctl", &["-m", "-c", "backlight"])?;
+        parse_machine_line(&output)
+    }
+
+    pub fn set_brightness(&self, percent: f32) -> Result<(), String> {
+        let value = format!("{:.0}%", percent);
+        self.runner
+            .run("brightnessctl", &["-q", "-c", "backlight", "set", &value])
+            .map(|_| ())
+    }
+}
+
+/// Parses `brightnessctl -m`, lines like
+/// `intel_backlight,backlight,19200,80%,24000`. The rounded percent field is
+/// skipped in favour of current/max.
+pub fn parse_machine_line(output: &str) -> Result<f32, String> {
+    let line = output
+        .lines()
+        .next()
+        .ok_or_else(|| "No backlight found".to_string())?;
+    let fields: Vec<&str> = line.split(',').collect();
+    let (Some(current), Some(max)) = (fields.get(2), fields.get(4)) else {
+        return Err(format!("Unexpected brightnessctl output: {}", line));
+    };
+    let parse = |field: &str| field.trim().parse::<f32>().ok();
+    match (parse(current), parse(max)) {
+        (Some(current), Some(max)) if max > 0.0 => Ok(current / max * 100.0),
+        _ => Err(format!("Unexpected brightnessctl output: {}", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    #[test]
+    fn reads_first_backlight() {
+        let runner = FakeRunner::default().with(
+            "brightnessctl -m -c backlight",
+            "intel_backlight,backlight,6000,25%,24000\nacpi_video0,backlight,7,70%,10\n",
+        );
+        let backend = BrightnessBackend::new(Arc::new(runner));
+        assert_eq!(backend.brightness(), Ok(25.0));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(parse_machine_line("").is_err());
+        assert!(parse_machine_line("intel_backlight,backlight,6000").is_err());
+        assert!(parse_machine_line("intel_backlight,backlight,6000,0%,0").is_err());
+    }
+
+    #[test]
+    fn sets_percent() {
+        let runner =
+            Arc::new(FakeRunner::default().with("brightnessctl -q -c backlight set 40%", ""));
+        BrightnessBackend::new(runner.clone())
+            .set_brightness(40.2)
+            .unwrap();
+        assert_eq!(runner.calls(), ["brightnessctl -q -c backlight set 40%"]);
+    }
+}
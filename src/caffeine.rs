@@ -0,0 +1,131 @@
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::command_runner::CommandRunner;
+use crate::hyprland;
+
+/// How often the other inhibiting windows are looked up
+const CLIENTS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Keeps the screen awake by holding a systemd idle inhibitor for as long as
+/// a `systemd-inhibit` child runs
+pub struct Caffeine {
+    runner: Arc<dyn CommandRunner>,
+    child: Option<Child>,
+    /// When the inhibitor is let go by itself, `None` to hold it until
+    /// switched off
+    until: Option<Instant>,
+    /// Titles of other windows that are keeping the screen awake
+    others: Vec<String>,
+    last_clients_update: Option<Instant>,
+}
+
+impl Caffeine {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            runner,
+            child: None,
+            until: None,
+            others: Vec::new(),
+            last_clients_update: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.child.is_some()
+    }
+
+    /// Holds the inhibitor, for `timeout` or until switched off
+    pub fn start(&mut self, timeout: Option<Duration>) {
+        self.stop();
+        let child = Command::new("systemd-inhibit")
+            .args([
+                "--what=idle",
+                "--who=Action Center",
+                "--why=Caffeine mode",
+                "--mode=block",
+                "sleep",
+                "infinity",
+            ])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            Ok(child) => {
+                self.child = Some(child);
+                self.until = timeout.map(|timeout| Instant::now() + timeout);
+            }
+            Err(e) => eprintln!("Failed to run systemd-inhibit: {}", e),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.until = None;
+    }
+
+    /// Time left before the inhibitor is let go, if it has a timeout
+    pub fn remaining(&self) -> Option<Duration> {
+        self.until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    /// Ends a timed inhibitor and refreshes the list of other inhibitors.
+    /// Returns how soon it wants to be called again.
+    pub fn tick(&mut self) -> Duration {
+        if self.remaining().is_some_and(|left| left.is_zero()) {
+            self.stop();
+        }
+        if let Some(child) = &mut self.child
+            && !matches!(child.try_wait(), Ok(None))
+        {
+            eprintln!("systemd-inhibit exited");
+            self.child = None;
+            self.until = None;
+        }
+
+        if self
+            .last_clients_update
+            .is_none_or(|last| last.elapsed() >= CLIENTS_INTERVAL)
+        {
+            self.last_clients_update = Some(Instant::now());
+            match hyprland::clients(self.runner.as_ref()) {
+                Ok(clients) => {
+                    self.others = clients
+                        .into_iter()
+                        .filter(|client| client.inhibiting_idle)
+                        .map(|client| {
+                            if client.title.is_empty() {
+                                client.class
+                            } else {
+                                client.title
+                            }
+                        })
+                        .collect();
+                }
+                Err(e) => eprintln!("Failed to get clients: {}", e),
+            }
+        }
+
+        match self.remaining() {
+            // Tick the countdown in the tooltip once a second
+            Some(_) => Duration::from_secs(1),
+            None => CLIENTS_INTERVAL,
+        }
+    }
+
+    /// Windows other than ours that keep the screen awake
+    pub fn others(&self) -> &[String] {
+        &self.others
+    }
+}
+
+impl Drop for Caffeine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,276 @@
+use eframe::egui;
+use egui::{Align2, Color32, FontId, RichText, Sense, Vec2};
+use egui_phosphor::regular::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::agenda::Agenda;
+use crate::battery;
+use crate::config::{CalendarConfig, ClockConfig};
+use crate::local_time::{self, LocalTime};
+use crate::palette::Palette;
+
+/// Size of one day in the calendar grid
+const CELL_SIZE: f32 = 30.0;
+
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// Header clock that unfolds into a month calendar when clicked
+pub struct Clock {
+    config: ClockConfig,
+    agenda: Agenda,
+    expanded: bool,
+    /// Month the calendar shows as (year, month), `None` for the current one
+    shown_month: Option<(i32, u32)>,
+}
+
+impl Clock {
+    pub fn new(config: ClockConfig, calendar: CalendarConfig) -> Self {
+        Self {
+            config,
+            agenda: Agenda::new(calendar),
+            expanded: false,
+            shown_month: None,
+        }
+    }
+
+    /// Time until the displayed time next changes, so we can repaint right
+    /// then instead of every frame
+    fn until_next_tick(&self) -> Duration {
+        let period: u128 = if self.config.ticks_seconds() {
+            1_000
+        } else {
+            60_000
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Duration::from_millis((period - now % period) as u64)
+    }
+
+    /// Loads calendar events and sends reminders, even while hidden
+    pub fn tick(&mut self, ctx: &egui::Context) {
+        self.agenda.tick(ctx);
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        let now = LocalTime::now();
+        ui.ctx().request_repaint_after(self.until_next_tick());
+
+        let response = ui
+            .horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.vertical(|ui| {
+                    ui.label(
+                        RichText::new(now.format(&self.config.time_format()))
+                            .size(32.0)
+                            .color(colors.on_surface),
+                    );
+                    let mut subtitle = now.format(&self.config.date_format);
+                    if let Some(next) = self.agenda.next(&now) {
+                        // Round up so it never says "in 0 min"
+                        let left = Duration::from_secs((next.start - now.timestamp) as u64 + 59);
+                        subtitle = format!(
+                            "{} · {} in {}",
+                            subtitle,
+                            next.summary,
+                            battery::format_duration(left)
+                        );
+                    }
+                    ui.add(
+                        egui::Label::new(
+                            RichText::new(subtitle)
+                                .size(12.0)
+                                .color(colors.on_surface.gamma_multiply(0.7)),
+                        )
+                        .truncate(),
+                    );
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.add_space(8.0);
+                    let caret = if self.expanded { CARET_UP } else { CARET_DOWN };
+                    ui.label(RichText::new(caret).size(16.0).color(colors.on_surface));
+                });
+            })
+            .response
+            .interact(Sense::click())
+            .on_hover_text("Show calendar");
+        if response.clicked() {
+            self.expanded = !self.expanded;
+            self.shown_month = None;
+        }
+
+        if self.expanded {
+            ui.add_space(4.0);
+            self.show_calendar(ui, &now, colors);
+            self.show_today(ui, &now, colors);
+        }
+    }
+
+    /// The rest of today's events under the calendar
+    fn show_today(&self, ui: &mut egui::Ui, now: &LocalTime, colors: &Palette) {
+        let events: Vec<_> = self.agenda.today(now).collect();
+        if events.is_empty() {
+            return;
+        }
+        ui.add_space(4.0);
+        for event in events {
+            egui::Frame::new()
+                .fill(colors.surface)
+                .corner_radius(12.0)
+                .inner_margin(8.0)
+                .show(ui, |ui| {
+                    ui.set_min_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(CALENDAR_BLANK)
+                                .size(20.0)
+                                .color(colors.on_surface),
+                        );
+                        ui.vertical(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    RichText::new(&event.summary).color(colors.on_surface),
+                                )
+                                .truncate(),
+                            );
+                            let mut when = if event.all_day {
+                                "All day".to_string()
+                            } else {
+                                format!(
+                                    "{} – {}",
+                                    LocalTime::from_unix(event.start).format("%H:%M"),
+                                    LocalTime::from_unix(event.end).format("%H:%M")
+                                )
+                            };
+                            if let Some(location) = &event.location {
+                                when = format!("{} · {}", when, location);
+                            }
+                            ui.add(
+                                egui::Label::new(
+                                    RichText::new(when)
+                                        .size(12.0)
+                                        .color(colors.on_surface.gamma_multiply(0.7)),
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                });
+            ui.add_space(4.0);
+        }
+    }
+
+    fn show_calendar(&mut self, ui: &mut egui::Ui, now: &LocalTime, colors: &Palette) {
+        let (year, month) = self.shown_month.unwrap_or((now.year, now.month));
+        let first = local_time::days_from_civil(year, month, 1);
+        // Noon on the 15th is safely inside the month in any time zone
+        let title = LocalTime::from_unix(first * 86400 + 14 * 86400 + 43200).format("%B %Y");
+        let dim = colors.on_surface.gamma_multiply(0.5);
+
+        egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(12.0)
+            .inner_margin(8.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(CARET_LEFT)
+                        .on_hover_text("Previous month")
+                        .clicked()
+                    {
+                        self.shown_month = Some(if month == 1 {
+                            (year - 1, 12)
+                        } else {
+                            (year, month - 1)
+                        });
+                    }
+                    let title = ui
+                        .add(
+                            egui::Label::new(
+                                RichText::new(title).strong().color(colors.on_surface),
+                            )
+                            .sense(Sense::click()),
+                        )
+                        .on_hover_text("Back to today");
+                    if title.clicked() {
+                        self.shown_month = None;
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button(CARET_RIGHT).on_hover_text("Next month").clicked() {
+                            self.shown_month = Some(if month == 12 {
+                                (year + 1, 1)
+                            } else {
+                                (year, month + 1)
+                            });
+                        }
+                    });
+                });
+                ui.add_space(4.0);
+
+                // Weeks start on Monday, padded with the neighbouring months
+                let leading = (local_time::weekday_from_days(first) + 6) % 7;
+                let start = first - leading as i64;
+                let rows = (leading + local_time::days_in_month(year, month)).div_ceil(7);
+                let marked = self
+                    .agenda
+                    .marked_days(start, start + rows as i64 * 7 - 1)
+                    .clone();
+
+                egui::Grid::new("calendar")
+                    .spacing(Vec2::ZERO)
+                    .show(ui, |ui| {
+                        if self.config.week_numbers {
+                            cell(ui, "Wk", dim, None, false);
+                        }
+                        for weekday in WEEKDAYS {
+                            cell(ui, weekday, dim, None, false);
+                        }
+                        ui.end_row();
+
+                        for row in 0..rows {
+                            let week_start = start + row as i64 * 7;
+                            if self.config.week_numbers {
+                                let week = local_time::iso_week(week_start).to_string();
+                                cell(ui, &week, dim, None, false);
+                            }
+                            for days in week_start..week_start + 7 {
+                                let (_, day_month, day) = local_time::civil_from_days(days);
+                                let (text_color, fill) = if days == now.days() {
+                                    (colors.on_primary, Some(colors.primary))
+                                } else if day_month == month {
+                                    (colors.on_surface, None)
+                                } else {
+                                    (dim, None)
+                                };
+                                let has_events = marked.contains(&days);
+                                cell(ui, &day.to_string(), text_color, fill, has_events);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+/// One centred calendar cell, on a filled circle when `fill` is set and
+/// with a dot under it for days with events
+fn cell(ui: &mut egui::Ui, text: &str, color: Color32, fill: Option<Color32>, dot: bool) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::splat(CELL_SIZE), Sense::hover());
+    if let Some(fill) = fill {
+        ui.painter()
+            .circle_filled(rect.center(), CELL_SIZE / 2.0 - 2.0, fill);
+    }
+    ui.painter().text(
+        rect.center(),
+        Align2::CENTER_CENTER,
+        text,
+        FontId::proportional(13.0),
+        color,
+    );
+    if dot {
+        let center = rect.center_bottom() - Vec2::new(0.0, 5.0);
+        ui.painter().circle_filled(center, 2.0, color);
+    }
+}
@@ -0,0 +1,121 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs external programs for the system backends. Backends take a runner
+/// instead of calling `Command` directly so they can be driven with canned
+/// output. Runners are shared with background threads for slow commands.
+pub trait CommandRunner: Send + Sync {
+    /// Runs `program` with `args` and returns its stdout. A program that can't
+    /// be started or exits non-zero is an error carrying its stderr.
+    fn run(&self, program: &str, args: &[&str]) -> Result<String, String>;
+
+    /// Like `run`, with `input` written to the program's stdin. For secrets
+    /// that shouldn't show up in the process list.
+    fn run_with_input(&self, program: &str, args: &[&str], input: &str) -> Result<String, String>;
+}
+
+/// Runs commands for real
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<String, String> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        stdout(program, output)
+    }
+
+    fn run_with_input(&self, program: &str, args: &[&str], input: &str) -> Result<String, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        // Dropping stdin afterwards closes it, so the program sees EOF
+        if let Some(mut stdin) = child.stdin.take()
+            && let Err(e) = stdin.write_all(input.as_bytes())
+        {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("Failed to write to {}: {}", program, e));
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        stdout(program, output)
+    }
+}
+
+/// The stdout of a finished program, or its stderr as the error when it
+/// exited non-zero
+fn stdout(program: &str, output: Output) -> Result<String, String> {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if stderr.is_empty() {
+            format!("{} exited with {}", program, output.status)
+        } else {
+            stderr
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Canned output for tests, keyed by the full command line
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeRunner {
+    outputs: std::collections::HashMap<String, Result<String, String>>,
+    calls: parking_lot::Mutex<Vec<String>>,
+    inputs: parking_lot::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl FakeRunner {
+    /// Answers `command` ("program arg arg") with `output`
+    pub fn with(mut self, command: &str, output: &str) -> Self {
+        self.outputs
+            .insert(command.to_string(), Ok(output.to_string()));
+        self
+    }
+
+    /// Makes `command` fail with `error`
+    pub fn failing(mut self, command: &str, error: &str) -> Self {
+        self.outputs
+            .insert(command.to_string(), Err(error.to_string()));
+        self
+    }
+
+    /// Every command run so far, in order
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().clone()
+    }
+
+    /// Everything passed to `run_with_input`, in order
+    pub fn inputs(&self) -> Vec<String> {
+        self.inputs.lock().clone()
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for FakeRunner {
+    fn run(&self, program: &str, args: &[&str]) -> Result<String, String> {
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.calls.lock().push(command.clone());
+        self.outputs
+            .get(&command)
+            .cloned()
+            .unwrap_or_else(|| Err(format!("unexpected command: {}", command)))
+    }
+
+    fn run_with_input(&self, program: &str, args: &[&str], input: &str) -> Result<String, String> {
+        self.inputs.lock().push(input.to_string());
+        self.run(program, args)
+    }
+}
@@ -0,0 +1,345 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+use crate::palette;
+
+const CONFIG_PATH: &str = "~/.config/hermit-action-center/config.json";
+
+/// User configuration, read once at startup. Every section is optional and
+/// falls back to its defaults.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub theme: ThemeConfig,
+    pub battery: BatteryConfig,
+    pub power_profile: PowerProfileConfig,
+    pub session: SessionConfig,
+    pub night_light: NightLightConfig,
+    pub caffeine: CaffeineConfig,
+    pub screenshot: ScreenshotConfig,
+    pub clock: ClockConfig,
+    pub calendar: CalendarConfig,
+    pub resources: ResourcesConfig,
+    pub storage: StorageConfig,
+    pub downloads: DownloadsConfig,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Path to the generated theme file
+    pub path: String,
+    /// "css", "matugen" or "pywal"; detected from the file when unset
+    pub format: Option<String>,
+    /// Which variant to take from files that contain both, "dark" or "light"
+    pub scheme: String,
+    /// Palette role -> theme token overrides, merged over the default mapping
+    pub mapping: HashMap<String, String>,
+    /// Palette role -> opacity (0.0-1.0) for translucent surfaces
+    pub opacity: HashMap<String, f32>,
+    /// Minimum WCAG contrast ratio (1-21) for text and icons, 0 turns the
+    /// adjustment off
+    pub min_contrast: f32,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            path: "./src/colors.css".to_string(),
+            format: None,
+            scheme: "dark".to_string(),
+            mapping: HashMap::new(),
+            opacity: HashMap::new(),
+            min_contrast: 4.5,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BatteryConfig {
+    /// Where power supplies are read from, normally sysfs
+    pub root: String,
+    /// Charge levels (percent) to send a low-battery notification at
+    pub low_thresholds: Vec<u8>,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            root: "/sys/class/power_supply".to_string(),
+            low_thresholds: vec![20, 10, 5],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PowerProfileConfig {
+    /// Firmware profile file used when power-profiles-daemon isn't running
+    pub platform_profile: String,
+}
+
+impl Default for PowerProfileConfig {
+    fn default() -> Self {
+        Self {
+            platform_profile: "/sys/firmware/acpi/platform_profile".to_string(),
+        }
+    }
+}
+
+/// Shell commands for the session power menu. An empty command turns its
+/// button into a no-op.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub lock: String,
+    pub logout: String,
+    pub suspend: String,
+    pub hibernate: String,
+    pub reboot: String,
+    pub shutdown: String,
+    /// Seconds to wait before logging out, restarting or shutting down,
+    /// 0 skips the confirmation
+    pub countdown_seconds: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            lock: "loginctl lock-session".to_string(),
+            logout: "hyprctl dispatch exit".to_string(),
+            suspend: "systemctl suspend".to_string(),
+            hibernate: "systemctl hibernate".to_string(),
+            reboot: "systemctl reboot".to_string(),
+            shutdown: "systemctl poweroff".to_string(),
+            countdown_seconds: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NightLightConfig {
+    /// Long-running command that tints the screen while it runs,
+    /// `{temperature}` is replaced with the slider's value in Kelvin
+    pub command: String,
+    /// Starting colour temperature in Kelvin
+    pub temperature: u32,
+    /// "manual", "fixed" (between `start` and `end`) or "sun" (from sunset
+    /// to sunrise at `latitude`/`longitude`)
+    pub schedule: String,
+    /// "HH:MM" local time
+    pub start: String,
+    pub end: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        Self {
+            command: "hyprsunset --temperature {temperature}".to_string(),
+            temperature: 4000,
+            schedule: "manual".to_string(),
+            start: "20:00".to_string(),
+            end: "07:00".to_string(),
+            latitude: 0.0,
+            longitude: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct CaffeineConfig {
+    /// Minutes a click keeps the screen awake for, 0 until switched off
+    pub timeout_minutes: u64,
+}
+
+impl CaffeineConfig {
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.timeout_minutes > 0).then(|| Duration::from_secs(self.timeout_minutes * 60))
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ScreenshotConfig {
+    /// Where screenshots are saved, created if missing
+    pub directory: String,
+    /// strftime template, `{mode}` becomes region, window or screen
+    pub filename: String,
+    /// How long to wait for the panel to disappear before capturing
+    pub hide_delay_ms: u64,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            directory: "~/Pictures/Screenshots".to_string(),
+            filename: "Screenshot_%Y-%m-%d_%H-%M-%S_{mode}.png".to_string(),
+            hide_delay_ms: 300,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// strftime format for the time, overrides the two switches below
+    pub format: Option<String>,
+    pub twenty_four_hour: bool,
+    pub seconds: bool,
+    /// strftime format for the date under the time
+    pub date_format: String,
+    /// Show ISO week numbers in the calendar
+    pub week_numbers: bool,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            format: None,
+            twenty_four_hour: true,
+            seconds: false,
+            date_format: "%A, %-d %B".to_string(),
+            week_numbers: true,
+        }
+    }
+}
+
+impl ClockConfig {
+    pub fn time_format(&self) -> String {
+        if let Some(format) = &self.format {
+            return format.clone();
+        }
+        match (self.twenty_four_hour, self.seconds) {
+            (true, false) => "%H:%M",
+            (true, true) => "%H:%M:%S",
+            (false, false) => "%-I:%M %p",
+            (false, true) => "%-I:%M:%S %p",
+        }
+        .to_string()
+    }
+
+    /// Whether the time changes every second rather than every minute
+    pub fn ticks_seconds(&self) -> bool {
+        let format = self.time_format();
+        ["%S", "%T", "%r", "%s", "%c"]
+            .iter()
+            .any(|field| format.contains(field))
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CalendarConfig {
+    /// `.ics` files, or directories of them such as vdirsyncer's storage
+    pub paths: Vec<String>,
+    /// Minutes before an event starts to send a notification, 0 for none
+    pub notify_minutes: u32,
+    /// How often the files are read again
+    pub refresh_minutes: u64,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            notify_minutes: 0,
+            refresh_minutes: 5,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ResourcesConfig {
+    /// Where `proc` and `sys` are read from, "/" outside of fixtures
+    pub root: String,
+    /// Time between samples
+    pub interval_ms: u64,
+    /// Samples kept for the sparklines
+    pub history: usize,
+}
+
+impl Default for ResourcesConfig {
+    fn default() -> Self {
+        Self {
+            root: "/".to_string(),
+            interval_ms: 2000,
+            history: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Mount points to show the free space of
+    pub mounts: Vec<String>,
+    /// Notify when a removable drive is plugged in or pulled out
+    pub notify: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            mounts: vec!["/".to_string(), "~".to_string()],
+            notify: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DownloadsConfig {
+    /// Overrides the download directory from user-dirs.dirs
+    pub directory: Option<String>,
+    /// How many of the newest files to list
+    pub limit: usize,
+    /// Run when a file is dragged out of the list, `{path}` becomes the file.
+    /// Something like dragon-drop or ripdrag that offers it to other apps.
+    pub drag_command: String,
+}
+
+impl Default for DownloadsConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            limit: 20,
+            drag_command: "dragon-drop --and-exit --on-top {path}".to_string(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// The effective role -> token mapping with the user's overrides applied
+    pub fn mapping(&self) -> HashMap<String, String> {
+        let mut mapping = palette::default_mapping();
+        mapping.extend(self.mapping.clone());
+        mapping
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let path = shellexpand::tilde(CONFIG_PATH).to_string();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            // No config file is fine, everything has a default
+            Err(_) => return Config::default(),
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to parse {}: {}", path, e);
+                Config::default()
+            }
+        }
+    }
+}
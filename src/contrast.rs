@@ -0,0 +1,327 @@
+use egui::Color32;
+
+use crate::palette::{Palette, Role};
+
+/// Foreground/background role pairs the widgets actually draw. The foreground
+/// is the one that gets adjusted.
+pub const ROLE_PAIRS: &[(Role, Role)] = &[
+    // Labels and inactive quick settings buttons
+    (Role::OnSurface, Role::Surface),
+    (Role::OnSurface, Role::Background),
+    // Album art placeholder icon
+    (Role::OnSurface, Role::SurfaceContainerHigh),
+    // Bluetooth passkey in the pairing dialog. Primary is also a background
+    // below, so it has to be settled before OnPrimary is checked against it.
+    (Role::Primary, Role::SurfaceContainerHigh),
+    // Active quick settings buttons, slider icons over the filled track and
+    // today in the calendar
+    (Role::OnPrimary, Role::Primary),
+    // Connected Wi-Fi, VPN and Bluetooth rows
+    (Role::OnSecondaryContainer, Role::SecondaryContainer),
+    // Error lines under page headers and inside section cards
+    (Role::Error, Role::Background),
+    (Role::Error, Role::Surface),
+    // Warning icon in the session countdown dialog
+    (Role::Error, Role::SurfaceContainerHigh),
+    // Slider icons over the empty track
+    (Role::OnSurface, Role::SurfaceContainerHighest),
+];
+
+/// Black on white, the highest contrast ratio there is
+pub const MAX_RATIO: f32 = 21.0;
+
+/// A role pair that doesn't meet the minimum contrast ratio
+pub struct ContrastFailure {
+    pub foreground: Role,
+    pub background: Role,
+    pub ratio: f32,
+}
+
+/// WCAG 2 relative luminance of an sRGB color
+pub fn relative_luminance(color: Color32) -> f32 {
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    let linear = |channel: u8| {
+        let c = channel as f32 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// WCAG 2 contrast ratio, from 1.0 (identical) to 21.0 (black on white)
+pub fn contrast_ratio(a: Color32, b: Color32) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// What's behind the window depends on the wallpaper, so translucent
+/// backgrounds are checked over both extremes
+const BACKDROPS: [Color32; 2] = [Color32::BLACK, Color32::WHITE];
+
+/// `top` composited over an opaque `bottom`
+fn over(top: Color32, bottom: Color32) -> Color32 {
+    let [r, g, b, a] = top.to_srgba_unmultiplied();
+    let [br, bg, bb, _] = bottom.to_srgba_unmultiplied();
+    let alpha = a as f32 / 255.0;
+    let mix =
+        |top: u8, bottom: u8| (top as f32 * alpha + bottom as f32 * (1.0 - alpha)).round() as u8;
+    Color32::from_rgb(mix(r, br), mix(g, bg), mix(b, bb))
+}
+
+/// The opaque colors `role` can end up as on screen. Everything is drawn on
+/// the panel's background fill, which sits over the backdrop.
+fn on_screen(palette: &Palette, role: Role) -> Vec<Color32> {
+    BACKDROPS
+        .iter()
+        .map(|&backdrop| {
+            let panel = over(palette.get(Role::Background), backdrop);
+            if role == Role::Background {
+                panel
+            } else {
+                over(palette.get(role), panel)
+            }
+        })
+        .collect()
+}
+
+/// Lowest contrast of `fg` against any of `backgrounds`, with `fg` blended
+/// over each of them first
+fn worst_ratio(fg: Color32, backgrounds: &[Color32]) -> f32 {
+    backgrounds
+        .iter()
+        .map(|&bg| contrast_ratio(over(fg, bg), bg))
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Every role pair in `ROLE_PAIRS` that falls below `min_ratio`, using the
+/// colors as they end up on screen after opacity
+pub fn check(palette: &Palette, min_ratio: f32) -> Vec<ContrastFailure> {
+    ROLE_PAIRS
+        .iter()
+        .filter_map(|&(foreground, background)| {
+            let ratio = worst_ratio(palette.get(foreground), &on_screen(palette, background));
+            (ratio < min_ratio).then_some(ContrastFailure {
+                foreground,
+                background,
+                ratio,
+            })
+        })
+        .collect()
+}
+
+/// Adjusts the lightness of each foreground role once, against all the
+/// backgrounds it's drawn on, so fixing one pair can't break another. Run it
+/// after opacity is applied.
+pub fn enforce(palette: &mut Palette, min_ratio: f32) {
+    let mut foregrounds: Vec<Role> = Vec::new();
+    for &(foreground, _) in ROLE_PAIRS {
+        if !foregrounds.contains(&foreground) {
+            foregrounds.push(foreground);
+        }
+    }
+
+    for foreground in foregrounds {
+        let backgrounds: Vec<Color32> = ROLE_PAIRS
+            .iter()
+            .filter(|(fg, _)| *fg == foreground)
+            .flat_map(|&(_, background)| on_screen(palette, background))
+            .collect();
+        let adjusted = adjust_foreground(palette.get(foreground), &backgrounds, min_ratio);
+        palette.set(foreground, adjusted);
+    }
+}
+
+/// Finds the lightness closest to `fg`'s that meets `min_ratio` against
+/// every one of `backgrounds`, keeping hue, saturation and alpha. If no
+/// lightness gets there, returns the one with the best worst-case contrast.
+pub fn adjust_foreground(fg: Color32, backgrounds: &[Color32], min_ratio: f32) -> Color32 {
+    if worst_ratio(fg, backgrounds) >= min_ratio {
+        return fg;
+    }
+
+    let [r, g, b, a] = fg.to_srgba_unmultiplied();
+    let (h, s, l) = rgb_to_hsl(r, g, b);
+    let with_lightness = |l: f32| {
+        let (r, g, b) = hsl_to_rgb(h, s, l);
+        Color32::from_rgba_unmultiplied(r, g, b, a)
+    };
+
+    // Walk outwards from the current lightness, one percent at a time
+    let start = (l * 100.0).round() as i32;
+    for distance in 1..=100 {
+        for level in [start + distance, start - distance] {
+            if !(0..=100).contains(&level) {
+                continue;
+            }
+            let color = with_lightness(level as f32 / 100.0);
+            if worst_ratio(color, backgrounds) >= min_ratio {
+                return color;
+            }
+        }
+    }
+
+    (0..=100)
+        .map(|level| with_lightness(level as f32 / 100.0))
+        .max_by(|x, y| worst_ratio(*x, backgrounds).total_cmp(&worst_ratio(*y, backgrounds)))
+        .unwrap_or(fg)
+}
+
+fn rgb_to_hsl(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    if max == min {
+        return (0.0, 0.0, l);
+    }
+
+    let d = max - min;
+    let s = if l > 0.5 {
+        d / (2.0 - max - min)
+    } else {
+        d / (max + min)
+    };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h / 6.0, s, l)
+}
+
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
+    let to_u8 = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+    if s == 0.0 {
+        return (to_u8(l), to_u8(l), to_u8(l));
+    }
+
+    let q = if l < 0.5 {
+        l * (1.0 + s)
+    } else {
+        l + s - l * s
+    };
+    let p = 2.0 * l - q;
+    let hue = |mut t: f32| {
+        if t < 0.0 {
+            t += 1.0;
+        }
+        if t > 1.0 {
+            t -= 1.0;
+        }
+        if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        }
+    };
+    (
+        to_u8(hue(h + 1.0 / 3.0)),
+        to_u8(hue(h)),
+        to_u8(hue(h - 1.0 / 3.0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> Palette {
+        let mut palette = Palette::default();
+        palette.set(Role::Background, Color32::from_rgb(0x10, 0x14, 0x18));
+        palette.set(Role::Surface, Color32::from_rgb(0x1c, 0x20, 0x24));
+        palette.set(
+            Role::SurfaceContainerHigh,
+            Color32::from_rgb(0x26, 0x2b, 0x2f),
+        );
+        palette.set(
+            Role::SurfaceContainerHighest,
+            Color32::from_rgb(0x31, 0x36, 0x3a),
+        );
+        palette.set(Role::Primary, Color32::from_rgb(0x8d, 0xcf, 0xf2));
+        palette.set(Role::OnPrimary, Color32::from_rgb(0x00, 0x35, 0x48));
+        palette.set(Role::OnSurface, Color32::from_rgb(0xdf, 0xe3, 0xe7));
+        palette.set(
+            Role::SecondaryContainer,
+            Color32::from_rgb(0x33, 0x4b, 0x58),
+        );
+        palette.set(
+            Role::OnSecondaryContainer,
+            Color32::from_rgb(0xcf, 0xe6, 0xf5),
+        );
+        palette.set(Role::Error, Color32::from_rgb(0xff, 0xb4, 0xab));
+        palette
+    }
+
+    #[test]
+    fn ratio_extremes() {
+        assert!((contrast_ratio(Color32::BLACK, Color32::WHITE) - MAX_RATIO).abs() < 0.01);
+        assert!((contrast_ratio(Color32::GRAY, Color32::GRAY) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn good_palette_passes_untouched() {
+        let mut adjusted = palette();
+        assert!(check(&adjusted, 4.5).is_empty());
+        enforce(&mut adjusted, 4.5);
+        assert_eq!(
+            adjusted.get(Role::OnSurface),
+            palette().get(Role::OnSurface)
+        );
+    }
+
+    #[test]
+    fn enforce_meets_every_background_at_once() {
+        // A light and a dark background for the same foreground: fixing one
+        // pair at a time would undo the other
+        let mut palette = palette();
+        palette.set(Role::OnSurface, Color32::from_rgb(0x60, 0x60, 0x60));
+        palette.set(Role::Surface, Color32::from_rgb(0x10, 0x10, 0x10));
+        palette.set(
+            Role::SurfaceContainerHighest,
+            Color32::from_rgb(0x58, 0x58, 0x58),
+        );
+        enforce(&mut palette, 3.0);
+        assert!(check(&palette, 3.0).is_empty());
+    }
+
+    #[test]
+    fn primary_is_settled_before_on_primary() {
+        // A primary too dark for the dialog gets lighter, and on_primary has
+        // to follow it
+        let mut palette = palette();
+        palette.set(Role::Primary, Color32::from_rgb(0x10, 0x30, 0x40));
+        palette.set(Role::OnPrimary, Color32::from_rgb(0x30, 0x50, 0x60));
+        enforce(&mut palette, 4.5);
+        assert!(check(&palette, 4.5).is_empty());
+    }
+
+    #[test]
+    fn translucent_background_is_checked_blended() {
+        let mut palette = palette();
+        // Opaque this passes, but at 30 % a white wallpaper shows through
+        palette.apply_opacity(&[("background".to_string(), 0.3)].into());
+        let failures = check(&palette, 4.5);
+        assert!(
+            failures
+                .iter()
+                .any(|failure| failure.background == Role::Background)
+        );
+    }
+
+    #[test]
+    fn unreachable_ratio_picks_the_best_contrast() {
+        let mid = Color32::from_rgb(0x77, 0x77, 0x77);
+        let adjusted = adjust_foreground(mid, &[mid], MAX_RATIO);
+        assert!(worst_ratio(adjusted, &[mid]) > 4.0);
+    }
+}
@@ -0,0 +1,345 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioBackend, AudioDevice, DeviceKind};
+use crate::palette::Palette;
+use crate::task::{BackgroundTask, WriteQueue};
+
+/// How often the device list is re-read while open, so hotplugged headsets
+/// show up
+const OPEN_INTERVAL: Duration = Duration::from_secs(5);
+/// Closed, only the current output's name is on screen
+const CLOSED_INTERVAL: Duration = Duration::from_secs(30);
+
+type Devices = (Vec<AudioDevice>, Vec<AudioDevice>);
+
+/// Output/input device chooser shown with the Sound slider
+pub struct DevicePicker {
+    backend: AudioBackend,
+    expanded: bool,
+    sinks: Vec<AudioDevice>,
+    sources: Vec<AudioDevice>,
+    /// Also move playing/recording streams when switching the default
+    move_streams: bool,
+    last_update: Option<Instant>,
+    refresh_task: Option<BackgroundTask<Result<Devices, String>>>,
+    /// Default device switches, at most one waiting per kind
+    writes: WriteQueue<DeviceKind>,
+    /// From listing the devices
+    error: Option<String>,
+    /// From the last switch, kept apart so the next listing doesn't hide it
+    select_error: Option<String>,
+}
+
+impl DevicePicker {
+    pub fn new(backend: AudioBackend) -> Self {
+        Self {
+            backend,
+            expanded: false,
+            sinks: Vec::new(),
+            sources: Vec::new(),
+            move_streams: true,
+            last_update: None,
+            refresh_task: None,
+            writes: WriteQueue::default(),
+            error: None,
+            select_error: None,
+        }
+    }
+
+    /// Starts re-reading the devices, unless that's already running
+    fn refresh(&mut self, ctx: &egui::Context) {
+        self.last_update = Some(Instant::now());
+        if self.refresh_task.is_some() {
+            return;
+        }
+        let backend = self.backend.clone();
+        self.refresh_task = Some(BackgroundTask::spawn(ctx, move || {
+            Ok((
+                backend.devices(DeviceKind::Sink)?,
+                backend.devices(DeviceKind::Source)?,
+            ))
+        }));
+    }
+
+    /// Takes in finished listings and switches
+    fn poll_tasks(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.refresh_task.as_ref().and_then(BackgroundTask::poll) {
+            self.refresh_task = None;
+            match result {
+                Ok((sinks, sources)) => {
+                    self.sinks = sinks;
+                    self.sources = sources;
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if let Some(result) = self.writes.poll(ctx) {
+            self.select_error = result.err();
+            // Streams may have moved, or the switch not happened at all
+            self.last_update = None;
+        }
+    }
+
+    /// Switches the default in the background, marking it right away
+    fn select(&mut self, device: &AudioDevice) {
+        // A listing started before the switch would show the old default
+        self.refresh_task = None;
+        let devices = match device.kind {
+            DeviceKind::Sink => &mut self.sinks,
+            DeviceKind::Source => &mut self.sources,
+        };
+        for other in devices.iter_mut() {
+            other.is_default = other.name == device.name;
+        }
+
+        let backend = self.backend.clone();
+        let (kind, name) = (device.kind, device.name.clone());
+        let move_streams = self.move_streams;
+        self.writes.push(kind, move || {
+            backend.set_default_device(kind, &name)?;
+            if move_streams {
+                backend.move_streams(kind, &name)?;
+            }
+            Ok(())
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        self.poll_tasks(ui.ctx());
+        let interval = if self.expanded {
+            OPEN_INTERVAL
+        } else {
+            CLOSED_INTERVAL
+        };
+        if !self.writes.is_busy()
+            && self
+                .last_update
+                .is_none_or(|last| last.elapsed() >= interval)
+        {
+            self.refresh(ui.ctx());
+        }
+        ui.ctx().request_repaint_after(interval);
+
+        let current = self
+            .sinks
+            .iter()
+            .find(|sink| sink.is_default)
+            .map_or("No output device".to_string(), |sink| {
+                sink.description.clone()
+            });
+
+        ui.horizontal(|ui| {
+            ui.add_space(8.0);
+            let caret = if self.expanded {
+                CARET_DOWN
+            } else {
+                CARET_RIGHT
+            };
+            let label = format!("{}  {}  {}", caret, HEADPHONES, current);
+            if ui
+                .add(
+                    Button::new(RichText::new(label).size(12.0).color(colors.on_surface))
+                        .fill(Color32::TRANSPARENT)
+                        .truncate(),
+                )
+                .on_hover_text("Audio devices")
+                .clicked()
+            {
+                self.expanded = !self.expanded;
+                if self.expanded && !self.writes.is_busy() {
+                    self.refresh(ui.ctx());
+                }
+            }
+        });
+
+        if !self.expanded {
+            return;
+        }
+
+        for error in [&self.error, &self.select_error].into_iter().flatten() {
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.label(RichText::new(error).size(12.0).color(colors.error));
+            });
+        }
+
+        let mut selected = None;
+        egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(12.0)
+            .inner_margin(8.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                for (title, devices) in [("Output", &self.sinks), ("Input", &self.sources)] {
+                    ui.label(
+                        RichText::new(title)
+                            .size(12.0)
+                            .color(colors.on_surface.gamma_multiply(0.7)),
+                    );
+                    for device in devices {
+                        let (icon, color) = if device.is_default {
+                            (RADIO_BUTTON, colors.primary)
+                        } else {
+                            (CIRCLE, colors.on_surface)
+                        };
+                        let label = format!("{}  {}", icon, device.description);
+                        let response = ui.add(
+                            Button::new(RichText::new(label).color(color))
+                                .fill(Color32::TRANSPARENT)
+                                .truncate(),
+                        );
+                        if response.clicked() && !device.is_default {
+                            selected = Some(device.clone());
+                        }
+                    }
+                    ui.add_space(4.0);
+                }
+
+                ui.checkbox(
+                    &mut self.move_streams,
+                    RichText::new("Move playing streams to the new device")
+                        .size(12.0)
+                        .color(colors.on_surface),
+                );
+            });
+
+        if let Some(device) = selected {
+            self.select(&device);
+            self.poll_tasks(ui.ctx());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+    use std::sync::Arc;
+
+    fn fixture(name: &str) -> String {
+        std::fs::read_to_string(format!(
+            "{}/tests/fixtures/audio/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    fn runner() -> FakeRunner {
+        FakeRunner::default()
+            .with("pactl -f json list sinks", &fixture("sinks.json"))
+            .with("pactl -f json list sources", &fixture("sources.json"))
+            .with(
+                "pactl get-default-sink",
+                "alsa_output.pci-0000_00_1f.3.analog-stereo\n",
+            )
+            .with(
+                "pactl get-default-source",
+                "alsa_input.pci-0000_00_1f.3.analog-stereo\n",
+            )
+    }
+
+    /// Polls until the listing and any switch are done
+    fn settle(picker: &mut DevicePicker, ctx: &egui::Context) {
+        for _ in 0..200 {
+            picker.poll_tasks(ctx);
+            if picker.refresh_task.is_none() && !picker.writes.is_busy() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("background task didn't finish");
+    }
+
+    fn listed(runner: Arc<FakeRunner>, ctx: &egui::Context) -> DevicePicker {
+        let mut picker = DevicePicker::new(AudioBackend::new(runner));
+        picker.refresh(ctx);
+        settle(&mut picker, ctx);
+        picker
+    }
+
+    #[test]
+    fn refresh_lists_devices_in_the_background() {
+        let ctx = egui::Context::default();
+        let mut picker = DevicePicker::new(AudioBackend::new(Arc::new(runner())));
+        picker.refresh(&ctx);
+        assert!(picker.sinks.is_empty());
+        settle(&mut picker, &ctx);
+        assert_eq!(picker.error, None);
+        assert_eq!(picker.sinks.len(), 3);
+        assert_eq!(picker.sources.len(), 1);
+        assert!(picker.sinks[0].is_default);
+    }
+
+    #[test]
+    fn select_moves_streams() {
+        let ctx = egui::Context::default();
+        let runner = Arc::new(
+            runner()
+                .with(
+                    "pactl set-default-sink bluez_output.AC_80_0A_12_34_56.1",
+                    "",
+                )
+                .with(
+                    "pactl -f json list sink-inputs",
+                    &fixture("sink_inputs.json"),
+                )
+                .with(
+                    "pactl move-sink-input 131 bluez_output.AC_80_0A_12_34_56.1",
+                    "",
+                )
+                .failing(
+                    "pactl move-sink-input 140 bluez_output.AC_80_0A_12_34_56.1",
+                    "Failure: Invalid argument",
+                ),
+        );
+        let mut picker = listed(runner.clone(), &ctx);
+        let headset = picker.sinks[1].clone();
+        picker.select(&headset);
+        // Marked before pactl has answered
+        assert!(picker.sinks[1].is_default && !picker.sinks[0].is_default);
+        settle(&mut picker, &ctx);
+
+        // A stream that refuses to move doesn't fail the switch
+        assert_eq!(picker.select_error, None);
+        let calls = runner.calls();
+        assert!(
+            calls.contains(
+                &"pactl move-sink-input 131 bluez_output.AC_80_0A_12_34_56.1".to_string()
+            )
+        );
+        assert!(
+            calls.contains(
+                &"pactl move-sink-input 140 bluez_output.AC_80_0A_12_34_56.1".to_string()
+            )
+        );
+        // The list is read again afterwards
+        assert_eq!(picker.last_update, None);
+    }
+
+    #[test]
+    fn select_error_survives_refresh() {
+        let ctx = egui::Context::default();
+        let runner = runner().failing(
+            "pactl set-default-sink bluez_output.AC_80_0A_12_34_56.1",
+            "Failure: No such entity",
+        );
+        let mut picker = listed(Arc::new(runner), &ctx);
+        let headset = picker.sinks[1].clone();
+        picker.select(&headset);
+        settle(&mut picker, &ctx);
+        picker.refresh(&ctx);
+        settle(&mut picker, &ctx);
+        assert_eq!(picker.error, None);
+        assert_eq!(
+            picker.select_error.as_deref(),
+            Some("Failure: No such entity")
+        );
+        // The real default comes back
+        assert!(picker.sinks[0].is_default);
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Suffixes browsers give files while they're still downloading
+const PARTIAL_SUFFIXES: &[&str] = &[".part", ".crdownload", ".download"];
+
+/// A file in the download directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Download {
+    pub path: PathBuf,
+    /// File name, without the partial suffix while downloading
+    pub name: String,
+    pub size: u64,
+    pub modified: SystemTime,
+    pub in_progress: bool,
+}
+
+/// The download directory from `user-dirs.dirs`, unless the config names one
+pub fn directory(configured: Option<&str>) -> PathBuf {
+    match configured {
+        Some(dir) => PathBuf::from(shellexpand::tilde(dir).as_ref()),
+        None => crate::xdg_dirs::user_dir("DOWNLOAD", "Downloads"),
+    }
+}
+
+/// The newest `limit` files in `dir`
+pub fn list(dir: &Path, limit: usize) -> Result<Vec<Download>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let files = entries
+        .flatten()
+        .filter_map(|entry| {
+            // Follows symlinks, so linked files show their own size
+            let metadata = fs::metadata(entry.path()).ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.path(), metadata.len(), modified))
+        })
+        .collect();
+    Ok(newest(files, limit))
+}
+
+/// Turns (path, size, modified) into downloads, newest first. Hidden files
+/// are left out, and so is the empty placeholder Firefox creates next to its
+/// `.part` file.
+pub fn newest(files: Vec<(PathBuf, u64, SystemTime)>, limit: usize) -> Vec<Download> {
+    let partial: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|(path, _, _)| {
+            let name = path.file_name()?.to_str()?;
+            name.strip_suffix(".part")
+                .map(|stem| path.with_file_name(stem))
+        })
+        .collect();
+
+    let mut downloads: Vec<Download> = files
+        .into_iter()
+        .filter_map(|(path, size, modified)| {
+            let file_name = path.file_name()?.to_string_lossy().into_owned();
+            if file_name.starts_with('.') || (size == 0 && partial.contains(&path)) {
+                return None;
+            }
+            let stem = PARTIAL_SUFFIXES
+                .iter()
+                .find_map(|suffix| file_name.strip_suffix(suffix))
+                .filter(|stem| !stem.is_empty());
+            Some(Download {
+                name: stem.unwrap_or(&file_name).to_string(),
+                in_progress: stem.is_some(),
+                path,
+                size,
+                modified,
+            })
+        })
+        .collect();
+    downloads.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.name.cmp(&b.name)));
+    downloads.truncate(limit);
+    downloads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    /// A Downloads folder with a finished file, one download from each
+    /// browser still running and some clutter
+    fn listing() -> Vec<(PathBuf, u64, SystemTime)> {
+        [
+            ("report.pdf", 48_213, 100),
+            // Firefox: the data goes to the .part file, next to an empty
+            // placeholder with the final name
+            ("ubuntu.iso", 0, 300),
+            ("ubuntu.iso.part", 1_073_741_824, 310),
+            ("video.mp4.crdownload", 5_242_880, 200),
+            ("archive.zip.download", 1024, 150),
+            (".hidden", 10, 400),
+            ("empty.txt", 0, 20),
+        ]
+        .into_iter()
+        .map(|(name, size, modified)| (Path::new("/dl").join(name), size, at(modified)))
+        .collect()
+    }
+
+    #[test]
+    fn strips_partial_suffixes_and_skips_placeholders() {
+        let downloads = newest(listing(), 10);
+        let summary: Vec<(&str, bool, u64)> = downloads
+            .iter()
+            .map(|d| (d.name.as_str(), d.in_progress, d.size))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("ubuntu.iso", true, 1_073_741_824),
+                ("video.mp4", true, 5_242_880),
+                ("archive.zip", true, 1024),
+                ("report.pdf", false, 48_213),
+                // Empty without a .part next to it is a real file
+                ("empty.txt", false, 0),
+            ]
+        );
+        assert_eq!(downloads[0].path, Path::new("/dl/ubuntu.iso.part"));
+    }
+
+    #[test]
+    fn limit_keeps_the_newest() {
+        let names: Vec<String> = newest(listing(), 2).into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["ubuntu.iso", "video.mp4"]);
+    }
+}
@@ -0,0 +1,269 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::downloads::{self, Download};
+use crate::files::{self, FileActions, FileKind};
+use crate::icons::IconCache;
+use crate::palette::Palette;
+use crate::task::BackgroundTask;
+
+/// Reading one directory is cheap, and in-progress sizes should move
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+enum DownloadAction {
+    Open(PathBuf),
+    ShowInFolder(PathBuf),
+    Drag(PathBuf),
+    Trash(PathBuf),
+}
+
+/// Detail page with the newest files in the download directory, opened from
+/// the download button
+pub struct DownloadsPage {
+    actions: FileActions,
+    directory: PathBuf,
+    limit: usize,
+    downloads: Vec<Download>,
+    icons: IconCache,
+    /// Revealing or trashing, which wait on other processes
+    task: Option<BackgroundTask<Result<(), String>>>,
+    error: Option<String>,
+    last_update: Option<Instant>,
+}
+
+impl DownloadsPage {
+    pub fn new(actions: FileActions, directory: PathBuf, limit: usize) -> Self {
+        Self {
+            actions,
+            directory,
+            limit,
+            downloads: Vec::new(),
+            icons: IconCache::default(),
+            task: None,
+            error: None,
+            last_update: None,
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        self.last_update = Some(Instant::now());
+        match downloads::list(&self.directory, self.limit) {
+            Ok(downloads) => {
+                self.downloads = downloads;
+                if self.task.is_none() {
+                    self.error = None;
+                }
+            }
+            Err(e) => {
+                self.downloads.clear();
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn run(&mut self, ctx: &egui::Context, action: DownloadAction) {
+        let actions = self.actions.clone();
+        let result = match action {
+            DownloadAction::Open(path) => actions.open(&path),
+            DownloadAction::Drag(path) => actions.drag(&path),
+            DownloadAction::ShowInFolder(path) => {
+                self.task = Some(BackgroundTask::spawn(ctx, move || {
+                    actions.show_in_folder(&path)
+                }));
+                Ok(())
+            }
+            DownloadAction::Trash(path) => {
+                self.task = Some(BackgroundTask::spawn(ctx, move || actions.trash(&path)));
+                Ok(())
+            }
+        };
+        self.error = result.err();
+    }
+
+    /// Returns true when the user asked to go back to the main page
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) -> bool {
+        if let Some(result) = self.task.as_ref().and_then(BackgroundTask::poll) {
+            self.task = None;
+            self.error = result.err();
+            self.last_update = None;
+        }
+        if self
+            .last_update
+            .is_none_or(|last| last.elapsed() >= UPDATE_INTERVAL)
+        {
+            self.refresh();
+        }
+        ui.ctx().request_repaint_after(UPDATE_INTERVAL);
+
+        let mut back = false;
+        let mut picked = None;
+        ui.horizontal(|ui| {
+            if icon_button(ui, ARROW_LEFT, colors)
+                .on_hover_text("Back")
+                .clicked()
+            {
+                back = true;
+            }
+            ui.label(
+                RichText::new("Downloads")
+                    .size(18.0)
+                    .color(colors.on_surface),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if icon_button(ui, FOLDER_OPEN, colors)
+                    .on_hover_text(self.directory.display().to_string())
+                    .clicked()
+                {
+                    picked = Some(DownloadAction::Open(self.directory.clone()));
+                }
+                if self.task.is_some() {
+                    ui.spinner();
+                }
+            });
+        });
+        ui.add_space(8.0);
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).size(12.0).color(colors.error));
+        }
+        if self.downloads.is_empty() {
+            ui.label(
+                RichText::new("Nothing downloaded yet")
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+        }
+
+        let now = SystemTime::now();
+        for download in &self.downloads {
+            if let Some(action) = Self::download_row(&mut self.icons, ui, download, now, colors) {
+                picked = Some(action);
+            }
+        }
+        if let Some(action) = picked {
+            self.run(ui.ctx(), action);
+        }
+        back
+    }
+
+    /// One file with its buttons; click opens, dragging hands it to the drag
+    /// command
+    fn download_row(
+        icons: &mut IconCache,
+        ui: &mut egui::Ui,
+        download: &Download,
+        now: SystemTime,
+        colors: &Palette,
+    ) -> Option<DownloadAction> {
+        let mut action = None;
+        let kind = FileKind::from_name(&download.name);
+        egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(12.0)
+            .inner_margin(8.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if download.in_progress {
+                            ui.spinner();
+                        } else {
+                            if icon_button(ui, TRASH, colors)
+                                .on_hover_text("Move to trash")
+                                .clicked()
+                            {
+                                action = Some(DownloadAction::Trash(download.path.clone()));
+                            }
+                            if icon_button(ui, FOLDER, colors)
+                                .on_hover_text("Show in folder")
+                                .clicked()
+                            {
+                                action = Some(DownloadAction::ShowInFolder(download.path.clone()));
+                            }
+                        }
+
+                        // The rest of the row is the part you click and drag
+                        let details = ui
+                            .with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                                icons.show(
+                                    ui,
+                                    Some(kind.icon_name()),
+                                    "mimetypes",
+                                    kind.glyph(),
+                                    24.0,
+                                    colors.on_surface,
+                                );
+                                ui.vertical(|ui| {
+                                    ui.add(
+                                        egui::Label::new(
+                                            RichText::new(&download.name).color(colors.on_surface),
+                                        )
+                                        .truncate(),
+                                    );
+                                    let status = if download.in_progress {
+                                        format!(
+                                            "Downloading · {}",
+                                            files::format_size(download.size)
+                                        )
+                                    } else {
+                                        format!(
+                                            "{} · {}",
+                                            files::format_size(download.size),
+                                            files::format_age(download.modified, now)
+                                        )
+                                    };
+                                    ui.label(
+                                        RichText::new(status)
+                                            .size(12.0)
+                                            .color(colors.on_surface.gamma_multiply(0.7)),
+                                    );
+                                });
+                            })
+                            .response;
+                        if download.in_progress {
+                            return;
+                        }
+                        let response = details
+                            .interact(egui::Sense::click_and_drag())
+                            .on_hover_text("Click to open, drag to drop it somewhere");
+                        if response.clicked() {
+                            action = Some(DownloadAction::Open(download.path.clone()));
+                        }
+                        if response.drag_started() {
+                            action = Some(DownloadAction::Drag(download.path.clone()));
+                        }
+                        response.context_menu(|ui| {
+                            let items = [
+                                (
+                                    ARROW_SQUARE_OUT,
+                                    "Open",
+                                    DownloadAction::Open as fn(PathBuf) -> DownloadAction,
+                                ),
+                                (FOLDER, "Show in folder", DownloadAction::ShowInFolder),
+                                (HAND_GRABBING, "Drag out", DownloadAction::Drag),
+                                (TRASH, "Move to trash", DownloadAction::Trash),
+                            ];
+                            for (icon, label, make) in items {
+                                if ui.button(format!("{}  {}", icon, label)).clicked() {
+                                    action = Some(make(download.path.clone()));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                });
+            });
+        ui.add_space(4.0);
+        action
+    }
+}
+
+fn icon_button(ui: &mut egui::Ui, icon: &str, colors: &Palette) -> egui::Response {
+    ui.add(
+        Button::new(RichText::new(icon).size(18.0).color(colors.on_surface))
+            .fill(Color32::TRANSPARENT),
+    )
+}
@@ -0,0 +1,253 @@
+use egui_phosphor::regular::*;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::command_runner::CommandRunner;
+
+/// Rough kind of a file, from its extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    Image,
+    Audio,
+    Video,
+    Pdf,
+    Archive,
+    Text,
+    Other,
+}
+
+impl FileKind {
+    pub fn from_name(name: &str) -> Self {
+        let extension = match name.rsplit_once('.') {
+            Some((_, extension)) => extension.to_ascii_lowercase(),
+            None => return FileKind::Other,
+        };
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" | "tif" | "tiff" | "avif"
+            | "heic" | "ico" => FileKind::Image,
+            "mp3" | "flac" | "ogg" | "opus" | "wav" | "m4a" | "aac" => FileKind::Audio,
+            "mp4" | "mkv" | "webm" | "mov" | "avi" | "m4v" => FileKind::Video,
+            "pdf" => FileKind::Pdf,
+            "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "zst" | "7z" | "rar" | "deb" | "rpm"
+            | "appimage" | "iso" => FileKind::Archive,
+            "txt" | "md" | "csv" | "json" | "toml" | "yaml" | "yml" | "xml" | "html" | "log"
+            | "odt" | "doc" | "docx" | "rtf" => FileKind::Text,
+            _ => FileKind::Other,
+        }
+    }
+
+    /// Generic icon name from the freedesktop icon spec, under "mimetypes"
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            FileKind::Image => "image-x-generic",
+            FileKind::Audio => "audio-x-generic",
+            FileKind::Video => "video-x-generic",
+            FileKind::Pdf => "application-pdf",
+            FileKind::Archive => "package-x-generic",
+            FileKind::Text => "text-x-generic",
+            FileKind::Other => "application-x-generic",
+        }
+    }
+
+    /// Phosphor glyph for when the icon theme has nothing
+    pub fn glyph(self) -> &'static str {
+        match self {
+            FileKind::Image => FILE_IMAGE,
+            FileKind::Audio => FILE_AUDIO,
+            FileKind::Video => FILE_VIDEO,
+            FileKind::Pdf => FILE_PDF,
+            FileKind::Archive => FILE_ZIP,
+            FileKind::Text => FILE_TEXT,
+            FileKind::Other => FILE,
+        }
+    }
+}
+
+/// Bytes as "812 B" / "14 KiB" / "3.2 MiB" / "1.4 GiB" / "1.8 TiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// How long ago `time` was, as "just now" / "5 min ago" / "3 h ago" /
+/// "2 days ago"
+pub fn format_age(time: SystemTime, now: SystemTime) -> String {
+    let age = now.duration_since(time).unwrap_or(Duration::ZERO).as_secs();
+    match age {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", age / 60),
+        3600..86400 => format!("{} h ago", age / 3600),
+        86400..172800 => "yesterday".to_string(),
+        _ => format!("{} days ago", age / 86400),
+    }
+}
+
+/// `file://` URI for an absolute path, percent-encoding what needs it
+pub fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(*byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Undoes %XX escapes; anything malformed is kept as it is
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The local path behind a `file://` URI, `None` for other schemes
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // file://host/path, where only an empty host or localhost is local
+    let path = match rest.find('/') {
+        Some(0) => rest,
+        Some(slash) if &rest[..slash] == "localhost" => &rest[slash..],
+        _ => return None,
+    };
+    Some(PathBuf::from(percent_decode(path)))
+}
+
+/// Starts `program` without waiting for it. Opened apps can outlive the
+/// panel, so nothing is captured; a thread reaps the child.
+fn launch(program: &str, args: &[&str]) -> Result<(), String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Opening, revealing, dragging and trashing files. Cheap to clone.
+#[derive(Clone)]
+pub struct FileActions {
+    runner: Arc<dyn CommandRunner>,
+    /// Shell command that offers `{path}` for dragging, e.g. dragon-drop
+    drag_command: String,
+}
+
+impl FileActions {
+    pub fn new(runner: Arc<dyn CommandRunner>, drag_command: String) -> Self {
+        Self {
+            runner,
+            drag_command,
+        }
+    }
+
+    /// Opens with the default app for the file's type
+    pub fn open(&self, path: &Path) -> Result<(), String> {
+        launch("xdg-open", &[&path.to_string_lossy()])
+    }
+
+    /// Same for a URI, which may point somewhere remote like sftp://
+    pub fn open_uri(&self, uri: &str) -> Result<(), String> {
+        launch("xdg-open", &[uri])
+    }
+
+    /// Asks the file manager to show the file selected in its folder, or just
+    /// opens the folder when no file manager answers on D-Bus
+    pub fn show_in_folder(&self, path: &Path) -> Result<(), String> {
+        let uri = file_uri(path);
+        let shown = self.runner.run(
+            "dbus-send",
+            &[
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+                &format!("array:string:{}", uri),
+                "string:",
+            ],
+        );
+        match (shown, path.parent()) {
+            (Ok(_), _) => Ok(()),
+            (Err(_), Some(parent)) => self.open(parent),
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// Moves the file to the trash, so it can still be restored
+    pub fn trash(&self, path: &Path) -> Result<(), String> {
+        self.runner
+            .run("gio", &["trash", "--", &path.to_string_lossy()])
+            .map(|_| ())
+    }
+
+    /// Runs the drag command for `path`. egui can't start drags into other
+    /// apps, so a small helper window offers the file instead.
+    pub fn drag(&self, path: &Path) -> Result<(), String> {
+        if self.drag_command.trim().is_empty() {
+            return Err("No drag command set".to_string());
+        }
+        // The path goes in as $1 so it needs no quoting
+        let command = self.drag_command.replace("{path}", "\"$1\"");
+        launch(
+            "sh",
+            &[
+                "-c",
+                &format!("exec {}", command),
+                "sh",
+                &path.to_string_lossy(),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(812), "812 B");
+        assert_eq!(format_size(14 * 1024), "14 KiB");
+        assert_eq!(format_size(3_355_443 * 1024), "3.2 GiB");
+        // A 2 TB disk
+        assert_eq!(format_size(2_000_398_934_016), "1.8 TiB");
+        assert_eq!(format_size(u64::MAX), "16777216.0 TiB");
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: String,
+}
+
+/// A window as reported by `hyprctl clients -j`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Client {
+    pub address: String,
+    pub mapped: bool,
+    pub hidden: bool,
+    pub at: Vec<i32>,
+    pub size: Vec<i32>,
+    pub workspace: Workspace,
+    pub floating: bool,
+    pub pseudo: bool,
+    pub monitor: i32,
+    pub class: String,
+    pub title: String,
+    pub initial_class: String,
+    pub initial_title: String,
+    pub pid: i32,
+    pub xwayland: bool,
+    pub pinned: bool,
+    pub fullscreen: i32,
+    pub fullscreen_client: i32,
+    pub grouped: Vec<String>,
+    pub tags: Vec<String>,
+    pub swallowing: String,
+    #[serde(rename = "focusHistoryID")]
+    pub focus_history_id: i32,
+    pub inhibiting_idle: bool,
+}
+
+/// All windows Hyprland knows about
+pub fn clients(runner: &dyn CommandRunner) -> Result<Vec<Client>, String> {
+    let output = runner.run("hyprctl", &["clients", "-j"])?;
+    serde_json::from_str(&output).map_err(|e| format!("Failed to parse clients: {}", e))
+}
+
+/// Runs `hyprctl dispatch DISPATCHER ARG`. hyprctl exits 0 even when the
+/// dispatcher fails, so anything but "ok" is treated as an error.
+pub fn dispatch(runner: &dyn CommandRunner, dispatcher: &str, arg: &str) -> Result<(), String> {
+    let output = runner.run("hyprctl", &["dispatch", dispatcher, arg])?;
+    match output.trim() {
+        "ok" => Ok(()),
+        message => Err(message.to_string()),
+    }
+}
@@ -0,0 +1,143 @@
+use eframe::egui;
+use egui::{Color32, ColorImage, RichText, TextureHandle, TextureOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::xdg_dirs;
+
+/// Pixel size icons are rasterized at, big enough for every place we draw them
+const RASTER_SIZE: u32 = 64;
+
+const THEMES: &[&str] = &["hicolor", "Adwaita", "breeze"];
+const SIZES: &[&str] = &[
+    "scalable", "64x64", "48x48", "128x128", "256x256", "32x32", "24x24", "16x16",
+];
+
+/// Finds freedesktop icon-theme icons by name and keeps them as egui textures.
+/// Lookups that fail are remembered too, so missing icons cost one search.
+#[derive(Default)]
+pub struct IconCache {
+    textures: HashMap<String, Option<TextureHandle>>,
+}
+
+impl IconCache {
+    /// The texture for icon `name` from the `category` directory of the icon
+    /// themes (`apps`, `mimetypes`, `devices`...). `name` may also be a path.
+    pub fn get(
+        &mut self,
+        ctx: &egui::Context,
+        name: &str,
+        category: &str,
+    ) -> Option<TextureHandle> {
+        let key = format!("{}/{}", category, name);
+        self.textures
+            .entry(key.clone())
+            .or_insert_with(|| {
+                let path = find_icon(name, category)?;
+                let image = match load_image(&path) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        eprintln!("Failed to load icon {}: {}", path.display(), e);
+                        return None;
+                    }
+                };
+                Some(ctx.load_texture(key, image, TextureOptions::LINEAR))
+            })
+            .clone()
+    }
+
+    /// Draws icon `name` at `size`, or the phosphor `fallback` glyph in `color`
+    /// when there's no such icon
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        name: Option<&str>,
+        category: &str,
+        fallback: &str,
+        size: f32,
+        color: Color32,
+    ) -> egui::Response {
+        let texture = name.and_then(|name| self.get(ui.ctx(), name, category));
+        match texture {
+            Some(texture) => {
+                ui.add(egui::Image::new(&texture).fit_to_exact_size(egui::vec2(size, size)))
+            }
+            None => ui.label(RichText::new(fallback).size(size).color(color)),
+        }
+    }
+}
+
+/// Looks `name` up in the icon theme directories and `/usr/share/pixmaps`
+pub fn find_icon(name: &str, category: &str) -> Option<PathBuf> {
+    let direct = Path::new(name);
+    if direct.is_absolute() {
+        return direct.exists().then(|| direct.to_path_buf());
+    }
+
+    for data_dir in data_dirs() {
+        for theme in THEMES {
+            for size in SIZES {
+                let dir = data_dir.join("icons").join(theme).join(size).join(category);
+                for ext in ["svg", "png"] {
+                    let path = dir.join(format!("{}.{}", name, ext));
+                    if path.exists() {
+                        return Some(path);
+                    }
+                }
+            }
+        }
+        for ext in ["svg", "png"] {
+            let path = data_dir.join("pixmaps").join(format!("{}.{}", name, ext));
+            if path.exists() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![xdg_dirs::data_home()];
+    let system = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(
+        system
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+    );
+    dirs
+}
+
+fn load_image(path: &Path) -> Result<ColorImage, String> {
+    if path.extension().is_some_and(|ext| ext == "svg") {
+        return rasterize_svg(path);
+    }
+
+    let image = image::open(path).map_err(|e| e.to_string())?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Ok(ColorImage::from_rgba_unmultiplied(size, image.as_raw()))
+}
+
+fn rasterize_svg(path: &Path) -> Result<ColorImage, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let tree =
+        usvg::Tree::from_data(&data, &usvg::Options::default()).map_err(|e| e.to_string())?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(RASTER_SIZE, RASTER_SIZE)
+        .ok_or_else(|| "Failed to allocate pixmap".to_string())?;
+    let size = tree.size();
+    let scale = RASTER_SIZE as f32 / size.width().max(size.height());
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia pixmaps are premultiplied, same as egui wants
+    Ok(ColorImage::from_rgba_premultiplied(
+        [RASTER_SIZE as usize, RASTER_SIZE as usize],
+        pixmap.data(),
+    ))
+}
@@ -0,0 +1,678 @@
+//! Just enough of RFC 5545 to show the events in `.ics` files: VEVENTs
+//! with their recurrences, exceptions and time zones.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::local_time::{parse_date, parse_time};
+use crate::rrule::RecurrenceRule;
+use crate::timezone::{Observance, Zone};
+
+static LOCAL: Zone = Zone::Local;
+static UTC: Zone = Zone::Utc;
+
+/// One occurrence of an event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub summary: String,
+    pub location: Option<String>,
+    /// Unix timestamps; all-day events run from local midnight to midnight
+    pub start: i64,
+    pub end: i64,
+    pub all_day: bool,
+}
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Component {
+    name: String,
+    properties: Vec<Property>,
+    children: Vec<Component>,
+}
+
+impl Component {
+    fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> {
+        self.properties
+            .iter()
+            .filter(move |property| property.name == name)
+    }
+}
+
+/// A date or time as written in the file
+#[derive(Clone, Debug)]
+enum DateValue {
+    /// Days since 1970-01-01
+    Date(i64),
+    /// Wall-clock seconds in whatever zone the reader is in
+    Floating(i64),
+    Utc(i64),
+    /// Wall-clock seconds in a TZID zone
+    Zoned(i64, String),
+}
+
+/// A VEVENT before expansion
+struct EventSource {
+    uid: String,
+    summary: String,
+    location: Option<String>,
+    start: DateValue,
+    end: Option<DateValue>,
+    duration: Option<i64>,
+    rule: Option<RecurrenceRule>,
+    dates: Vec<DateValue>,
+    exceptions: Vec<DateValue>,
+    recurrence_id: Option<DateValue>,
+}
+
+/// The events of one `.ics` file, with the zones they refer to
+pub struct Calendar {
+    events: Vec<EventSource>,
+    zones: HashMap<String, Zone>,
+}
+
+impl Calendar {
+    pub fn parse(text: &str) -> Calendar {
+        let components = parse_components(text);
+        let mut events = Vec::new();
+        let mut zones = HashMap::new();
+        for calendar in components.iter().filter(|c| c.name == "VCALENDAR") {
+            for component in &calendar.children {
+                match component.name.as_str() {
+                    "VEVENT" => {
+                        if let Some(event) = parse_event(component) {
+                            events.push(event);
+                        }
+                    }
+                    "VTIMEZONE" => {
+                        if let Some((tzid, zone)) = parse_timezone(component) {
+                            zones.insert(tzid, zone);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // TZIDs without a VTIMEZONE are looked up in the system zoneinfo
+        let referenced: HashSet<String> = events
+            .iter()
+            .flat_map(|event| {
+                [
+                    Some(&event.start),
+                    event.end.as_ref(),
+                    event.recurrence_id.as_ref(),
+                ]
+                .into_iter()
+                .flatten()
+                .chain(&event.dates)
+                .chain(&event.exceptions)
+            })
+            .filter_map(|value| match value {
+                DateValue::Zoned(_, tzid) => Some(tzid.clone()),
+                _ => None,
+            })
+            .collect();
+        for tzid in referenced {
+            zones.entry(tzid).or_insert_with_key(|tzid| {
+                Zone::system(tzid).unwrap_or_else(|| {
+                    eprintln!("Unknown time zone {}, using local time", tzid);
+                    Zone::Local
+                })
+            });
+        }
+        Calendar { events, zones }
+    }
+
+    /// Every occurrence overlapping `from..to` (Unix timestamps), sorted by
+    /// start
+    pub fn events_between(&self, from: i64, to: i64) -> Vec<Event> {
+        // Instances moved or changed by a RECURRENCE-ID event
+        let overridden: HashSet<(&str, i64)> = self
+            .events
+            .iter()
+            .filter_map(|event| {
+                let id = event.recurrence_id.as_ref()?;
+                Some((event.uid.as_str(), self.to_utc(id)))
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        for source in &self.events {
+            let all_day = matches!(source.start, DateValue::Date(_));
+            let (wall, zone) = self.wall_and_zone(&source.start);
+            let length = self.length(source);
+
+            let mut starts: Vec<i64> = match &source.rule {
+                Some(rule) if source.recurrence_id.is_none() => {
+                    let until = rule.until.map(|until| {
+                        if rule.until_utc {
+                            zone.to_wall(until)
+                        } else {
+                            until
+                        }
+                    });
+                    // A day's margin for zone offsets
+                    let end = zone.to_wall(to) + 86400;
+                    rule.occurrences(wall, until, end)
+                }
+                _ => vec![wall],
+            };
+            for date in &source.dates {
+                starts.push(zone.to_wall(self.to_utc(date)));
+            }
+
+            let exceptions: HashSet<i64> = source
+                .exceptions
+                .iter()
+                .map(|exception| self.to_utc(exception))
+                .collect();
+
+            for start_wall in starts {
+                let start = zone.to_utc(start_wall);
+                let end = if all_day {
+                    zone.to_utc(start_wall + length)
+                } else {
+                    start + length
+                };
+                if exceptions.contains(&start)
+                    || (source.recurrence_id.is_none()
+                        && overridden.contains(&(source.uid.as_str(), start)))
+                {
+                    continue;
+                }
+                // Zero-length events still count at their start
+                if start < to && (end > from || (end == start && start >= from)) {
+                    events.push(Event {
+                        summary: source.summary.clone(),
+                        location: source.location.clone(),
+                        start,
+                        end,
+                        all_day,
+                    });
+                }
+            }
+        }
+        events.sort_by_key(|event| (event.start, event.end));
+        events.dedup();
+        events
+    }
+
+    fn zone(&self, tzid: &str) -> &Zone {
+        self.zones.get(tzid).unwrap_or(&LOCAL)
+    }
+
+    /// Wall-clock seconds and the zone they're in
+    fn wall_and_zone(&self, value: &DateValue) -> (i64, &Zone) {
+        match value {
+            DateValue::Date(days) => (days * 86400, &LOCAL),
+            DateValue::Floating(wall) => (*wall, &LOCAL),
+            DateValue::Utc(utc) => (*utc, &UTC),
+            DateValue::Zoned(wall, tzid) => (*wall, self.zone(tzid)),
+        }
+    }
+
+    fn to_utc(&self, value: &DateValue) -> i64 {
+        let (wall, zone) = self.wall_and_zone(value);
+        zone.to_utc(wall)
+    }
+
+    /// Event length: wall-clock seconds for all-day events so they stay
+    /// whole days across DST changes, real seconds otherwise
+    fn length(&self, source: &EventSource) -> i64 {
+        let all_day = matches!(source.start, DateValue::Date(_));
+        if let Some(end) = &source.end {
+            let length = if all_day {
+                self.wall_and_zone(end).0 - self.wall_and_zone(&source.start).0
+            } else {
+                self.to_utc(end) - self.to_utc(&source.start)
+            };
+            return length.max(0);
+        }
+        match source.duration {
+            Some(duration) => duration.max(0),
+            None if all_day => 86400,
+            None => 0,
+        }
+    }
+}
+
+/// Undoes line folding and splits the file into nested components
+fn parse_components(text: &str) -> Vec<Component> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut stack: Vec<Component> = Vec::new();
+    let mut top = Vec::new();
+    for line in lines {
+        let Some(property) = parse_property(&line) else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: property.value.to_ascii_uppercase(),
+                properties: Vec::new(),
+                children: Vec::new(),
+            }),
+            "END" => {
+                if let Some(component) = stack.pop() {
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(component),
+                        None => top.push(component),
+                    }
+                }
+            }
+            _ => {
+                if let Some(component) = stack.last_mut() {
+                    component.properties.push(property);
+                }
+            }
+        }
+    }
+    top
+}
+
+/// "NAME;PARAM=value;PARAM=\"quoted\":value"
+fn parse_property(line: &str) -> Option<Property> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some(index),
+            _ => {}
+        }
+        None
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim_matches('"').to_string()))
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+fn parse_date_value(property: &Property) -> Option<DateValue> {
+    parse_date_text(
+        property.value.trim(),
+        property.param("VALUE"),
+        property.param("TZID"),
+    )
+}
+
+fn parse_date_text(text: &str, kind: Option<&str>, tzid: Option<&str>) -> Option<DateValue> {
+    if kind.is_some_and(|kind| kind.eq_ignore_ascii_case("DATE")) || !text.contains('T') {
+        return parse_date(text).map(DateValue::Date);
+    }
+    let (date, time) = text.split_once('T')?;
+    let days = parse_date(date)?;
+    let utc = time.ends_with('Z');
+    let wall = days * 86400 + parse_time(time.trim_end_matches('Z'))?;
+    Some(match (utc, tzid) {
+        (true, _) => DateValue::Utc(wall),
+        (false, Some(tzid)) => DateValue::Zoned(wall, tzid.to_string()),
+        (false, None) => DateValue::Floating(wall),
+    })
+}
+
+/// All values of a list property like EXDATE, which may repeat and hold
+/// several comma-separated values each
+fn parse_date_list(component: &Component, name: &str) -> Vec<DateValue> {
+    component
+        .all(name)
+        .flat_map(|property| {
+            property.value.split(',').filter_map(|text| {
+                parse_date_text(text.trim(), property.param("VALUE"), property.param("TZID"))
+            })
+        })
+        .collect()
+}
+
+fn parse_event(component: &Component) -> Option<EventSource> {
+    if component
+        .property("STATUS")
+        .is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED"))
+    {
+        return None;
+    }
+    let start = parse_date_value(component.property("DTSTART")?)?;
+    let rule =
+        component
+            .property("RRULE")
+            .and_then(|rule| match RecurrenceRule::parse(&rule.value) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    eprintln!("Ignoring recurrence rule: {}", e);
+                    None
+                }
+            });
+    Some(EventSource {
+        uid: component
+            .property("UID")
+            .map(|uid| uid.value.clone())
+            .unwrap_or_default(),
+        summary: component
+            .property("SUMMARY")
+            .map_or_else(|| "Untitled event".to_string(), |s| unescape(&s.value)),
+        location: component
+            .property("LOCATION")
+            .map(|location| unescape(&location.value))
+            .filter(|location| !location.is_empty()),
+        start,
+        end: component.property("DTEND").and_then(parse_date_value),
+        duration: component
+            .property("DURATION")
+            .and_then(|duration| parse_duration(&duration.value)),
+        rule,
+        dates: parse_date_list(component, "RDATE"),
+        exceptions: parse_date_list(component, "EXDATE"),
+        recurrence_id: component
+            .property("RECURRENCE-ID")
+            .and_then(parse_date_value),
+    })
+}
+
+fn parse_timezone(component: &Component) -> Option<(String, Zone)> {
+    let tzid = component.property("TZID")?.value.clone();
+    let observances: Vec<Observance> = component
+        .children
+        .iter()
+        .filter(|child| matches!(child.name.as_str(), "STANDARD" | "DAYLIGHT"))
+        .filter_map(|child| {
+            let start = match parse_date_value(child.property("DTSTART")?)? {
+                DateValue::Date(days) => days * 86400,
+                DateValue::Floating(wall) | DateValue::Utc(wall) | DateValue::Zoned(wall, _) => {
+                    wall
+                }
+            };
+            Some(Observance {
+                start,
+                offset_from: parse_utc_offset(&child.property("TZOFFSETFROM")?.value)?,
+                offset_to: parse_utc_offset(&child.property("TZOFFSETTO")?.value)?,
+                rule: child
+                    .property("RRULE")
+                    .and_then(|rule| RecurrenceRule::parse(&rule.value).ok()),
+                dates: parse_date_list(child, "RDATE")
+                    .into_iter()
+                    .filter_map(|date| match date {
+                        DateValue::Floating(wall) => Some(wall),
+                        _ => None,
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+    (!observances.is_empty()).then_some((tzid, Zone::Observances(observances)))
+}
+
+/// "+0100" or "-023000" in seconds
+fn parse_utc_offset(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits = &text[1..];
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[0..2].parse().ok()?;
+    let minutes: i64 = digits[2..4].parse().ok()?;
+    let seconds: i64 = digits.get(4..6).map_or(Some(0), |s| s.parse().ok())?;
+    Some(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+/// "P1DT2H30M", "PT15M", "P2W" in seconds
+fn parse_duration(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let text = text.strip_prefix('P')?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let value: i64 = number.parse().ok()?;
+                number.clear();
+                seconds += value
+                    * match unit {
+                        'W' => 7 * 86400,
+                        'D' => 86400,
+                        'H' => 3600,
+                        'M' => 60,
+                        'S' => 1,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    Some(sign * seconds)
+}
+
+/// Reads the events from `.ics` files, looking through directories (like
+/// vdirsyncer's storage) a few levels deep
+pub fn load(paths: &[String]) -> (Vec<Calendar>, Vec<String>) {
+    let mut calendars = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        let path = std::path::PathBuf::from(shellexpand::tilde(path).as_ref());
+        collect(&path, 0, &mut calendars, &mut errors);
+    }
+    (calendars, errors)
+}
+
+fn collect(
+    path: &std::path::Path,
+    depth: u32,
+    calendars: &mut Vec<Calendar>,
+    errors: &mut Vec<String>,
+) {
+    if path.is_dir() {
+        if depth > 3 {
+            return;
+        }
+        match std::fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    collect(&entry.path(), depth + 1, calendars, errors);
+                }
+            }
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+        return;
+    }
+    // Files named directly are read whatever their extension
+    let is_ics = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ics"));
+    if depth > 0 && !is_ics {
+        return;
+    }
+    match std::fs::read(path) {
+        Ok(data) => calendars.push(Calendar::parse(&String::from_utf8_lossy(&data))),
+        Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_time::{self, days_from_civil};
+
+    /// A few events in Europe/Berlin, which switches to summer time on
+    /// 2024-03-31
+    fn calendar() -> Calendar {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/calendar/events.ics"
+        );
+        Calendar::parse(&std::fs::read_to_string(path).unwrap())
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: i64, minute: i64) -> i64 {
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60
+    }
+
+    fn named(summary: &str) -> Vec<Event> {
+        calendar()
+            .events_between(utc(2024, 3, 1, 0, 0), utc(2024, 5, 1, 0, 0))
+            .into_iter()
+            .filter(|event| event.summary == summary)
+            .collect()
+    }
+
+    #[test]
+    fn unfolds_lines_and_unescapes_text() {
+        let events = named("Quarterly planning with the whole team");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].location.as_deref(), Some("Room 4, second floor"));
+
+        let text = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Tab\r\n\tbed\r\n\
+                    DTSTART:20240101T000000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let events = Calendar::parse(text).events_between(0, i64::MAX);
+        assert_eq!(events[0].summary, "Tabbed");
+    }
+
+    #[test]
+    fn reads_zoned_start() {
+        let events = named("Quarterly planning with the whole team");
+        assert_eq!(events[0].start, utc(2024, 3, 20, 9, 0));
+        assert_eq!(events[0].end, utc(2024, 3, 20, 10, 0));
+        assert!(!events[0].all_day);
+    }
+
+    #[test]
+    fn reads_utc_start_with_duration() {
+        let events = named("Call");
+        assert_eq!(events[0].start, utc(2024, 3, 21, 12, 0));
+        assert_eq!(events[0].end, utc(2024, 3, 21, 12, 30));
+    }
+
+    #[test]
+    fn reads_floating_start_in_local_time() {
+        let events = named("Gym");
+        assert_eq!(
+            events[0].start,
+            local_time::local_to_unix(utc(2024, 3, 22, 8, 0))
+        );
+        assert_eq!(
+            events[0].end,
+            local_time::local_to_unix(utc(2024, 3, 22, 9, 0))
+        );
+    }
+
+    #[test]
+    fn all_day_events_run_midnight_to_midnight() {
+        let events = named("Holiday");
+        assert!(events[0].all_day);
+        assert_eq!(
+            events[0].start,
+            local_time::local_to_unix(utc(2024, 3, 23, 0, 0))
+        );
+        assert_eq!(
+            events[0].end,
+            local_time::local_to_unix(utc(2024, 3, 24, 0, 0))
+        );
+    }
+
+    #[test]
+    fn recurrences_keep_wall_time_across_dst() {
+        let starts: Vec<i64> = named("Standup").iter().map(|e| e.start).collect();
+        assert_eq!(
+            starts,
+            [
+                utc(2024, 3, 25, 8, 0),
+                utc(2024, 4, 1, 7, 0),
+                utc(2024, 4, 8, 7, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn by_day_until_and_exdate() {
+        let starts: Vec<i64> = named("Choir").iter().map(|e| e.start).collect();
+        // The 3rd is excluded and the 15th is past UNTIL
+        assert_eq!(
+            starts,
+            [
+                utc(2024, 4, 1, 16, 0),
+                utc(2024, 4, 8, 16, 0),
+                utc(2024, 4, 10, 16, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_cancelled_events() {
+        assert!(named("Cancelled").is_empty());
+    }
+
+    #[test]
+    fn only_returns_events_in_range() {
+        let events = calendar().events_between(utc(2024, 4, 2, 0, 0), utc(2024, 4, 9, 0, 0));
+        let summaries: Vec<&str> = events.iter().map(|e| e.summary.as_str()).collect();
+        assert_eq!(summaries, ["Standup", "Choir"]);
+    }
+
+    #[test]
+    fn parses_durations_and_offsets() {
+        assert_eq!(parse_duration("P1DT2H30M"), Some(95400));
+        assert_eq!(parse_duration("-PT15M"), Some(-900));
+        assert_eq!(parse_duration("P2W"), Some(14 * 86400));
+        assert_eq!(parse_utc_offset("+0530"), Some(19800));
+        assert_eq!(parse_utc_offset("-023000"), Some(-9000));
+        assert_eq!(parse_utc_offset("0100"), None);
+    }
+}
@@ -0,0 +1,201 @@
+use eframe::egui;
+use parking_lot::Mutex;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use crate::quick_settings::QuickSettingsState;
+
+/// A command from another process for the panel to carry out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpcCommand {
+    /// Mute (true) or unmute (false) the microphone, or toggle when `None`
+    MicMute(Option<bool>),
+}
+
+/// One line received on the socket
+enum Request {
+    Command(IpcCommand),
+    /// Reply with the quick settings state as JSON
+    State,
+}
+
+fn parse_request(line: &str) -> Result<Request, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["state"] => Ok(Request::State),
+        ["mic-mute"] | ["mic-mute", "toggle"] => Ok(Request::Command(IpcCommand::MicMute(None))),
+        ["mic-mute", "on"] => Ok(Request::Command(IpcCommand::MicMute(Some(true)))),
+        ["mic-mute", "off"] => Ok(Request::Command(IpcCommand::MicMute(Some(false)))),
+        _ => Err(format!("unknown command: {}", line.trim())),
+    }
+}
+
+/// A client that connects and then says nothing would otherwise hold up
+/// everyone after it
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `$XDG_RUNTIME_DIR/hermit-action-center.sock`. There's no fallback: a
+/// shared dir like /tmp would let other users send commands.
+pub fn socket_path() -> Result<PathBuf, String> {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir).join("hermit-action-center.sock")),
+        _ => Err("XDG_RUNTIME_DIR is not set, no place for the socket".to_string()),
+    }
+}
+
+/// Listens on the panel's Unix socket. Commands are handed to the UI thread
+/// through a channel; state queries are answered from the last snapshot the
+/// UI published.
+pub struct IpcServer {
+    path: PathBuf,
+    commands: Receiver<IpcCommand>,
+    state: Arc<Mutex<String>>,
+}
+
+impl IpcServer {
+    /// Binds the socket and starts the listener thread. `ctx` is woken up
+    /// whenever a command arrives so it's handled right away.
+    pub fn start(ctx: egui::Context) -> Option<Self> {
+        let path = match socket_path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Not listening for commands: {}", e);
+                return None;
+            }
+        };
+
+        // A socket nobody answers on is left over from a previous run
+        if path.exists() && UnixStream::connect(&path).is_err() {
+            let _ = fs::remove_file(&path);
+        }
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("Failed to bind {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        let (sender, commands) = mpsc::channel();
+        let state = Arc::new(Mutex::new(String::from("{}")));
+        let thread_state = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle_client(stream, &sender, &thread_state, &ctx);
+            }
+        });
+
+        Some(Self {
+            path,
+            commands,
+            state,
+        })
+    }
+
+    /// The next pending command, if any
+    pub fn try_recv(&self) -> Option<IpcCommand> {
+        self.commands.try_recv().ok()
+    }
+
+    /// Stores the state that `state` requests are answered with
+    pub fn publish(&self, state: &QuickSettingsState) {
+        match serde_json::to_string(state) {
+            Ok(json) => *self.state.lock() = json,
+            Err(e) => eprintln!("Failed to serialize state: {}", e),
+        }
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_client(
+    stream: UnixStream,
+    sender: &Sender<IpcCommand>,
+    state: &Mutex<String>,
+    ctx: &egui::Context,
+) {
+    if let Err(e) = stream
+        .set_read_timeout(Some(CLIENT_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT)))
+    {
+        eprintln!("Failed to set IPC timeout: {}", e);
+        return;
+    }
+
+    let mut line = String::new();
+    let mut reader = BufReader::new(&stream);
+    if let Err(e) = reader.read_line(&mut line) {
+        eprintln!("Failed to read IPC request: {}", e);
+        return;
+    }
+
+    let reply = match parse_request(&line) {
+        Ok(Request::State) => state.lock().clone(),
+        Ok(Request::Command(command)) => {
+            let _ = sender.send(command);
+            ctx.request_repaint();
+            "ok".to_string()
+        }
+        Err(e) => e,
+    };
+
+    let mut stream = &stream;
+    if let Err(e) = writeln!(stream, "{}", reply) {
+        eprintln!("Failed to write IPC reply: {}", e);
+    }
+}
+
+/// Sends `message` to the running panel and returns its reply
+pub fn send(message: &str) -> Result<String, String> {
+    let path = socket_path()?;
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|e| format!("Failed to connect to {}: {}", path.display(), e))?;
+    writeln!(stream, "{}", message).map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    stream
+        .read_to_string(&mut reply)
+        .map_err(|e| e.to_string())?;
+    Ok(reply.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn command_is_forwarded() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let state = Mutex::new(String::new());
+        writeln!(&client, "mic-mute on").unwrap();
+
+        handle_client(server, &sender, &state, &egui::Context::default());
+        let mut reply = String::new();
+        (&client).read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "ok\n");
+        assert_eq!(receiver.try_recv(), Ok(IpcCommand::MicMute(Some(true))));
+    }
+
+    #[test]
+    fn silent_client_times_out() {
+        let (_client, server) = UnixStream::pair().unwrap();
+        let (sender, _receiver) = mpsc::channel();
+        let state = Mutex::new(String::new());
+
+        let start = Instant::now();
+        handle_client(server, &sender, &state, &egui::Context::default());
+        assert!(start.elapsed() < CLIENT_TIMEOUT * 2);
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A moment broken down in the system time zone, via `localtime_r`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i32,
+    /// 1-12
+    pub month: u32,
+    /// 1-31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// 0 is Sunday
+    pub weekday: u32,
+    /// 1-366
+    pub day_of_year: u32,
+    /// Seconds east of UTC, including daylight saving
+    pub utc_offset: i64,
+    /// The Unix timestamp this was made from
+    pub timestamp: i64,
+}
+
+impl LocalTime {
+    pub fn now() -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+        Self::from_unix(timestamp)
+    }
+
+    pub fn from_unix(timestamp: i64) -> Self {
+        let time = timestamp as libc::time_t;
+        // SAFETY: localtime_r only writes to the tm we hand it
+        let tm = unsafe {
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&time, &mut tm);
+            tm
+        };
+        Self {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u32 + 1,
+            day: tm.tm_mday as u32,
+            hour: tm.tm_hour as u32,
+            minute: tm.tm_min as u32,
+            second: tm.tm_sec as u32,
+            weekday: tm.tm_wday as u32,
+            day_of_year: tm.tm_yday as u32 + 1,
+            utc_offset: tm.tm_gmtoff,
+            timestamp,
+        }
+    }
+
+    /// Formats with `strftime`, e.g. "%H:%M" or "%A, %d %B"
+    pub fn format(&self, format: &str) -> String {
+        let Ok(format) = std::ffi::CString::new(format) else {
+            return String::new();
+        };
+        let time = self.timestamp as libc::time_t;
+        let mut buffer = [0u8; 256];
+        // SAFETY: strftime writes at most buffer.len() bytes and returns how
+        // many it wrote, 0 if the result didn't fit
+        let written = unsafe {
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&time, &mut tm);
+            libc::strftime(
+                buffer.as_mut_ptr() as *mut libc::c_char,
+                buffer.len(),
+                format.as_ptr(),
+                &tm,
+            )
+        };
+        String::from_utf8_lossy(&buffer[..written]).into_owned()
+    }
+
+    /// Minutes since local midnight
+    pub fn minute_of_day(&self) -> u32 {
+        self.hour * 60 + self.minute
+    }
+
+    /// The local date as days since 1970-01-01
+    pub fn days(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+}
+
+/// The Unix timestamp of a wall-clock time (days since 1970-01-01 * 86400 +
+/// seconds into the day) in the system time zone, via `mktime`
+pub fn local_to_unix(wall: i64) -> i64 {
+    let (year, month, day) = civil_from_days(wall.div_euclid(86400));
+    let seconds = wall.rem_euclid(86400);
+    // SAFETY: mktime only reads and normalises the tm we hand it
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        tm.tm_year = year - 1900;
+        tm.tm_mon = month as i32 - 1;
+        tm.tm_mday = day as i32;
+        tm.tm_hour = (seconds / 3600) as i32;
+        tm.tm_min = (seconds / 60 % 60) as i32;
+        tm.tm_sec = (seconds % 60) as i32;
+        // Let mktime work out whether daylight saving applies
+        tm.tm_isdst = -1;
+        libc::mktime(&mut tm) as i64
+    }
+}
+
+/// Parses "HH:MM" into minutes since midnight
+pub fn parse_clock_time(text: &str) -> Option<u32> {
+    let (hour, minute) = text.trim().split_once(':')?;
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+/// Minutes since midnight as "HH:MM"
+pub fn format_clock_time(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+}
+
+/// "YYYYMMDD" (basic ISO 8601) as days since 1970-01-01
+pub fn parse_date(text: &str) -> Option<i64> {
+    if text.len() != 8 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let year = text[0..4].parse().ok()?;
+    let month = text[4..6].parse().ok()?;
+    let day = text[6..8].parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day))
+        .then(|| days_from_civil(year, month, day))
+}
+
+/// "HHMMSS" as seconds into the day
+pub fn parse_time(text: &str) -> Option<i64> {
+    if text.len() != 6 || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let hour: i64 = text[0..2].parse().ok()?;
+    let minute: i64 = text[2..4].parse().ok()?;
+    let second: i64 = text[4..6].parse().ok()?;
+    Some(hour * 3600 + minute * 60 + second)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+pub fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    // Howard Hinnant's algorithm, with the year starting in March
+    let year = if month <= 2 { year - 1 } else { year } as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The (year, month, day) `days` after 1970-01-01
+pub fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
+
+/// 0 is Sunday, like `LocalTime::weekday`
+pub fn weekday_from_days(days: i64) -> u32 {
+    // 1970-01-01 was a Thursday
+    (days + 4).rem_euclid(7) as u32
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+/// ISO 8601 week number: weeks start on Monday and week 1 holds the year's
+/// first Thursday
+pub fn iso_week(days: i64) -> u32 {
+    let monday_based = (weekday_from_days(days) + 6) % 7;
+    let thursday = days - monday_based as i64 + 3;
+    let (year, _, _) = civil_from_days(thursday);
+    ((thursday - days_from_civil(year, 1, 1)) / 7 + 1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dates_and_times() {
+        assert_eq!(parse_date("19700102"), Some(1));
+        assert_eq!(parse_date("20241301"), None);
+        assert_eq!(parse_date("2024-01-01"), None);
+        assert_eq!(parse_time("013005"), Some(5405));
+        assert_eq!(parse_time("0130"), None);
+    }
+}
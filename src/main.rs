@@ -1,87 +1,134 @@
+use clap::Parser;
 use eframe::egui;
-use egui::{Button, Color32, Label, Layout, RichText, Vec2, Widget};
+use egui::RichText;
 use egui_phosphor::regular::*;
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::Read;
 use std::process::Command;
-use std::time::{Duration, Instant};
+use std::sync::Arc;
 
 mod active_actions;
+mod agenda;
+mod audio;
+mod battery;
+mod battery_section;
+mod bluetooth;
+mod bluetooth_page;
+mod brightness;
+mod caffeine;
+mod clock;
+mod command_runner;
+mod config;
+mod contrast;
+mod device_picker;
+mod downloads;
+mod downloads_page;
+mod files;
+mod hyprland;
+mod icons;
+mod ics;
+mod ipc;
+mod local_time;
+mod night_light;
+mod nmcli;
+mod notify;
+mod palette;
+mod places;
+mod places_page;
+mod power_profile;
 mod quick_settings;
+mod resource_monitor;
+mod resources;
+mod rrule;
+mod screenshot;
+mod session_menu;
 mod slider_controls;
+mod storage;
+mod storage_section;
+mod sun;
+mod task;
+mod theme_loader;
+mod timezone;
+mod volume_mixer;
+mod vpn;
+mod vpn_page;
+mod wifi;
+mod wifi_page;
+mod window_switcher;
+mod xdg_dirs;
 
 use active_actions::ActiveActions;
-use quick_settings::QuickSettings;
-use slider_controls::IconSlider;
-
-#[derive(Clone)]
-struct Colors {
-    background: Color32,
-    surface: Color32,
-    on_surface: Color32,
-    primary: Color32,
-    on_primary: Color32,
-    secondary: Color32,
-    on_secondary: Color32,
-}
-
-impl Default for Colors {
-    fn default() -> Self {
-        Self {
-            background: Color32::from_rgb(30, 30, 30),      // Dark gray
-            surface: Color32::from_rgb(30, 30, 30),         // Dark gray
-            on_surface: Color32::from_rgb(200, 200, 200),   // Light gray
-            primary: Color32::from_rgb(0, 120, 215),        // Blue
-            on_primary: Color32::from_rgb(255, 255, 255),   // White
-            secondary: Color32::from_rgb(0, 153, 204),      // Light blue
-            on_secondary: Color32::from_rgb(255, 255, 255), // White
-        }
-    }
+use audio::AudioBackend;
+use battery_section::BatterySection;
+use bluetooth::BluetoothBackend;
+use bluetooth_page::BluetoothPage;
+use brightness::BrightnessBackend;
+use caffeine::Caffeine;
+use clock::Clock;
+use command_runner::{CommandRunner, SystemRunner};
+use config::{Config, ThemeConfig};
+use device_picker::DevicePicker;
+use downloads_page::DownloadsPage;
+use files::FileActions;
+use ipc::{IpcCommand, IpcServer};
+use night_light::NightLight;
+use palette::Palette;
+use places_page::PlacesPage;
+use power_profile::PowerProfiles;
+use quick_settings::{DetailPage, QuickSettings};
+use resource_monitor::ResourceMonitor;
+use screenshot::Screenshotter;
+use session_menu::SessionMenu;
+use slider_controls::{IconSlider, SliderEvent, SliderKind};
+use storage::StorageBackend;
+use storage_section::StorageSection;
+use task::{BackgroundTask, WriteQueue};
+use theme_loader::ThemeFormat;
+use volume_mixer::VolumeMixer;
+use vpn::VpnBackend;
+use vpn_page::VpnPage;
+use wifi::WifiBackend;
+use wifi_page::WifiPage;
+use window_switcher::WindowSwitcher;
+
+/// Brightness and output volume, read in the background since both can
+/// change from outside the panel
+struct Levels {
+    brightness: Result<f32, String>,
+    sink: Result<(f32, bool), String>,
 }
 
 struct ActionCenterWidget {
-    colors: Colors,
+    config: Config,
+    colors: Palette,
     positioned: bool,
+    clock: Clock,
     brightness_slider: IconSlider,
     volume_slider: IconSlider,
+    microphone_slider: IconSlider,
+    battery: BatterySection,
+    storage: StorageSection,
+    session_menu: SessionMenu,
+    window_switcher: WindowSwitcher,
+    resource_monitor: ResourceMonitor,
+    device_picker: DevicePicker,
+    volume_mixer: VolumeMixer,
     quick_settings: QuickSettings,
+    page: Option<DetailPage>,
+    wifi_page: WifiPage,
+    bluetooth_page: BluetoothPage,
+    vpn_page: VpnPage,
+    downloads_page: DownloadsPage,
+    places_page: PlacesPage,
     active_actions: ActiveActions,
     last_wifi_update: std::time::Instant,
     last_bluetooth_update: std::time::Instant,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Workspace {
-    id: i32,
-    name: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Client {
-    address: String,
-    mapped: bool,
-    hidden: bool,
-    at: Vec<i32>,
-    size: Vec<i32>,
-    workspace: Workspace,
-    floating: bool,
-    pseudo: bool,
-    monitor: i32,
-    class: String,
-    title: String,
-    initialClass: String,
-    initialTitle: String,
-    pid: i32,
-    xwayland: bool,
-    pinned: bool,
-    fullscreen: i32,
-    fullscreenClient: i32,
-    grouped: Vec<String>,
-    tags: Vec<String>,
-    swallowing: String,
-    focusHistoryID: i32,
-    inhibitingIdle: bool,
+    last_microphone_update: std::time::Instant,
+    audio: AudioBackend,
+    brightness: BrightnessBackend,
+    /// Brightness and volume set from the sliders, applied off the UI thread
+    slider_writes: WriteQueue<&'static str>,
+    levels: Option<BackgroundTask<Levels>>,
+    last_levels_update: Option<std::time::Instant>,
+    ipc: Option<IpcServer>,
 }
 
 impl ActionCenterWidget {
@@ -91,23 +138,15 @@ impl ActionCenterWidget {
         }
 
         // get hyprctl clients
-        let output = match Command::new("hyprctl").arg("clients").arg("-j").output() {
-            Ok(output) => output,
-            Err(e) => {
-                eprintln!("Failed to execute command: {}", e);
-                return; // Exit the function if the command fails
-            }
-        };
-
-        let clients: Vec<Client> = match serde_json::from_slice(&output.stdout) {
+        let clients = match hyprland::clients(&SystemRunner) {
             Ok(clients) => clients,
             Err(e) => {
-                eprintln!("Failed to parse clients: {}", e);
-                return; // Exit the function if parsing fails
+                eprintln!("Failed to get clients: {}", e);
+                return; // Exit the function if the command fails
             }
         };
 
-        if clients.len() > 0 {
+        if !clients.is_empty() {
             // get client with title "Action Center "
             let action_center_client = clients
                 .iter()
@@ -126,111 +165,316 @@ impl ActionCenterWidget {
                     "hyprctl dispatch movewindowpixel \"exact {} {},address:{}\"",
                     x, y, address
                 );
-                Command::new("sh").args(&["-c", &move_cmd]).output().ok();
+                Command::new("sh").args(["-c", &move_cmd]).output().ok();
 
                 // Resize window
                 let resize_cmd = format!(
                     "hyprctl dispatch resizewindowpixel \"exact {} {},address:{}\"",
                     width, height, address
                 );
-                Command::new("sh").args(&["-c", &resize_cmd]).output().ok();
+                Command::new("sh").args(["-c", &resize_cmd]).output().ok();
 
                 // Pin window
                 let address_arg = format!("address:{}", address);
                 Command::new("hyprctl")
-                    .args(&["dispatch", "pin", &address_arg])
+                    .args(["dispatch", "pin", &address_arg])
                     .output()
                     .ok();
 
-                self.positioned = true;
-
                 self.positioned = true;
                 // println!("Address: {}", address);
             }
         }
     }
-    fn get_colors(&mut self) {
-        let file_path = "./src/colors.css";
-        let mut file = match File::open(file_path) {
-            Ok(file) => file,
-            Err(e) => {
-                eprintln!("Error opening colors.css: {}", e);
-                return; // Return early, using default colors if file cannot be opened
-            }
-        };
 
-        let mut contents = String::new();
-        if let Err(e) = file.read_to_string(&mut contents) {
-            eprintln!("Error reading colors.css: {}", e);
-            return;
+    /// Switches to a detail page, starting whatever it needs to load
+    fn open_page(&mut self, ctx: &egui::Context, page: DetailPage) {
+        match page {
+            DetailPage::Wifi => self.wifi_page.rescan(ctx),
+            DetailPage::Bluetooth => self.bluetooth_page.refresh(ctx),
+            DetailPage::Vpn => self.vpn_page.refresh(ctx),
+            DetailPage::Downloads => self.downloads_page.refresh(),
+            DetailPage::Places => self.places_page.refresh(),
         }
+        self.page = Some(page);
+    }
 
-        let mut colors = Colors::default(); // Use default colors
-
-        for line in contents.lines() {
-            // Check for color definitions in the format "@define-color key #value;"
-            if line.contains("@define-color") {
-                let line = line.replace("@define-color ", "");
-                let parts: Vec<&str> = line.split(' ').collect();
-                if parts.len() >= 2 {
-                    let key = parts[0].trim();
+    fn show_main_page(&mut self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            // Time and date, unfolds into the calendar
+            self.clock.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // Top section with equal-width widgets
+            ui.horizontal(|ui| {
+                ui.add_space(4.0);
+                ui.set_min_width(ui.available_width());
+                let available_width = ui.available_width() / 2.0 - 8.0; // Half spacing between columns
+
+                // Left column - Quick Settings
+                ui.scope(|ui| {
+                    ui.set_min_width(available_width);
+                    ui.set_max_width(available_width);
+                    if let Some(page) = self.quick_settings.show(ui) {
+                        self.open_page(ui.ctx(), page);
+                    }
+                });
 
-                    // Extract the color value and clean it
-                    let mut value = parts[1].trim().replace(";", "");
+                ui.add_space(4.0);
 
-                    // Ensure the value starts with #
-                    if !value.starts_with('#') {
-                        continue;
+                // Right column - Active Actions
+                ui.scope(|ui| {
+                    ui.set_min_width(available_width);
+                    ui.set_max_width(available_width);
+                    if let Some(page) = self.active_actions.show(ui) {
+                        self.open_page(ui.ctx(), page);
                     }
+                });
+            });
+            //ADD seperator
+            ui.add_space(8.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            // Display section with brightness slider
+            self.show_brightness_slider(ui);
+            self.quick_settings
+                .night_light_mut()
+                .show_slider(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // Sound section with volume slider
+            self.show_volume_slider(ui);
+            self.device_picker.show(ui, &self.colors);
+            self.volume_mixer.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // Microphone section with input volume slider
+            self.show_microphone_slider(ui);
+            ui.add_space(8.0);
+
+            // Battery section, empty on desktops
+            self.battery.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // Disk space and removable drives
+            self.storage.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // Lock, log out, suspend, ...
+            self.session_menu.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // Open windows across workspaces
+            self.window_switcher.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            // CPU, memory and temperature
+            self.resource_monitor.show(ui, &self.colors);
+            ui.add_space(8.0);
+
+            ui.separator();
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.label(RichText::new("Notifications").color(self.colors.on_surface));
+                ui.add_space(8.0);
+            });
+        });
+    }
 
-                    // Remove the # prefix for hex parsing
-                    value = value.trim_start_matches('#').to_string();
+    /// Applies commands received over the IPC socket and publishes the
+    /// resulting state for `state` queries
+    fn handle_ipc(&mut self) {
+        let Some(ipc) = &self.ipc else {
+            return;
+        };
 
-                    // Ensure 6 characters for RGB (add alpha if needed)
-                    if value.len() == 6 {
-                        value = format!("#{}ff", value); // Add alpha channel
-                    } else if value.len() != 8 {
-                        println!("Invalid color format for {}: {}", key, value);
-                        continue;
-                    }
+        while let Some(command) = ipc.try_recv() {
+            match command {
+                IpcCommand::MicMute(muted) => {
+                    let muted = muted.unwrap_or(!self.quick_settings.state().mic_muted);
+                    self.quick_settings.set_microphone_muted(muted);
+                }
+            }
+        }
+        ipc.publish(self.quick_settings.state());
+    }
 
-                    // Try to parse the hex value
-                    let color = match Color32::from_hex(&value) {
-                        Ok(color) => color,
-                        Err(e) => {
-                            println!("Failed to parse color for {}: {:?}", key, e);
-                            continue;
-                        }
-                    };
-
-                    // Map the color based on the key
-                    match key {
-                        "surface_container_lowest" => colors.background = color,
-                        "surface_container_low" => colors.surface = color,
-                        "on_surface_variant" => colors.on_surface = color,
-                        "primary_fixed_dim" => colors.primary = color,
-                        "on_primary_fixed" => colors.on_primary = color,
-                        "secondary_fixed_dim" => colors.secondary = color,
-                        "on_secondary_fixed" => colors.on_secondary = color,
-                        _ => {} // Ignore other keys
+    /// Reads brightness and sink volume every 5 seconds and applies what
+    /// the sliders queued
+    fn poll_levels(&mut self, ctx: &egui::Context) {
+        if let Some(Err(e)) = self.slider_writes.poll(ctx) {
+            eprintln!("Failed to apply slider: {}", e);
+        }
+
+        if let Some(levels) = self.levels.as_ref().and_then(BackgroundTask::poll) {
+            self.levels = None;
+            // A read that raced a write would yank the slider back
+            if !self.slider_writes.is_busy() {
+                match levels.brightness {
+                    Ok(_) if self.brightness_slider.is_dragging() => {}
+                    Ok(value) => {
+                        self.brightness_slider.value =
+                            value.clamp(self.brightness_slider.min, self.brightness_slider.max)
+                    }
+                    Err(e) => eprintln!("Failed to read brightness: {}", e),
+                }
+                match levels.sink {
+                    Ok(_) if self.volume_slider.is_dragging() => {}
+                    Ok((volume, muted)) => {
+                        self.volume_slider.value = volume.min(self.volume_slider.max);
+                        self.volume_slider.muted = muted;
                     }
+                    Err(e) => eprintln!("Failed to read volume: {}", e),
                 }
             }
         }
 
+        if self.levels.is_none()
+            && self
+                .last_levels_update
+                .is_none_or(|last| last.elapsed().as_secs() >= 5)
+        {
+            let brightness = self.brightness.clone();
+            let audio = self.audio.clone();
+            self.levels = Some(BackgroundTask::spawn(ctx, move || Levels {
+                brightness: brightness.brightness(),
+                sink: audio.sink_volume(),
+            }));
+            self.last_levels_update = Some(std::time::Instant::now());
+        }
+    }
+
+    fn show_brightness_slider(&mut self, ui: &mut egui::Ui) {
+        match self.brightness_slider.show(ui, &self.colors) {
+            Some(SliderEvent::Started(value))
+            | Some(SliderEvent::Changed(value))
+            | Some(SliderEvent::Committed(value)) => {
+                let brightness = self.brightness.clone();
+                self.slider_writes
+                    .push("brightness", move || brightness.set_brightness(value));
+                ui.ctx().request_repaint();
+            }
+            // Clicking the sun drops to the minimum, which comes as Committed
+            Some(SliderEvent::Muted(_)) | None => {}
+        }
+    }
+
+    fn show_volume_slider(&mut self, ui: &mut egui::Ui) {
+        let audio = self.audio.clone();
+        match self.volume_slider.show(ui, &self.colors) {
+            Some(SliderEvent::Started(value))
+            | Some(SliderEvent::Changed(value))
+            | Some(SliderEvent::Committed(value)) => {
+                self.slider_writes
+                    .push("volume", move || audio.set_sink_volume(value));
+            }
+            Some(SliderEvent::Muted(muted)) => {
+                self.slider_writes
+                    .push("mute", move || audio.set_sink_mute(muted));
+            }
+            None => return,
+        }
+        ui.ctx().request_repaint();
+    }
+
+    fn show_microphone_slider(&mut self, ui: &mut egui::Ui) {
+        match self.microphone_slider.show(ui, &self.colors) {
+            Some(SliderEvent::Started(value))
+            | Some(SliderEvent::Changed(value))
+            | Some(SliderEvent::Committed(value)) => {
+                let audio = self.audio.clone();
+                self.slider_writes
+                    .push("mic-volume", move || audio.set_source_volume(value));
+                ui.ctx().request_repaint();
+            }
+            Some(SliderEvent::Muted(muted)) => self.quick_settings.set_microphone_muted(muted),
+            None => {}
+        }
+    }
+
+    fn get_colors(&mut self) {
+        let theme = &self.config.theme;
+        let mut colors = load_palette(theme);
+        colors.apply_opacity(&theme.opacity);
+        if theme.min_contrast > 0.0 {
+            // Ratios run from 1 to 21, anything outside can't mean much else
+            let min_ratio = theme.min_contrast.clamp(1.0, contrast::MAX_RATIO);
+            contrast::enforce(&mut colors, min_ratio);
+        }
+
         self.colors = colors.clone();
         self.quick_settings.update_colors(colors.clone());
         self.active_actions.update_colors(colors);
     }
 }
 
+/// Loads the configured theme file into a palette, before any opacity or
+/// contrast adjustments
+fn load_palette(theme: &ThemeConfig) -> Palette {
+    let format = theme.format.as_deref().and_then(ThemeFormat::from_name);
+    let tokens = match theme_loader::load(&theme.path, format, &theme.scheme) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            // Keep going with the default palette
+            eprintln!("Error loading theme: {}", e);
+            Default::default()
+        }
+    };
+    Palette::from_tokens(&tokens, &theme.mapping())
+}
+
+/// Prints every role pair the widgets draw that is below the configured
+/// minimum contrast and returns false if there were any
+fn check_theme(theme: &ThemeConfig) -> bool {
+    let min_ratio = theme.min_contrast;
+    if !(1.0..=contrast::MAX_RATIO).contains(&min_ratio) {
+        eprintln!(
+            "min_contrast is {}, set it between 1 and {} to check the theme",
+            min_ratio,
+            contrast::MAX_RATIO
+        );
+        return false;
+    }
+    let mut palette = load_palette(theme);
+    palette.apply_opacity(&theme.opacity);
+    let failures = contrast::check(&palette, min_ratio);
+
+    for failure in &failures {
+        println!(
+            "{} on {}: {:.2}:1 (minimum {:.1}:1)",
+            failure.foreground.name(),
+            failure.background.name(),
+            failure.ratio,
+            min_ratio
+        );
+    }
+
+    if failures.is_empty() {
+        println!(
+            "All {} pairs meet {:.1}:1",
+            contrast::ROLE_PAIRS.len(),
+            min_ratio
+        );
+    }
+    failures.is_empty()
+}
+
 impl eframe::App for ActionCenterWidget {
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        // Clear to fully transparent so translucent surfaces show what's behind the window
+        egui::Rgba::TRANSPARENT.to_array()
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let now = std::time::Instant::now();
 
         // Update WiFi status every 5 seconds
         if now.duration_since(self.last_wifi_update).as_secs() >= 5 {
             self.quick_settings.update_wifi_state();
+            self.quick_settings.update_vpn_state();
+            self.quick_settings.update_power_profile_state();
             self.last_wifi_update = now;
         }
 
@@ -240,6 +484,34 @@ impl eframe::App for ActionCenterWidget {
             self.last_bluetooth_update = now;
         }
 
+        // Update microphone status every 5 seconds, it can be muted from outside the panel
+        if now.duration_since(self.last_microphone_update).as_secs() >= 5 {
+            self.quick_settings.update_microphone_state();
+            // Leave the slider alone while it's dragged or its value is still
+            // on the way out, or it would jump back
+            if !self.microphone_slider.is_dragging() && !self.slider_writes.is_busy() {
+                self.microphone_slider.value = self.quick_settings.state().mic_volume;
+            }
+            self.last_microphone_update = now;
+        }
+
+        self.poll_levels(ctx);
+        self.battery.poll(ctx);
+        self.storage.poll(ctx);
+        self.quick_settings.update_night_light(ctx);
+        self.active_actions.tick(ctx);
+        self.clock.tick(ctx);
+        self.handle_ipc();
+        self.microphone_slider.muted = self.quick_settings.state().mic_muted;
+
+        self.draw(ctx);
+        self.place_widgets();
+    }
+}
+
+impl ActionCenterWidget {
+    /// Lays out the whole panel for one frame
+    fn draw(&mut self, ctx: &egui::Context) {
         // Set the global visual style with our custom colors
         {
             let mut style = (*ctx.style()).clone();
@@ -248,92 +520,262 @@ impl eframe::App for ActionCenterWidget {
             ctx.set_style(style);
         }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            egui::ScrollArea::vertical()
-                .auto_shrink([false; 2])
-                .max_height(ui.available_height() - 8.0)
-                .show(ui, |ui| {
-                    ui.add_space(4.0);
-                    ui.vertical(|ui| {
-                        // Top section with equal-width widgets
-                        ui.horizontal(|ui| {
-                            ui.add_space(4.0);
-                            ui.set_min_width(ui.available_width());
-                            let available_width = ui.available_width() / 2.0 - 8.0; // Half spacing between columns
-
-                            // Left column - Quick Settings
-                            ui.scope(|ui| {
-                                ui.set_min_width(available_width);
-                                ui.set_max_width(available_width);
-                                self.quick_settings.show(ui);
-                            });
-
-                            ui.add_space(4.0);
-
-                            // Right column - Active Actions
-                            ui.scope(|ui| {
-                                ui.set_min_width(available_width);
-                                ui.set_max_width(available_width);
-                                self.active_actions.show(ui);
-                            });
-                        });
-                        //ADD seperator
-                        ui.add_space(8.0);
-                        ui.separator();
-                        ui.add_space(8.0);
-
-                        // Display section with brightness slider
-                        self.brightness_slider.show(ui, &self.colors);
-                        ui.add_space(8.0);
-
-                        // Sound section with volume slider
-                        self.volume_slider.show(ui, &self.colors);
-                        ui.add_space(8.0);
-
-                        ui.separator();
-                        ui.add_space(8.0);
-                        ui.horizontal(|ui| {
-                            ui.add_space(8.0);
-                            ui.label(RichText::new("Notifications").color(self.colors.on_surface));
-                            ui.add_space(8.0);
-                        });
+        egui::CentralPanel::default()
+            .frame(egui::Frame::central_panel(&ctx.style()).fill(self.colors.background))
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .max_height(ui.available_height() - 8.0)
+                    .show(ui, |ui| {
+                        ui.add_space(4.0);
+                        match self.page {
+                            Some(DetailPage::Wifi) => {
+                                if self.wifi_page.show(ui, &self.colors) {
+                                    self.page = None;
+                                    self.quick_settings.update_wifi_state();
+                                }
+                            }
+                            Some(DetailPage::Bluetooth) => {
+                                if self.bluetooth_page.show(ui, &self.colors) {
+                                    self.page = None;
+                                    self.quick_settings.update_bluetooth_state();
+                                }
+                            }
+                            Some(DetailPage::Vpn) => {
+                                if self.vpn_page.show(ui, &self.colors) {
+                                    self.page = None;
+                                    self.quick_settings.update_vpn_state();
+                                }
+                            }
+                            Some(DetailPage::Downloads) => {
+                                if self.downloads_page.show(ui, &self.colors) {
+                                    self.page = None;
+                                }
+                            }
+                            Some(DetailPage::Places) => {
+                                if self.places_page.show(ui, &self.colors) {
+                                    self.page = None;
+                                }
+                            }
+                            None => self.show_main_page(ui),
+                        }
                     });
-                });
-        });
-        self.place_widgets();
+            });
     }
-}
 
-impl Default for ActionCenterWidget {
-    fn default() -> Self {
-        let colors = Colors::default();
+    fn new(config: Config, runner: Arc<dyn CommandRunner>) -> Self {
+        let colors = Palette::default();
+        let audio = AudioBackend::new(runner.clone());
+        let brightness = BrightnessBackend::new(runner.clone());
+        let power_profiles = PowerProfiles::new(
+            runner.clone(),
+            shellexpand::tilde(&config.power_profile.platform_profile)
+                .to_string()
+                .into(),
+        );
+        let night_light = NightLight::new(config.night_light.clone());
+        let file_actions = FileActions::new(runner.clone(), config.downloads.drag_command.clone());
         let mut widget = Self {
             colors: colors.clone(),
             positioned: false,
-            brightness_slider: IconSlider::new(50.0, SUN.to_string()).with_title("Display"),
-            volume_slider: IconSlider::new(50.0, SPEAKER_HIGH.to_string()).with_title("Sound"),
-            quick_settings: QuickSettings::new(colors.clone()),
-            active_actions: ActiveActions::new(colors),
+            brightness_slider: IconSlider::new(50.0, SUN.to_string())
+                .with_title("Display")
+                .with_kind(SliderKind::Brightness)
+                .with_range(1.0, 100.0)
+                .with_value_label(),
+            volume_slider: IconSlider::new(50.0, SPEAKER_HIGH.to_string())
+                .with_title("Sound")
+                .with_kind(SliderKind::Volume)
+                .with_step(2.0)
+                .with_value_label(),
+            device_picker: DevicePicker::new(audio.clone()),
+            volume_mixer: VolumeMixer::new(audio.clone()),
+            microphone_slider: IconSlider::new(0.0, MICROPHONE.to_string())
+                .with_title("Microphone")
+                .with_kind(SliderKind::Microphone)
+                .with_step(2.0)
+                .with_value_label(),
+            battery: BatterySection::new(&config.battery),
+            storage: StorageSection::new(StorageBackend::new(runner.clone()), &config.storage),
+            session_menu: SessionMenu::new(config.session.clone()),
+            clock: Clock::new(config.clock.clone(), config.calendar.clone()),
+            window_switcher: WindowSwitcher::new(runner.clone()),
+            resource_monitor: ResourceMonitor::new(&config.resources),
+            quick_settings: QuickSettings::new(
+                colors.clone(),
+                audio.clone(),
+                WifiBackend::new(runner.clone()),
+                BluetoothBackend::new(runner.clone()),
+                VpnBackend::new(runner.clone()),
+                power_profiles,
+                night_light,
+            ),
+            page: None,
+            wifi_page: WifiPage::new(WifiBackend::new(runner.clone())),
+            bluetooth_page: BluetoothPage::new(BluetoothBackend::new(runner.clone())),
+            vpn_page: VpnPage::new(VpnBackend::new(runner.clone())),
+            downloads_page: DownloadsPage::new(
+                file_actions.clone(),
+                downloads::directory(config.downloads.directory.as_deref()),
+                config.downloads.limit,
+            ),
+            places_page: PlacesPage::new(file_actions),
+            active_actions: ActiveActions::new(
+                colors,
+                Caffeine::new(runner.clone()),
+                config.caffeine.timeout(),
+                Screenshotter::new(runner, config.screenshot.clone()),
+            ),
             last_wifi_update: std::time::Instant::now(),
             last_bluetooth_update: std::time::Instant::now(),
+            last_microphone_update: std::time::Instant::now(),
+            audio,
+            brightness,
+            slider_writes: WriteQueue::default(),
+            levels: None,
+            last_levels_update: None,
+            ipc: None,
+            config,
         };
         widget.get_colors();
+        widget.microphone_slider.value = widget.quick_settings.state().mic_volume;
         widget
     }
 }
 
+impl Default for ActionCenterWidget {
+    fn default() -> Self {
+        Self::new(Config::load(), Arc::new(SystemRunner))
+    }
+}
+
+/// Closes the window on Ctrl+C like any other quit, so the children that
+/// Drop impls stop (hyprsunset, systemd-inhibit) and the socket go away
+fn close_on_ctrl_c(ctx: egui::Context) {
+    if let Err(e) = ctrlc::set_handler(move || {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        ctx.request_repaint();
+    }) {
+        eprintln!("Failed to set Ctrl+C handler: {}", e);
+    }
+}
+
+/// Registers the phosphor icon font with egui
+fn install_fonts(ctx: &egui::Context) {
+    let mut fonts = egui::FontDefinitions::default();
+    egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
+    ctx.set_fonts(fonts);
+}
+
+#[derive(Parser)]
+#[command(about = "Action center panel for Hyprland")]
+struct Args {
+    /// Report foreground/background pairs in the theme that fail the contrast check, then exit
+    #[arg(long)]
+    check_theme: bool,
+
+    /// Send a command to the running panel (e.g. "mic-mute toggle" or "state") and print its reply
+    #[arg(long, value_name = "COMMAND")]
+    msg: Option<String>,
+}
+
 fn main() -> Result<(), eframe::Error> {
-    let options = eframe::NativeOptions::default();
+    let args = Args::parse();
+    if args.check_theme {
+        let passed = check_theme(&Config::load().theme);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    if let Some(message) = args.msg {
+        match ipc::send(&message) {
+            Ok(reply) => println!("{}", reply),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("Action Center")
+            .with_transparent(true)
+            .with_decorations(false),
+        ..Default::default()
+    };
     eframe::run_native(
         "Action Center",
         options,
         Box::new(|cc| {
-            // Register the phosphor icon font with egui
-            let mut fonts = egui::FontDefinitions::default();
-            egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
-            cc.egui_ctx.set_fonts(fonts);
+            install_fonts(&cc.egui_ctx);
+            close_on_ctrl_c(cc.egui_ctx.clone());
 
-            Ok(Box::new(ActionCenterWidget::default()))
+            Ok(Box::new(ActionCenterWidget {
+                ipc: IpcServer::start(cc.egui_ctx.clone()),
+                ..Default::default()
+            }))
         }),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use command_runner::FakeRunner;
+
+    fn widget(opacity: f32) -> ActionCenterWidget {
+        let mut config = Config::default();
+        config.theme.path = format!(
+            "{}/tests/fixtures/theme/pywal.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        config.theme.format = Some("pywal".to_string());
+        config
+            .theme
+            .opacity
+            .insert("background".to_string(), opacity);
+        // Every command fails, like on a machine with none of the tools
+        ActionCenterWidget::new(config, Arc::new(FakeRunner::default()))
+    }
+
+    fn render(widget: &mut ActionCenterWidget) -> (egui::Context, egui::FullOutput) {
+        let ctx = egui::Context::default();
+        install_fonts(&ctx);
+        let input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(370.0, 1010.0),
+            )),
+            ..Default::default()
+        };
+        let output = ctx.run(input, |ctx| widget.draw(ctx));
+        (ctx, output)
+    }
+
+    #[test]
+    fn renders_main_page() {
+        let mut widget = widget(1.0);
+        let (ctx, output) = render(&mut widget);
+        assert!(!output.shapes.is_empty());
+
+        let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+        assert!(!primitives.is_empty());
+    }
+
+    #[test]
+    fn panel_background_is_translucent() {
+        let mut widget = widget(0.5);
+        assert_eq!(widget.colors.background.a(), 128);
+        let (_, output) = render(&mut widget);
+
+        let fills: Vec<egui::Color32> = output
+            .shapes
+            .iter()
+            .filter_map(|clipped| match &clipped.shape {
+                egui::Shape::Rect(rect) => Some(rect.fill),
+                _ => None,
+            })
+            .collect();
+        assert!(fills.contains(&widget.colors.background));
+    }
+}
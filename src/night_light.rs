@@ -0,0 +1,256 @@
+use eframe::egui;
+use egui_phosphor::regular::*;
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+use crate::config::NightLightConfig;
+use crate::local_time::{self, LocalTime};
+use crate::palette::Palette;
+use crate::slider_controls::{IconSlider, SliderEvent};
+use crate::sun::{self, SunTimes};
+
+/// How often the schedule is checked
+const UPDATE_INTERVAL: Duration = Duration::from_secs(30);
+
+const MIN_TEMPERATURE: f32 = 1500.0;
+const MAX_TEMPERATURE: f32 = 6500.0;
+
+/// Warms the screen by running hyprsunset/wlsunset, by hand or on a schedule.
+/// Switching by hand holds until the schedule's next change.
+pub struct NightLight {
+    config: NightLightConfig,
+    slider: IconSlider,
+    child: Option<Child>,
+    /// State picked by hand, overriding the schedule
+    manual: Option<bool>,
+    /// What the schedule asked for last time it was checked
+    last_scheduled: Option<bool>,
+    /// Minute of the day the schedule next switches
+    next_change: Option<u32>,
+    last_update: Option<Instant>,
+}
+
+impl NightLight {
+    pub fn new(config: NightLightConfig) -> Self {
+        let slider = IconSlider::new(config.temperature as f32, THERMOMETER.to_string())
+            .with_range(MIN_TEMPERATURE, MAX_TEMPERATURE)
+            .with_step(100.0);
+        Self {
+            config,
+            slider,
+            child: None,
+            manual: None,
+            last_scheduled: None,
+            next_change: None,
+            last_update: None,
+        }
+    }
+
+    /// Whether the schedule wants the night light on now, and when that
+    /// changes. `None` without a schedule.
+    fn scheduled(&self, now: &LocalTime) -> Option<(bool, Option<u32>)> {
+        let (start, end) = match self.config.schedule.as_str() {
+            "fixed" => (
+                local_time::parse_clock_time(&self.config.start)?,
+                local_time::parse_clock_time(&self.config.end)?,
+            ),
+            "sun" => {
+                let times =
+                    sun::sun_times(now.day_of_year, self.config.latitude, self.config.longitude);
+                match times {
+                    SunTimes::Normal { sunrise, sunset } => {
+                        let offset = now.utc_offset as f64 / 60.0;
+                        let local = |utc: f64| (utc + offset).rem_euclid(1440.0) as u32;
+                        (local(sunset), local(sunrise))
+                    }
+                    SunTimes::AlwaysUp => return Some((false, None)),
+                    SunTimes::AlwaysDown => return Some((true, None)),
+                }
+            }
+            _ => return None,
+        };
+        let (on, next) = sun::window_state(now.minute_of_day(), start, end);
+        Some((on, Some(next)))
+    }
+
+    /// Follows the schedule and notices a command that died on its own
+    pub fn tick(&mut self, ctx: &egui::Context) {
+        if let Some(child) = &mut self.child
+            && !matches!(child.try_wait(), Ok(None))
+        {
+            eprintln!("Night light command exited");
+            self.child = None;
+        }
+
+        if self
+            .last_update
+            .is_some_and(|last| last.elapsed() < UPDATE_INTERVAL)
+        {
+            return;
+        }
+        self.last_update = Some(Instant::now());
+        ctx.request_repaint_after(UPDATE_INTERVAL);
+
+        let scheduled = self.scheduled(&LocalTime::now());
+        self.next_change = scheduled.and_then(|(_, next)| next);
+        let scheduled_on = scheduled.map(|(on, _)| on);
+
+        // A switch by hand lasts until the schedule changes its mind
+        if scheduled_on != self.last_scheduled {
+            self.last_scheduled = scheduled_on;
+            self.manual = None;
+        }
+        let on = self.manual.or(scheduled_on).unwrap_or(false);
+        if on != self.is_on() {
+            self.apply(on);
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.child.is_some()
+    }
+
+    pub fn toggle(&mut self) {
+        let on = !self.is_on();
+        self.manual = Some(on);
+        self.apply(on);
+    }
+
+    fn apply(&mut self, on: bool) {
+        self.stop();
+        if on {
+            self.start();
+        }
+    }
+
+    fn start(&mut self) {
+        let command = self
+            .config
+            .command
+            .replace("{temperature}", &format!("{:.0}", self.slider.value));
+        // exec so killing the child stops the command itself, not just sh
+        match Command::new("sh")
+            .arg("-c")
+            .arg(format!("exec {}", command))
+            .spawn()
+        {
+            Ok(child) => self.child = Some(child),
+            Err(e) => eprintln!("Failed to run {}: {}", command, e),
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    /// Tile subtitle, e.g. "On until 07:12"
+    pub fn status(&self) -> String {
+        let state = if self.is_on() { "On" } else { "Off" };
+        // The time only means something while following the schedule
+        match self.next_change {
+            Some(next) if self.manual.is_none() => {
+                format!("{} until {}", state, local_time::format_clock_time(next))
+            }
+            _ => state.to_string(),
+        }
+    }
+
+    /// Colour temperature slider, drawn while the night light is on
+    pub fn show_slider(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        if !self.is_on() {
+            return;
+        }
+        self.slider.title = Some(format!("Night Light · {:.0} K", self.slider.value));
+        // The command only takes the temperature at startup, so restart it
+        // once the user lets go
+        if let Some(SliderEvent::Committed(_)) = self.slider.show(ui, colors) {
+            self.apply(true);
+        }
+    }
+}
+
+impl Drop for NightLight {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A local time on 21 June at `hour:minute`, `offset_hours` east of UTC
+    fn at(hour: u32, minute: u32, offset_hours: i64) -> LocalTime {
+        LocalTime {
+            year: 2024,
+            month: 6,
+            day: 21,
+            hour,
+            minute,
+            second: 0,
+            weekday: 5,
+            day_of_year: 172,
+            utc_offset: offset_hours * 3600,
+            timestamp: 0,
+        }
+    }
+
+    fn night_light(schedule: &str, latitude: f64, longitude: f64) -> NightLight {
+        NightLight::new(NightLightConfig {
+            schedule: schedule.to_string(),
+            start: "21:30".to_string(),
+            end: "06:15".to_string(),
+            latitude,
+            longitude,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn manual_has_no_schedule() {
+        assert_eq!(
+            night_light("manual", 0.0, 0.0).scheduled(&at(23, 0, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn fixed_schedule_wraps_past_midnight() {
+        let light = night_light("fixed", 0.0, 0.0);
+        assert_eq!(
+            light.scheduled(&at(12, 0, 0)),
+            Some((false, Some(21 * 60 + 30)))
+        );
+        assert_eq!(
+            light.scheduled(&at(23, 0, 0)),
+            Some((true, Some(6 * 60 + 15)))
+        );
+        assert_eq!(
+            light.scheduled(&at(3, 0, 0)),
+            Some((true, Some(6 * 60 + 15)))
+        );
+    }
+
+    #[test]
+    fn sun_schedule_in_local_time() {
+        // Tokyo, where sunrise is on the previous UTC day: sunset 19:00 and
+        // sunrise 04:25 local
+        let light = night_light("sun", 35.6762, 139.6503);
+        let (on, next) = light.scheduled(&at(2, 0, 9)).unwrap();
+        assert!(on);
+        assert!(next.unwrap().abs_diff(4 * 60 + 25) <= 5);
+
+        let (on, next) = light.scheduled(&at(12, 0, 9)).unwrap();
+        assert!(!on);
+        assert!(next.unwrap().abs_diff(19 * 60) <= 5);
+    }
+
+    #[test]
+    fn sun_schedule_at_the_poles() {
+        let light = night_light("sun", 69.6492, 18.9553);
+        assert_eq!(light.scheduled(&at(23, 0, 2)), Some((false, None)));
+    }
+}
@@ -0,0 +1,21 @@
+/// Splits a line of `nmcli -t` (terse) output into fields. Fields are
+/// separated by `:`, and a literal `:` or `\` inside a field is escaped
+/// with a backslash, as in BSSIDs and some SSIDs.
+pub fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    field.push(escaped);
+                }
+            }
+            ':' => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
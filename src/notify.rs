@@ -0,0 +1,37 @@
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Urgency {
+    Normal,
+    Critical,
+}
+
+impl Urgency {
+    fn name(self) -> &'static str {
+        match self {
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/// Shows a desktop notification through `notify-send`. `icon` is an icon
+/// name or an image path. Runs on its own thread so a slow notification
+/// daemon never holds up a frame.
+pub fn send(summary: &str, body: &str, icon: Option<&str>, urgency: Urgency) {
+    let mut args = vec![
+        "--app-name=Action Center".to_string(),
+        format!("--urgency={}", urgency.name()),
+    ];
+    if let Some(icon) = icon {
+        args.push(format!("--icon={}", icon));
+    }
+    args.push(summary.to_string());
+    args.push(body.to_string());
+
+    std::thread::spawn(move || {
+        if let Err(e) = Command::new("notify-send").args(&args).output() {
+            eprintln!("Failed to run notify-send: {}", e);
+        }
+    });
+}
@@ -0,0 +1,189 @@
+use egui::Color32;
+use std::collections::HashMap;
+
+/// Declares every Material 3 color role once and generates the `Palette`
+/// struct, the `Role` enum and the name lookups from that single list.
+macro_rules! palette_roles {
+    ($($field:ident => $variant:ident, $name:literal, $default:literal;)*) => {
+        /// The full Material You palette, one field per Material 3 color role
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct Palette {
+            $(pub $field: Color32,)*
+        }
+
+        impl Default for Palette {
+            fn default() -> Self {
+                Self {
+                    $($field: hex($default),)*
+                }
+            }
+        }
+
+        /// Identifies a single color role in the palette
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Role {
+            $($variant,)*
+        }
+
+        impl Role {
+            pub const ALL: &'static [Role] = &[$(Role::$variant,)*];
+
+            /// The Matugen token name for this role, e.g. `surface_container_high`
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Role::$variant => $name,)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Role> {
+                match name {
+                    $($name => Some(Role::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl Palette {
+            pub fn get(&self, role: Role) -> Color32 {
+                match role {
+                    $(Role::$variant => self.$field,)*
+                }
+            }
+
+            pub fn set(&mut self, role: Role, color: Color32) {
+                match role {
+                    $(Role::$variant => self.$field = color,)*
+                }
+            }
+        }
+    };
+}
+
+// Defaults are the dark scheme Matugen generates from its stock blue source color
+palette_roles! {
+    background => Background, "background", 0x000000;
+    error => Error, "error", 0xffb4ab;
+    error_container => ErrorContainer, "error_container", 0x93000a;
+    inverse_on_surface => InverseOnSurface, "inverse_on_surface", 0x2c3134;
+    inverse_primary => InversePrimary, "inverse_primary", 0x186584;
+    inverse_surface => InverseSurface, "inverse_surface", 0xdfe3e7;
+    on_background => OnBackground, "on_background", 0xdfe3e7;
+    on_error => OnError, "on_error", 0x690005;
+    on_error_container => OnErrorContainer, "on_error_container", 0xffdad6;
+    on_primary => OnPrimary, "on_primary", 0x003548;
+    on_primary_container => OnPrimaryContainer, "on_primary_container", 0xc1e8ff;
+    on_primary_fixed => OnPrimaryFixed, "on_primary_fixed", 0x001e2b;
+    on_primary_fixed_variant => OnPrimaryFixedVariant, "on_primary_fixed_variant", 0x004d67;
+    on_secondary => OnSecondary, "on_secondary", 0x1f333d;
+    on_secondary_container => OnSecondaryContainer, "on_secondary_container", 0xd0e6f3;
+    on_secondary_fixed => OnSecondaryFixed, "on_secondary_fixed", 0x091e27;
+    on_secondary_fixed_variant => OnSecondaryFixedVariant, "on_secondary_fixed_variant", 0x364954;
+    on_surface => OnSurface, "on_surface", 0xdfe3e7;
+    on_surface_variant => OnSurfaceVariant, "on_surface_variant", 0xc0c7cd;
+    on_tertiary => OnTertiary, "on_tertiary", 0x302c4c;
+    on_tertiary_container => OnTertiaryContainer, "on_tertiary_container", 0xe5deff;
+    on_tertiary_fixed => OnTertiaryFixed, "on_tertiary_fixed", 0x1b1736;
+    on_tertiary_fixed_variant => OnTertiaryFixedVariant, "on_tertiary_fixed_variant", 0x474364;
+    outline => Outline, "outline", 0x8a9297;
+    outline_variant => OutlineVariant, "outline_variant", 0x40484c;
+    primary => Primary, "primary", 0x8dcff2;
+    primary_container => PrimaryContainer, "primary_container", 0x004d67;
+    primary_fixed => PrimaryFixed, "primary_fixed", 0xc1e8ff;
+    primary_fixed_dim => PrimaryFixedDim, "primary_fixed_dim", 0x8dcff2;
+    scrim => Scrim, "scrim", 0x000000;
+    secondary => Secondary, "secondary", 0xb5cad7;
+    secondary_container => SecondaryContainer, "secondary_container", 0x364954;
+    secondary_fixed => SecondaryFixed, "secondary_fixed", 0xd0e6f3;
+    secondary_fixed_dim => SecondaryFixedDim, "secondary_fixed_dim", 0xb5cad7;
+    shadow => Shadow, "shadow", 0x000000;
+    source_color => SourceColor, "source_color", 0x129ac9;
+    surface => Surface, "surface", 0x0f1417;
+    surface_bright => SurfaceBright, "surface_bright", 0x353a3d;
+    surface_container => SurfaceContainer, "surface_container", 0x1b2023;
+    surface_container_high => SurfaceContainerHigh, "surface_container_high", 0x262b2e;
+    surface_container_highest => SurfaceContainerHighest, "surface_container_highest", 0x313539;
+    surface_container_low => SurfaceContainerLow, "surface_container_low", 0x171c1f;
+    surface_container_lowest => SurfaceContainerLowest, "surface_container_lowest", 0x0a0f12;
+    surface_dim => SurfaceDim, "surface_dim", 0x0f1417;
+    surface_tint => SurfaceTint, "surface_tint", 0x8dcff2;
+    surface_variant => SurfaceVariant, "surface_variant", 0x40484c;
+    tertiary => Tertiary, "tertiary", 0xc8c2ea;
+    tertiary_container => TertiaryContainer, "tertiary_container", 0x474364;
+    tertiary_fixed => TertiaryFixed, "tertiary_fixed", 0xe5deff;
+    tertiary_fixed_dim => TertiaryFixedDim, "tertiary_fixed_dim", 0xc8c2ea;
+}
+
+fn hex(rgb: u32) -> Color32 {
+    Color32::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+/// The mapping used when the config doesn't override it. The panel has always
+/// drawn its surfaces from the lower container tones and its accents from the
+/// fixed variants, so those roles are redirected to keep the original look.
+pub fn default_mapping() -> HashMap<String, String> {
+    [
+        ("background", "surface_container_lowest"),
+        ("surface", "surface_container_low"),
+        ("on_surface", "on_surface_variant"),
+        ("primary", "primary_fixed_dim"),
+        ("on_primary", "on_primary_fixed"),
+        ("secondary", "secondary_fixed_dim"),
+        ("on_secondary", "on_secondary_fixed"),
+    ]
+    .into_iter()
+    .map(|(role, token)| (role.to_string(), token.to_string()))
+    .collect()
+}
+
+impl Palette {
+    /// Builds a palette from parsed theme tokens. Every role first takes the
+    /// token of the same name, then `mapping` (role name -> token name)
+    /// redirects individual roles to other tokens. Missing tokens fall back to
+    /// the built-in defaults.
+    pub fn from_tokens(
+        tokens: &HashMap<String, Color32>,
+        mapping: &HashMap<String, String>,
+    ) -> Self {
+        let defaults = Palette::default();
+        let lookup = |token: &str| {
+            tokens
+                .get(token)
+                .copied()
+                .or_else(|| Role::from_name(token).map(|role| defaults.get(role)))
+        };
+
+        let mut palette = Palette::default();
+        for &role in Role::ALL {
+            if let Some(color) = lookup(role.name()) {
+                palette.set(role, color);
+            }
+        }
+
+        for (role_name, token) in mapping {
+            let Some(role) = Role::from_name(role_name) else {
+                eprintln!("Unknown palette role in color mapping: {}", role_name);
+                continue;
+            };
+            match lookup(token) {
+                Some(color) => palette.set(role, color),
+                None => eprintln!("Unknown theme token for {}: {}", role_name, token),
+            }
+        }
+
+        palette
+    }
+
+    /// Scales the alpha of individual roles, e.g. `{"background": 0.8}` for a
+    /// see-through panel. Color32 is premultiplied, so `gamma_multiply` scales
+    /// the color channels along with the alpha and the result blends correctly.
+    pub fn apply_opacity(&mut self, opacity: &HashMap<String, f32>) {
+        for (role_name, alpha) in opacity {
+            let Some(role) = Role::from_name(role_name) else {
+                eprintln!("Unknown palette role in opacity: {}", role_name);
+                continue;
+            };
+            let color = self.get(role).gamma_multiply(alpha.clamp(0.0, 1.0));
+            self.set(role, color);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::files::{percent_decode, uri_to_path};
+use crate::local_time::{parse_date, parse_time};
+use crate::xdg_dirs;
+
+/// A GTK bookmark, local or remote
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    pub uri: String,
+    /// The label given in the file, or the last part of the path
+    pub name: String,
+}
+
+/// An entry from recently-used.xbel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecentFile {
+    pub uri: String,
+    /// Set for file:// URIs
+    pub path: Option<PathBuf>,
+    pub name: String,
+    pub mime_type: Option<String>,
+    /// Last modified or visited, whichever is later, as a unix timestamp
+    pub used: Option<i64>,
+}
+
+/// Bookmarks from `gtk-3.0/bookmarks` in the config dir
+pub fn bookmarks() -> Result<Vec<Bookmark>, String> {
+    let path = xdg_dirs::config_home().join("gtk-3.0/bookmarks");
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(parse_bookmarks(&text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Recently used files that still exist, most recent first
+pub fn recent_files(limit: usize) -> Result<Vec<RecentFile>, String> {
+    let path = xdg_dirs::data_home().join("recently-used.xbel");
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let mut files: Vec<RecentFile> = parse_xbel(&text)
+        .into_iter()
+        .filter(|file| file.path.as_ref().is_none_or(|path| path.exists()))
+        .collect();
+    files.sort_by_key(|file| std::cmp::Reverse(file.used));
+    files.truncate(limit);
+    Ok(files)
+}
+
+/// One bookmark per line, a URI optionally followed by a space and a label
+pub fn parse_bookmarks(text: &str) -> Vec<Bookmark> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() {
+                return None;
+            }
+            let (uri, label) = match line.split_once(' ') {
+                Some((uri, label)) => (uri, Some(label.trim())),
+                None => (line, None),
+            };
+            let name = match label.filter(|label| !label.is_empty()) {
+                Some(label) => label.to_string(),
+                None => last_segment(uri),
+            };
+            Some(Bookmark {
+                uri: uri.to_string(),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Decoded last path segment of a URI, or the host for one like sftp://host/
+fn last_segment(uri: &str) -> String {
+    let trimmed = uri.trim_end_matches('/');
+    let segment = trimmed.rsplit('/').next().unwrap_or(trimmed);
+    if segment.is_empty() {
+        uri.to_string()
+    } else {
+        percent_decode(segment)
+    }
+}
+
+/// Reads the `<bookmark>` elements of an XBEL file. Only the bits the list
+/// needs: href, the timestamps and the shared-mime-info type.
+pub fn parse_xbel(text: &str) -> Vec<RecentFile> {
+    let mut files = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<bookmark ") {
+        rest = &rest[start..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        let body = if tag.ends_with('/') {
+            ""
+        } else {
+            let body_end = rest.find("</bookmark>").unwrap_or(rest.len());
+            &rest[tag_end..body_end]
+        };
+        rest = &rest[tag_end..];
+
+        let Some(uri) = attribute(tag, "href") else {
+            continue;
+        };
+        let used = ["modified", "visited", "added"]
+            .iter()
+            .filter_map(|name| attribute(tag, name))
+            .filter_map(|time| parse_timestamp(&time))
+            .max();
+        let mime_type = body.find("<mime:mime-type ").and_then(|start| {
+            let tag = &body[start..];
+            attribute(&tag[..tag.find('>').unwrap_or(tag.len())], "type")
+        });
+        files.push(RecentFile {
+            path: uri_to_path(&uri),
+            name: last_segment(&uri),
+            uri,
+            mime_type,
+            used,
+        });
+    }
+    files
+}
+
+/// The unescaped value of `name="..."` in a start tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut search = tag;
+    loop {
+        let found = search.find(name)?;
+        let before = search[..found].chars().next_back();
+        let after = &search[found + name.len()..];
+        if before.is_some_and(char::is_whitespace)
+            && let Some(value) = after.trim_start().strip_prefix('=')
+        {
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = &value[1..];
+            let end = value.find(quote)?;
+            return Some(unescape(&value[..end]));
+        }
+        search = after;
+    }
+}
+
+/// XML character and entity references
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|decimal| decimal.parse()))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// ISO 8601 like "2024-03-01T10:22:33.123456Z" or with a "+01:00" offset,
+/// as a unix timestamp
+pub fn parse_timestamp(text: &str) -> Option<i64> {
+    let date = parse_date(&text.get(..10)?.replace('-', ""))?;
+    if text.as_bytes().get(10) != Some(&b'T') {
+        return None;
+    }
+    let time = parse_time(&text.get(11..19)?.replace(':', ""))?;
+    let zone = text[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match zone.chars().next() {
+        None | Some('Z') => 0,
+        Some(sign @ ('+' | '-')) => {
+            let digits = zone[1..].replace(':', "");
+            let hours: i64 = digits.get(..2)?.parse().ok()?;
+            let minutes: i64 = digits.get(2..4).map_or(Some(0), |m| m.parse().ok())?;
+            let offset = hours * 3600 + minutes * 60;
+            if sign == '-' { -offset } else { offset }
+        }
+        Some(_) => return None,
+    };
+    Some(date * 86400 + time - offset)
+}
+
+/// Whether an entry matches the filter box, ignoring case
+pub fn matches(filter: &str, fields: &[&str]) -> bool {
+    let filter = filter.trim().to_lowercase();
+    filter.is_empty()
+        || fields
+            .iter()
+            .any(|field| field.to_lowercase().contains(&filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_time::days_from_civil;
+
+    fn fixture(name: &str) -> String {
+        fs::read_to_string(format!(
+            "{}/tests/fixtures/places/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    fn at(year: i32, month: u32, day: u32, seconds: i64) -> i64 {
+        days_from_civil(year, month, day) * 86400 + seconds
+    }
+
+    #[test]
+    fn bookmarks_use_label_or_last_segment() {
+        let names: Vec<(String, String)> = parse_bookmarks(&fixture("bookmarks"))
+            .into_iter()
+            .map(|bookmark| (bookmark.uri, bookmark.name))
+            .collect();
+        let expected = [
+            ("file:///home/alex/Documents", "Documents"),
+            ("file:///home/alex/Projects/hermit", "Hermit"),
+            ("file:///home/alex/Music%20Library", "Music Library"),
+            ("sftp://nas.local/", "nas.local"),
+            ("smb://nas.local/share/photos", "Photos on the NAS"),
+        ];
+        assert_eq!(
+            names,
+            expected.map(|(uri, name)| (uri.to_string(), name.to_string()))
+        );
+    }
+
+    #[test]
+    fn reads_recently_used() {
+        let files = parse_xbel(&fixture("recently-used.xbel"));
+        assert_eq!(
+            files,
+            [
+                RecentFile {
+                    uri:
+                        "file:///home/alex/Pictures/Screenshots/Screenshot%20from%202024-03-01.png"
+                            .to_string(),
+                    path: Some(PathBuf::from(
+                        "/home/alex/Pictures/Screenshots/Screenshot from 2024-03-01.png"
+                    )),
+                    name: "Screenshot from 2024-03-01.png".to_string(),
+                    mime_type: Some("image/png".to_string()),
+                    used: Some(at(2024, 3, 1, 10 * 3600 + 22 * 60 + 33)),
+                },
+                // Visited later than modified, in +01:00
+                RecentFile {
+                    uri: "file:///home/alex/Documents/Tom%20&%20Jerry.pdf".to_string(),
+                    path: Some(PathBuf::from("/home/alex/Documents/Tom & Jerry.pdf")),
+                    name: "Tom & Jerry.pdf".to_string(),
+                    mime_type: Some("application/pdf".to_string()),
+                    used: Some(at(2024, 3, 2, 8 * 3600 + 30 * 60)),
+                },
+                RecentFile {
+                    uri: "https://example.org/report.html".to_string(),
+                    path: None,
+                    name: "report.html".to_string(),
+                    mime_type: None,
+                    used: Some(at(2024, 2, 20, 12 * 3600)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("1970-01-01T01:00:00+01:00"), Some(0));
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00.5-0130"), Some(5400));
+        assert_eq!(parse_timestamp("1970-01-01 00:00:00Z"), None);
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00 garbage"), None);
+        assert_eq!(parse_timestamp("1970-01"), None);
+    }
+
+    #[test]
+    fn filter_ignores_case() {
+        assert!(matches("", &["anything"]));
+        assert!(matches(" jerry ", &["notes", "Tom & Jerry.pdf"]));
+        assert!(!matches("cat", &["Tom & Jerry.pdf"]));
+    }
+}
@@ -0,0 +1,362 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::files::{self, FileActions, FileKind};
+use crate::icons::IconCache;
+use crate::palette::Palette;
+use crate::places::{self, RecentFile};
+use crate::xdg_dirs;
+
+/// recently-used.xbel keeps a lot of history; the panel only needs the top
+const RECENT_LIMIT: usize = 50;
+
+/// A folder to jump to: home, a user dir or a GTK bookmark
+struct Place {
+    name: String,
+    uri: String,
+    /// Where it points, shown under the name
+    location: String,
+    icon: &'static str,
+}
+
+enum PlaceAction {
+    Open(String),
+    ShowInFolder(std::path::PathBuf),
+}
+
+/// Detail page with bookmarks, user dirs and recent files, opened from the
+/// folder button
+pub struct PlacesPage {
+    actions: FileActions,
+    places: Vec<Place>,
+    recent: Vec<RecentFile>,
+    icons: IconCache,
+    filter: String,
+    /// Focus the filter box on the next frame, so typing narrows right away
+    focus_filter: bool,
+    error: Option<String>,
+}
+
+impl PlacesPage {
+    pub fn new(actions: FileActions) -> Self {
+        Self {
+            actions,
+            places: Vec::new(),
+            recent: Vec::new(),
+            icons: IconCache::default(),
+            filter: String::new(),
+            focus_filter: false,
+            error: None,
+        }
+    }
+
+    /// Re-reads the bookmark files and clears the filter
+    pub fn refresh(&mut self) {
+        self.filter.clear();
+        self.focus_filter = true;
+        self.error = None;
+
+        let home = xdg_dirs::home();
+        self.places = vec![Place {
+            name: "Home".to_string(),
+            uri: files::file_uri(&home),
+            location: home.display().to_string(),
+            icon: HOUSE,
+        }];
+        for (key, path) in xdg_dirs::user_dirs() {
+            if !path.is_dir() {
+                continue;
+            }
+            self.places.push(Place {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| key.clone()),
+                uri: files::file_uri(&path),
+                location: tilde(&path.display().to_string()),
+                icon: user_dir_icon(&key),
+            });
+        }
+        match places::bookmarks() {
+            Ok(bookmarks) => {
+                for bookmark in bookmarks {
+                    let location = files::uri_to_path(&bookmark.uri)
+                        .map(|path| tilde(&path.display().to_string()))
+                        .unwrap_or_else(|| bookmark.uri.clone());
+                    self.places.push(Place {
+                        name: bookmark.name,
+                        uri: bookmark.uri,
+                        location,
+                        icon: BOOKMARK_SIMPLE,
+                    });
+                }
+            }
+            Err(e) => self.error = Some(e),
+        }
+
+        match places::recent_files(RECENT_LIMIT) {
+            Ok(recent) => self.recent = recent,
+            Err(e) => {
+                self.recent.clear();
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn run(&mut self, action: PlaceAction) {
+        let result = match action {
+            PlaceAction::Open(uri) => match files::uri_to_path(&uri) {
+                Some(path) => self.actions.open(&path),
+                None => self.actions.open_uri(&uri),
+            },
+            PlaceAction::ShowInFolder(path) => {
+                // Usually quick, and the page has nothing else to wait on
+                let actions = self.actions.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = actions.show_in_folder(&path) {
+                        eprintln!("Failed to show {}: {}", path.display(), e);
+                    }
+                });
+                Ok(())
+            }
+        };
+        self.error = result.err();
+    }
+
+    /// Returns true when the user asked to go back to the main page
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) -> bool {
+        let mut back = false;
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    Button::new(
+                        RichText::new(ARROW_LEFT)
+                            .size(18.0)
+                            .color(colors.on_surface),
+                    )
+                    .fill(Color32::TRANSPARENT),
+                )
+                .on_hover_text("Back")
+                .clicked()
+            {
+                back = true;
+            }
+            ui.label(RichText::new("Files").size(18.0).color(colors.on_surface));
+        });
+        ui.add_space(8.0);
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new(MAGNIFYING_GLASS)
+                    .size(16.0)
+                    .color(colors.on_surface),
+            );
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.filter)
+                    .hint_text("Filter")
+                    .desired_width(f32::INFINITY),
+            );
+            if std::mem::take(&mut self.focus_filter) {
+                response.request_focus();
+            }
+        });
+        ui.add_space(8.0);
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).size(12.0).color(colors.error));
+        }
+
+        let places: Vec<&Place> = self
+            .places
+            .iter()
+            .filter(|place| places::matches(&self.filter, &[&place.name, &place.location]))
+            .collect();
+        let recent: Vec<&RecentFile> = self
+            .recent
+            .iter()
+            .filter(|file| {
+                places::matches(
+                    &self.filter,
+                    &[&file.name, &files::percent_decode(&file.uri)],
+                )
+            })
+            .collect();
+        if places.is_empty() && recent.is_empty() {
+            ui.label(
+                RichText::new("Nothing matches")
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+        }
+
+        let mut picked = None;
+        if !places.is_empty() {
+            section_label(ui, "Places", colors);
+            for place in places {
+                if let Some(action) = Self::place_row(ui, place, colors) {
+                    picked = Some(action);
+                }
+            }
+        }
+        if !recent.is_empty() {
+            ui.add_space(4.0);
+            section_label(ui, "Recent", colors);
+            let now = SystemTime::now();
+            for file in recent {
+                if let Some(action) = Self::recent_row(&mut self.icons, ui, file, now, colors) {
+                    picked = Some(action);
+                }
+            }
+        }
+
+        if let Some(action) = picked {
+            self.run(action);
+        }
+        back
+    }
+
+    fn place_row(ui: &mut egui::Ui, place: &Place, colors: &Palette) -> Option<PlaceAction> {
+        let response = row(ui, colors, |ui| {
+            ui.label(
+                RichText::new(place.icon)
+                    .size(20.0)
+                    .color(colors.on_surface),
+            );
+            labels(ui, &place.name, &place.location, colors);
+        })
+        .on_hover_text("Click to open");
+        response
+            .clicked()
+            .then(|| PlaceAction::Open(place.uri.clone()))
+    }
+
+    fn recent_row(
+        icons: &mut IconCache,
+        ui: &mut egui::Ui,
+        file: &RecentFile,
+        now: SystemTime,
+        colors: &Palette,
+    ) -> Option<PlaceAction> {
+        let kind = FileKind::from_name(&file.name);
+        // shared-mime-info types map to icon names by swapping the slash
+        let icon_name = file
+            .mime_type
+            .as_ref()
+            .map(|mime| mime.replace('/', "-"))
+            .unwrap_or_else(|| kind.icon_name().to_string());
+        let folder = match &file.path {
+            Some(path) => path
+                .parent()
+                .map(|parent| tilde(&parent.display().to_string()))
+                .unwrap_or_default(),
+            None => file.uri.clone(),
+        };
+        let detail = match file.used {
+            Some(used) => format!(
+                "{} · {}",
+                folder,
+                files::format_age(UNIX_EPOCH + Duration::from_secs(used.max(0) as u64), now)
+            ),
+            None => folder,
+        };
+
+        let mut action = None;
+        let response = row(ui, colors, |ui| {
+            icons.show(
+                ui,
+                Some(&icon_name),
+                "mimetypes",
+                kind.glyph(),
+                20.0,
+                colors.on_surface,
+            );
+            labels(ui, &file.name, &detail, colors);
+        })
+        .on_hover_text("Click to open, right-click for more");
+        if response.clicked() {
+            action = Some(PlaceAction::Open(file.uri.clone()));
+        }
+        response.context_menu(|ui| {
+            if ui.button(format!("{}  Open", ARROW_SQUARE_OUT)).clicked() {
+                action = Some(PlaceAction::Open(file.uri.clone()));
+                ui.close_menu();
+            }
+            if let Some(path) = &file.path
+                && ui.button(format!("{}  Show in folder", FOLDER)).clicked()
+            {
+                action = Some(PlaceAction::ShowInFolder(path.clone()));
+                ui.close_menu();
+            }
+        });
+        action
+    }
+}
+
+/// A clickable surface row around `add_contents`
+fn row(
+    ui: &mut egui::Ui,
+    colors: &Palette,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) -> egui::Response {
+    let response = egui::Frame::new()
+        .fill(colors.surface)
+        .corner_radius(12.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(add_contents);
+        })
+        .response
+        .interact(egui::Sense::click());
+    ui.add_space(4.0);
+    response
+}
+
+fn labels(ui: &mut egui::Ui, title: &str, subtitle: &str, colors: &Palette) {
+    ui.vertical(|ui| {
+        ui.add(egui::Label::new(RichText::new(title).color(colors.on_surface)).truncate());
+        ui.add(
+            egui::Label::new(
+                RichText::new(subtitle)
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            )
+            .truncate(),
+        );
+    });
+}
+
+fn section_label(ui: &mut egui::Ui, text: &str, colors: &Palette) {
+    ui.horizontal(|ui| {
+        ui.add_space(8.0);
+        ui.label(
+            RichText::new(text)
+                .size(12.0)
+                .color(colors.on_surface.gamma_multiply(0.7)),
+        );
+    });
+}
+
+fn user_dir_icon(key: &str) -> &'static str {
+    match key {
+        "DESKTOP" => DESKTOP,
+        "DOWNLOAD" => DOWNLOAD_SIMPLE,
+        "DOCUMENTS" => FILE_TEXT,
+        "MUSIC" => MUSIC_NOTES,
+        "PICTURES" => IMAGE,
+        "VIDEOS" => VIDEO,
+        _ => FOLDER_SIMPLE,
+    }
+}
+
+/// Shortens paths under the home directory to ~/...
+fn tilde(path: &str) -> String {
+    let home = xdg_dirs::home();
+    let home = home.to_string_lossy();
+    match path.strip_prefix(home.as_ref()) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{}", rest),
+        _ => path.to_string(),
+    }
+}
@@ -0,0 +1,235 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::command_runner::CommandRunner;
+
+const BUS_NAME: &str = "net.hadess.PowerProfiles";
+const OBJECT_PATH: &str = "/net/hadess/PowerProfiles";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    PowerSaver,
+    Balanced,
+    Performance,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 3] = [Profile::PowerSaver, Profile::Balanced, Profile::Performance];
+
+    /// Name used by power-profiles-daemon
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::PowerSaver => "power-saver",
+            Profile::Balanced => "balanced",
+            Profile::Performance => "performance",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Profile::PowerSaver => "Power Saver",
+            Profile::Balanced => "Balanced",
+            Profile::Performance => "Performance",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|profile| profile.name() == name)
+    }
+
+    /// Maps an ACPI platform profile onto the three daemon profiles
+    pub fn from_platform(name: &str) -> Option<Self> {
+        match name {
+            "low-power" | "quiet" | "cool" => Some(Profile::PowerSaver),
+            "balanced" | "balanced-performance" => Some(Profile::Balanced),
+            "performance" => Some(Profile::Performance),
+            _ => None,
+        }
+    }
+
+    /// The one after this, wrapping around, for cycling from the tile
+    pub fn next(self) -> Self {
+        match self {
+            Profile::PowerSaver => Profile::Balanced,
+            Profile::Balanced => Profile::Performance,
+            Profile::Performance => Profile::PowerSaver,
+        }
+    }
+}
+
+/// Reads and switches the power profile through power-profiles-daemon,
+/// falling back to the ACPI platform profile in sysfs when it isn't running
+pub struct PowerProfiles {
+    runner: Arc<dyn CommandRunner>,
+    /// `/sys/firmware/acpi/platform_profile` normally
+    platform_profile: PathBuf,
+    /// Swapped out in tests, which run as root and can write anything
+    writable: fn(&Path) -> bool,
+}
+
+impl PowerProfiles {
+    pub fn new(runner: Arc<dyn CommandRunner>, platform_profile: PathBuf) -> Self {
+        Self {
+            runner,
+            platform_profile,
+            writable: is_writable,
+        }
+    }
+
+    /// The active profile, or `None` when neither the daemon nor the
+    /// firmware supports switching
+    pub fn active(&self) -> Option<Profile> {
+        let daemon = self.runner.run(
+            "busctl",
+            &[
+                "--system",
+                "get-property",
+                BUS_NAME,
+                OBJECT_PATH,
+                BUS_NAME,
+                "ActiveProfile",
+            ],
+        );
+        match daemon {
+            Ok(output) => parse_string_property(&output).and_then(|name| Profile::from_name(&name)),
+            Err(_) => {
+                // The file is root-owned on most systems. Offering profiles
+                // that can't be switched would only fail on click.
+                if !(self.writable)(&self.platform_profile) {
+                    return None;
+                }
+                let current = fs::read_to_string(&self.platform_profile).ok()?;
+                Profile::from_platform(current.trim())
+            }
+        }
+    }
+
+    pub fn set(&self, profile: Profile) -> Result<(), String> {
+        let daemon = self.runner.run(
+            "busctl",
+            &[
+                "--system",
+                "set-property",
+                BUS_NAME,
+                OBJECT_PATH,
+                BUS_NAME,
+                "ActiveProfile",
+                "s",
+                profile.name(),
+            ],
+        );
+        if daemon.is_ok() {
+            return Ok(());
+        }
+
+        // Without the daemon, write whichever firmware choice maps onto the profile
+        let choices_path = self
+            .platform_profile
+            .with_file_name("platform_profile_choices");
+        let choices = fs::read_to_string(&choices_path)
+            .map_err(|e| format!("Failed to read {}: {}", choices_path.display(), e))?;
+        let choice = choices
+            .split_whitespace()
+            .find(|choice| Profile::from_platform(choice) == Some(profile))
+            .ok_or_else(|| format!("{} isn't supported by the firmware", profile.label()))?;
+        fs::write(&self.platform_profile, choice)
+            .map_err(|e| format!("Failed to write {}: {}", self.platform_profile.display(), e))
+    }
+}
+
+/// The value of a `busctl get-property` string reply, e.g. `s "balanced"`
+/// Whether this user may write the firmware profile, e.g. through a udev
+/// rule. Opening for writing without writing changes nothing.
+fn is_writable(path: &Path) -> bool {
+    fs::OpenOptions::new().write(true).open(path).is_ok()
+}
+
+pub fn parse_string_property(output: &str) -> Option<String> {
+    let value = output.trim().strip_prefix("s ")?;
+    Some(value.trim_matches('"').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    const GET: &str = "busctl --system get-property net.hadess.PowerProfiles /net/hadess/PowerProfiles net.hadess.PowerProfiles ActiveProfile";
+    const SET_SAVER: &str = "busctl --system set-property net.hadess.PowerProfiles /net/hadess/PowerProfiles net.hadess.PowerProfiles ActiveProfile s power-saver";
+
+    /// A fake `/sys/firmware/acpi` with the given profile and choices
+    fn acpi_dir(name: &str, current: &str, choices: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hermit-power-profile-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("platform_profile"), current).unwrap();
+        fs::write(dir.join("platform_profile_choices"), choices).unwrap();
+        dir
+    }
+
+    #[test]
+    fn daemon() {
+        let runner = Arc::new(
+            FakeRunner::default()
+                .with(GET, "s \"performance\"\n")
+                .with(SET_SAVER, ""),
+        );
+        let profiles = PowerProfiles::new(runner.clone(), "/nonexistent".into());
+        assert_eq!(profiles.active(), Some(Profile::Performance));
+        profiles.set(Profile::PowerSaver).unwrap();
+        assert_eq!(runner.calls().last().unwrap(), SET_SAVER);
+    }
+
+    #[test]
+    fn firmware_fallback() {
+        let dir = acpi_dir("fallback", "balanced\n", "low-power balanced performance\n");
+        let profiles = PowerProfiles::new(
+            Arc::new(FakeRunner::default()),
+            dir.join("platform_profile"),
+        );
+        assert_eq!(profiles.active(), Some(Profile::Balanced));
+
+        profiles.set(Profile::PowerSaver).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("platform_profile")).unwrap(),
+            "low-power"
+        );
+        assert!(profiles.set(Profile::Performance).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unsupported_choice() {
+        let dir = acpi_dir("unsupported", "quiet\n", "quiet balanced\n");
+        let profiles = PowerProfiles::new(
+            Arc::new(FakeRunner::default()),
+            dir.join("platform_profile"),
+        );
+        assert_eq!(profiles.active(), Some(Profile::PowerSaver));
+        assert_eq!(
+            profiles.set(Profile::Performance),
+            Err("Performance isn't supported by the firmware".to_string())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn read_only_firmware_profile_isnt_offered() {
+        let dir = acpi_dir("read-only", "balanced\n", "low-power balanced\n");
+        let path = dir.join("platform_profile");
+        let mut profiles = PowerProfiles::new(Arc::new(FakeRunner::default()), path);
+        profiles.writable = |_| false;
+        assert_eq!(profiles.active(), None);
+
+        // Cases even root can't open for writing
+        assert!(!is_writable(&dir.join("missing")));
+        assert!(!is_writable(&dir));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,161 +1,215 @@
 use eframe::egui;
-use egui::{Button, Color32, Label, Layout, RichText, Vec2};
+use egui::{Button, RichText, Vec2};
 use egui_phosphor::regular::*;
-use std::io::Write;
-use std::process::{Command, Stdio};
+use serde::Serialize;
+
+use crate::audio::AudioBackend;
+use crate::bluetooth::BluetoothBackend;
+use crate::night_light::NightLight;
+use crate::palette::Palette;
+use crate::power_profile::{PowerProfiles, Profile};
+use crate::vpn::VpnBackend;
+use crate::wifi::WifiBackend;
+
+/// A full-panel page opened from one of the quick settings rows or action
+/// buttons
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetailPage {
+    Wifi,
+    Bluetooth,
+    Vpn,
+    Downloads,
+    Places,
+}
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize)]
 pub struct QuickSettingsState {
     pub wifi_enabled: bool,
     pub wifi_network_name: String,
     pub network_enabled: bool,
     pub bluetooth_enabled: bool,
     pub bluetooth_device_name: String,
+    /// Names of the active VPN connections, comma separated
+    pub vpn_name: String,
+    /// `None` when the machine can't switch profiles
+    pub power_profile: Option<Profile>,
+    pub night_light: bool,
     pub airplane_enabled: bool,
+    pub mic_muted: bool,
+    pub mic_volume: f32,
 }
 
 pub struct QuickSettings {
     state: QuickSettingsState,
-    colors: super::Colors,
+    colors: Palette,
+    audio: AudioBackend,
+    wifi: WifiBackend,
+    bluetooth: BluetoothBackend,
+    vpn: VpnBackend,
+    power_profiles: PowerProfiles,
+    night_light: NightLight,
 }
 
 impl QuickSettings {
-    pub fn new(colors: super::Colors) -> Self {
+    pub fn new(
+        colors: Palette,
+        audio: AudioBackend,
+        wifi: WifiBackend,
+        bluetooth: BluetoothBackend,
+        vpn: VpnBackend,
+        power_profiles: PowerProfiles,
+        night_light: NightLight,
+    ) -> Self {
         let mut qs = Self {
             state: QuickSettingsState::default(),
             colors,
+            audio,
+            wifi,
+            bluetooth,
+            vpn,
+            power_profiles,
+            night_light,
         };
 
         // Initialize states on startup
         qs.update_wifi_state();
         qs.update_bluetooth_state();
+        qs.update_vpn_state();
+        qs.update_power_profile_state();
+        qs.update_microphone_state();
 
         qs
     }
 
     /// Fetches current WiFi state and connected network
     pub fn update_wifi_state(&mut self) {
-        // Check if WiFi is enabled
-        let wifi_status = Command::new("nmcli").args(&["radio", "wifi"]).output();
-
-        if let Ok(output) = wifi_status {
-            let status_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            self.state.wifi_enabled = status_str == "enabled";
-
-            // If WiFi is enabled, get the connected network name
-            if self.state.wifi_enabled {
-                let network_cmd = Command::new("nmcli")
-                    .args(&["-t", "-f", "NAME,DEVICE", "connection", "show", "--active"])
-                    .output();
-
-                if let Ok(net_output) = network_cmd {
-                    let net_str = String::from_utf8_lossy(&net_output.stdout);
-                    self.state.wifi_network_name = String::new(); // Reset before checking
-
-                    // Parse the output for connections on WiFi devices (wlan0, etc.)
-                    for line in net_str.lines() {
-                        let parts: Vec<&str> = line.split(':').collect();
-                        if parts.len() >= 2 && parts[1].starts_with("wl") {
-                            self.state.wifi_network_name = parts[0].to_string();
-                            break;
-                        }
-                    }
-                }
-            } else {
-                self.state.wifi_network_name = String::new();
+        let enabled = match self.wifi.radio_enabled() {
+            Ok(enabled) => enabled,
+            Err(e) => {
+                eprintln!("Failed to read WiFi state: {}", e);
+                return;
             }
+        };
+        self.state.wifi_enabled = enabled;
+        if !enabled {
+            self.state.wifi_network_name = String::new();
+            return;
+        }
+        match self.wifi.active_network() {
+            Ok(name) => self.state.wifi_network_name = name.unwrap_or_default(),
+            Err(e) => eprintln!("Failed to read WiFi network: {}", e),
         }
     }
 
     /// Fetches current Bluetooth state and connected device
     pub fn update_bluetooth_state(&mut self) {
-        // Check if Bluetooth is powered on using grep to directly get the power state
-        let bt_status = Command::new("sh")
-            .arg("-c")
-            .arg("bluetoothctl show | grep 'Powered:'")
-            .output();
-
-        if let Ok(output) = bt_status {
-            let output_str = String::from_utf8_lossy(&output.stdout);
-
-            // Parse the line "Powered: yes/no" and check if it contains "yes"
-            self.state.bluetooth_enabled = output_str.contains("Powered: yes");
-
-            // If Bluetooth is enabled, check for connected devices
-            if self.state.bluetooth_enabled {
-                // Get connected devices - using direct shell command for more reliable output
-                let devices_cmd = Command::new("sh")
-                    .arg("-c")
-                    .arg("bluetoothctl devices Connected")
-                    .output();
-
-                if let Ok(devices_output) = devices_cmd {
-                    let devices_str = String::from_utf8_lossy(&devices_output.stdout);
-                    self.state.bluetooth_device_name = String::new(); // Reset before checking
-
-                    // Parse the first connected device (if any)
-                    // Format is typically: "Device XX:XX:XX:XX:XX:XX DeviceName"
-                    for line in devices_str.lines() {
-                        if line.starts_with("Device") {
-                            let parts: Vec<&str> = line.split(' ').collect();
-                            if parts.len() >= 3 {
-                                // Join all parts after the MAC address to get the full device name
-                                self.state.bluetooth_device_name = parts[2..].join(" ");
-                                break;
-                            }
-                        }
-                    }
-                }
-            } else {
-                self.state.bluetooth_device_name = String::new();
+        let powered = match self.bluetooth.powered() {
+            Ok(powered) => powered,
+            Err(e) => {
+                eprintln!("Failed to read Bluetooth state: {}", e);
+                return;
+            }
+        };
+        self.state.bluetooth_enabled = powered;
+        if !powered {
+            self.state.bluetooth_device_name = String::new();
+            return;
+        }
+        match self.bluetooth.connected_devices() {
+            // One device is shown by name, several are just counted
+            Ok(connected) => {
+                self.state.bluetooth_device_name = match connected.as_slice() {
+                    [] => String::new(),
+                    [(_, name)] => name.clone(),
+                    devices => format!("{} devices", devices.len()),
+                };
             }
+            Err(e) => eprintln!("Failed to read Bluetooth devices: {}", e),
         }
     }
 
-    /// Toggle WiFi on/off
-    fn toggle_wifi(&mut self) {
-        let new_state = if self.state.wifi_enabled { "off" } else { "on" };
+    /// Fetches which VPN connections are up
+    pub fn update_vpn_state(&mut self) {
+        match self.vpn.connections() {
+            Ok(connections) => {
+                let active: Vec<String> = connections
+                    .into_iter()
+                    .filter(|connection| connection.active)
+                    .map(|connection| connection.name)
+                    .collect();
+                self.state.vpn_name = active.join(", ");
+            }
+            Err(e) => eprintln!("Failed to read VPN state: {}", e),
+        }
+    }
 
-        let _ = Command::new("nmcli")
-            .args(&["radio", "wifi", new_state])
-            .output();
+    /// Brings every active VPN connection down
+    fn disconnect_vpn(&mut self) {
+        if let Err(e) = self.vpn.disconnect_all() {
+            eprintln!("Failed to disconnect VPN: {}", e);
+        }
+        self.update_vpn_state();
+    }
+
+    /// Re-reads the power profile, which can also be changed from outside
+    pub fn update_power_profile_state(&mut self) {
+        self.state.power_profile = self.power_profiles.active();
+    }
+
+    fn set_power_profile(&mut self, profile: Profile) {
+        if let Err(e) = self.power_profiles.set(profile) {
+            eprintln!("Failed to set power profile: {}", e);
+        }
+        self.update_power_profile_state();
+    }
 
-        // Update the state after toggling
+    /// Runs the night light schedule, called every frame
+    pub fn update_night_light(&mut self, ctx: &egui::Context) {
+        self.night_light.tick(ctx);
+        self.state.night_light = self.night_light.is_on();
+    }
+
+    /// The night light, for drawing its temperature slider
+    pub fn night_light_mut(&mut self) -> &mut NightLight {
+        &mut self.night_light
+    }
+
+    /// Toggle WiFi on/off
+    fn toggle_wifi(&mut self) {
+        if let Err(e) = self.wifi.set_radio(!self.state.wifi_enabled) {
+            eprintln!("Failed to toggle WiFi: {}", e);
+        }
         self.update_wifi_state();
     }
 
     /// Toggle Bluetooth on/off
     fn toggle_bluetooth(&mut self) {
-        let new_state = if self.state.bluetooth_enabled {
-            "off"
-        } else {
-            "on"
-        };
-
-        // Use echo to pipe commands to bluetoothctl for more reliable execution
-        let mut child = Command::new("bluetoothctl")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to start bluetoothctl");
-
-        // Write commands to bluetoothctl stdin
-        if let Some(mut stdin) = child.stdin.take() {
-            let cmd = format!("power {}\nquit\n", new_state);
-            stdin
-                .write_all(cmd.as_bytes())
-                .expect("Failed to write to bluetoothctl stdin");
+        if let Err(e) = self.bluetooth.set_powered(!self.state.bluetooth_enabled) {
+            eprintln!("Failed to toggle Bluetooth: {}", e);
         }
+        self.update_bluetooth_state();
+    }
 
-        // Wait for command to complete
-        let _ = child.wait();
+    /// Fetches the default source's volume and mute state
+    pub fn update_microphone_state(&mut self) {
+        match self.audio.source_volume() {
+            Ok((volume, muted)) => {
+                self.state.mic_volume = volume;
+                self.state.mic_muted = muted;
+            }
+            Err(e) => eprintln!("Failed to read microphone state: {}", e),
+        }
+    }
 
-        // Update the state after toggling - with a small delay to allow bluetoothctl to complete
-        std::thread::sleep(std::time::Duration::from_millis(500));
-        self.update_bluetooth_state();
+    /// Mute or unmute the default source
+    pub fn set_microphone_muted(&mut self, muted: bool) {
+        if let Err(e) = self.audio.set_source_mute(muted) {
+            eprintln!("Failed to set microphone mute: {}", e);
+        }
+        self.update_microphone_state();
     }
 
-    pub fn update_colors(&mut self, colors: super::Colors) {
+    pub fn update_colors(&mut self, colors: Palette) {
         self.colors = colors;
     }
 
@@ -163,11 +217,14 @@ impl QuickSettings {
         &self.state
     }
 
+    #[allow(dead_code)]
     pub fn state_mut(&mut self) -> &mut QuickSettingsState {
         &mut self.state
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui) {
+    /// Draws the grid and returns the detail page the user asked to open, if any
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<DetailPage> {
+        let mut page = None;
         ui.vertical(|ui| {
             // Quick Settings grid
             egui::Frame::new()
@@ -180,28 +237,15 @@ impl QuickSettings {
                         .spacing([10.0, 22.0])
                         .min_col_width(40.0)
                         .show(ui, |ui| {
-                            let on_primary = self.colors.on_primary;
-                            let on_surface = self.colors.on_surface;
-                            let primary = self.colors.primary;
-                            let surface = self.colors.surface;
-
                             // WiFi row
                             ui.horizontal(|ui| {
                                 ui.set_min_width(ui.available_width());
-                                if self.add_button(
-                                    ui,
-                                    WIFI_HIGH,
-                                    "Wi-Fi",
-                                    self.state.wifi_enabled,
-                                    on_primary,
-                                    on_surface,
-                                    primary,
-                                    surface,
-                                ) {
+                                if self.add_button(ui, WIFI_HIGH, "Wi-Fi", self.state.wifi_enabled)
+                                {
                                     // Toggle WiFi when clicked
                                     self.toggle_wifi();
                                 }
-                                ui.vertical_centered(|ui| {
+                                let details = ui.vertical_centered(|ui| {
                                     ui.horizontal(|ui| {
                                         ui.label(
                                             RichText::new("Wi-Fi").color(self.colors.on_surface),
@@ -225,6 +269,16 @@ impl QuickSettings {
                                         );
                                     });
                                 });
+                                // Clicking the name opens the network list
+                                if details
+                                    .response
+                                    .interact(egui::Sense::click())
+                                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                    .on_hover_text("Show networks")
+                                    .clicked()
+                                {
+                                    page = Some(DetailPage::Wifi);
+                                }
                             });
                             ui.end_row();
 
@@ -235,15 +289,11 @@ impl QuickSettings {
                                     BLUETOOTH,
                                     "Bluetooth",
                                     self.state.bluetooth_enabled,
-                                    on_primary,
-                                    on_surface,
-                                    primary,
-                                    surface,
                                 ) {
                                     // Toggle Bluetooth when clicked
                                     self.toggle_bluetooth();
                                 }
-                                ui.vertical_centered(|ui| {
+                                let details = ui.vertical_centered(|ui| {
                                     ui.horizontal(|ui| {
                                         ui.label(
                                             RichText::new("Bluetooth")
@@ -268,6 +318,168 @@ impl QuickSettings {
                                         );
                                     });
                                 });
+                                if details
+                                    .response
+                                    .interact(egui::Sense::click())
+                                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                    .on_hover_text("Show devices")
+                                    .clicked()
+                                {
+                                    page = Some(DetailPage::Bluetooth);
+                                }
+                            });
+                            ui.end_row();
+
+                            // Microphone row
+                            ui.horizontal(|ui| {
+                                let icon = if self.state.mic_muted {
+                                    MICROPHONE_SLASH
+                                } else {
+                                    MICROPHONE
+                                };
+                                if self.add_button(ui, icon, "Microphone", !self.state.mic_muted) {
+                                    self.set_microphone_muted(!self.state.mic_muted);
+                                }
+                                ui.vertical_centered(|ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(
+                                            RichText::new("Microphone")
+                                                .color(self.colors.on_surface),
+                                        );
+                                    });
+                                    ui.horizontal(|ui| {
+                                        let status_text =
+                                            if self.state.mic_muted { "Muted" } else { "On" };
+                                        ui.label(
+                                            RichText::new(status_text)
+                                                .size(12.0)
+                                                .color(self.colors.on_surface),
+                                        );
+                                    });
+                                });
+                            });
+                            ui.end_row();
+
+                            // VPN row
+                            ui.horizontal(|ui| {
+                                let vpn_active = !self.state.vpn_name.is_empty();
+                                let icon = if vpn_active { SHIELD_CHECK } else { SHIELD };
+                                if self.add_button(ui, icon, "VPN", vpn_active) {
+                                    // Turning a VPN on needs picking which one
+                                    if vpn_active {
+                                        self.disconnect_vpn();
+                                    } else {
+                                        page = Some(DetailPage::Vpn);
+                                    }
+                                }
+                                let details = ui.vertical_centered(|ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(
+                                            RichText::new("VPN").color(self.colors.on_surface),
+                                        );
+                                    });
+                                    ui.horizontal(|ui| {
+                                        let status_text = if vpn_active {
+                                            &self.state.vpn_name
+                                        } else {
+                                            "Off"
+                                        };
+                                        ui.label(
+                                            RichText::new(status_text)
+                                                .size(12.0)
+                                                .color(self.colors.on_surface),
+                                        );
+                                    });
+                                });
+                                if details
+                                    .response
+                                    .interact(egui::Sense::click())
+                                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                    .on_hover_text("Show connections")
+                                    .clicked()
+                                {
+                                    page = Some(DetailPage::Vpn);
+                                }
+                            });
+                            ui.end_row();
+
+                            // Power profile row, only where the profile can be switched
+                            if let Some(profile) = self.state.power_profile {
+                                ui.horizontal(|ui| {
+                                    let icon = match profile {
+                                        Profile::PowerSaver => LEAF,
+                                        Profile::Balanced => SCALES,
+                                        Profile::Performance => LIGHTNING,
+                                    };
+                                    if self.add_button(
+                                        ui,
+                                        icon,
+                                        "Power Profile",
+                                        profile != Profile::Balanced,
+                                    ) {
+                                        self.set_power_profile(profile.next());
+                                    }
+                                    let details = ui.vertical_centered(|ui| {
+                                        ui.horizontal(|ui| {
+                                            ui.label(
+                                                RichText::new("Power Mode")
+                                                    .color(self.colors.on_surface),
+                                            );
+                                        });
+                                        ui.horizontal(|ui| {
+                                            ui.label(
+                                                RichText::new(profile.label())
+                                                    .size(12.0)
+                                                    .color(self.colors.on_surface),
+                                            );
+                                        });
+                                    });
+                                    // Right-click picks a profile directly instead of cycling
+                                    details
+                                        .response
+                                        .interact(egui::Sense::click())
+                                        .on_hover_text("Right-click to choose a profile")
+                                        .context_menu(|ui| {
+                                            for choice in Profile::ALL {
+                                                if ui
+                                                    .radio(profile == choice, choice.label())
+                                                    .clicked()
+                                                {
+                                                    self.set_power_profile(choice);
+                                                    ui.close_menu();
+                                                }
+                                            }
+                                        });
+                                });
+                                ui.end_row();
+                            }
+
+                            // Night light row
+                            ui.horizontal(|ui| {
+                                if self.add_button(
+                                    ui,
+                                    MOON_STARS,
+                                    "Night Light",
+                                    self.state.night_light,
+                                ) {
+                                    self.night_light.toggle();
+                                    self.state.night_light = self.night_light.is_on();
+                                }
+                                ui.vertical_centered(|ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(
+                                            RichText::new("Night Light")
+                                                .color(self.colors.on_surface),
+                                        );
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.label(
+                                            RichText::new(self.night_light.status())
+                                                .size(12.0)
+                                                .color(self.colors.on_surface),
+                                        );
+                                    });
+                                });
                             });
                             ui.end_row();
 
@@ -278,10 +490,6 @@ impl QuickSettings {
                                     NETWORK,
                                     "Ethernet",
                                     self.state.network_enabled,
-                                    on_primary,
-                                    on_surface,
-                                    primary,
-                                    surface,
                                 ) {
                                     self.state.network_enabled = !self.state.network_enabled;
                                 }
@@ -309,10 +517,6 @@ impl QuickSettings {
                                     AIRPLANE,
                                     "Airplane Mode",
                                     self.state.airplane_enabled,
-                                    on_primary,
-                                    on_surface,
-                                    primary,
-                                    surface,
                                 ) {
                                     self.state.airplane_enabled = !self.state.airplane_enabled;
                                 }
@@ -340,10 +544,6 @@ impl QuickSettings {
                                     RECORD,
                                     "Screen Recording",
                                     self.state.airplane_enabled,
-                                    on_primary,
-                                    on_surface,
-                                    primary,
-                                    surface,
                                 ) {
                                     self.state.airplane_enabled = !self.state.airplane_enabled;
                                 }
@@ -371,10 +571,6 @@ impl QuickSettings {
                                     BELL_SLASH,
                                     "Do Not Disturb",
                                     self.state.airplane_enabled,
-                                    on_primary,
-                                    on_surface,
-                                    primary,
-                                    surface,
                                 ) {
                                     self.state.airplane_enabled = !self.state.airplane_enabled;
                                 }
@@ -398,28 +594,23 @@ impl QuickSettings {
                         });
                 });
         });
+        page
     }
 
-    fn add_button(
-        &self,
-        ui: &mut egui::Ui,
-        icon: &str,
-        tooltip: &str,
-        is_enabled: bool,
-        on_primary: Color32,
-        on_surface: Color32,
-        primary: Color32,
-        surface: Color32,
-    ) -> bool {
+    fn add_button(&self, ui: &mut egui::Ui, icon: &str, tooltip: &str, is_enabled: bool) -> bool {
         let button = ui.add(
             Button::new(RichText::new(icon).size(20.0).color(if is_enabled {
-                on_primary
+                self.colors.on_primary
             } else {
-                on_surface
+                self.colors.on_surface
             }))
             .min_size(Vec2::new(40.0, 40.0))
             .corner_radius(20.0)
-            .fill(if is_enabled { primary } else { surface }),
+            .fill(if is_enabled {
+                self.colors.primary
+            } else {
+                self.colors.surface
+            }),
         );
 
         let clicked = button.clicked();
@@ -0,0 +1,319 @@
+use eframe::egui;
+use egui::{Button, Color32, Pos2, Rect, RichText, Sense, Shape, Stroke, Vec2};
+use egui_phosphor::regular::*;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::config::ResourcesConfig;
+use crate::files;
+use crate::palette::Palette;
+use crate::resources::{self, CpuStat, Memory};
+
+const SPARKLINE_HEIGHT: f32 = 32.0;
+const CORE_BARS_HEIGHT: f32 = 20.0;
+
+/// Temperatures the sparkline spans, in °C
+const TEMPERATURE_RANGE: (f32, f32) = (20.0, 100.0);
+
+/// CPU, memory, load and temperature with short histories
+pub struct ResourceMonitor {
+    root: PathBuf,
+    interval: Duration,
+    history_length: usize,
+    expanded: bool,
+    /// Previous counters, usage is the difference to the next sample
+    last_cpu: Option<CpuStat>,
+    cpu_history: VecDeque<f32>,
+    core_usage: Vec<f32>,
+    memory: Option<Memory>,
+    memory_history: VecDeque<f32>,
+    load: Option<[f32; 3]>,
+    temperature: Option<f32>,
+    temperature_history: VecDeque<f32>,
+    last_update: Option<Instant>,
+}
+
+impl ResourceMonitor {
+    pub fn new(config: &ResourcesConfig) -> Self {
+        Self {
+            root: PathBuf::from(shellexpand::tilde(&config.root).as_ref()),
+            interval: Duration::from_millis(config.interval_ms.max(250)),
+            history_length: config.history.max(2),
+            expanded: false,
+            last_cpu: None,
+            cpu_history: VecDeque::new(),
+            core_usage: Vec::new(),
+            memory: None,
+            memory_history: VecDeque::new(),
+            load: None,
+            temperature: None,
+            temperature_history: VecDeque::new(),
+            last_update: None,
+        }
+    }
+
+    fn refresh(&mut self) {
+        let sample = resources::sample(&self.root);
+
+        if let Some(cpu) = sample.cpu {
+            if let Some(last) = &self.last_cpu {
+                let usage = cpu.total.usage_since(&last.total);
+                push(&mut self.cpu_history, usage, self.history_length);
+                self.core_usage = cpu
+                    .cores
+                    .iter()
+                    .zip(&last.cores)
+                    .map(|(now, before)| now.usage_since(before))
+                    .collect();
+            }
+            self.last_cpu = Some(cpu);
+        }
+        if let Some(memory) = sample.memory.filter(|memory| memory.total > 0) {
+            let used = memory.used() as f32 / memory.total as f32;
+            push(&mut self.memory_history, used, self.history_length);
+            self.memory = Some(memory);
+        }
+        self.load = sample.load;
+        self.temperature = sample.temperature;
+        if let Some(temperature) = sample.temperature {
+            push(
+                &mut self.temperature_history,
+                temperature,
+                self.history_length,
+            );
+        }
+        self.last_update = Some(Instant::now());
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        if self
+            .last_update
+            .is_none_or(|last| last.elapsed() >= self.interval)
+        {
+            self.refresh();
+        }
+        ui.ctx().request_repaint_after(self.interval);
+
+        let cpu = self.cpu_history.back().copied();
+        let memory = self.memory_history.back().copied();
+
+        ui.horizontal(|ui| {
+            ui.add_space(8.0);
+            let caret = if self.expanded {
+                CARET_DOWN
+            } else {
+                CARET_RIGHT
+            };
+            let mut label = format!("{}  System", caret);
+            if let Some(cpu) = cpu {
+                label.push_str(&format!(" · CPU {:.0}%", cpu * 100.0));
+            }
+            if let Some(memory) = memory {
+                label.push_str(&format!(" · Memory {:.0}%", memory * 100.0));
+            }
+            if ui
+                .add(
+                    Button::new(RichText::new(label).size(12.0).color(colors.on_surface))
+                        .fill(Color32::TRANSPARENT),
+                )
+                .clicked()
+            {
+                self.expanded = !self.expanded;
+            }
+        });
+
+        if !self.expanded {
+            return;
+        }
+
+        egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(12.0)
+            .inner_margin(8.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+
+                let load = self.load.map(|[one, five, fifteen]| {
+                    format!("Load {:.2} · {:.2} · {:.2}", one, five, fifteen)
+                });
+                heading(
+                    ui,
+                    CPU,
+                    &format!("CPU · {:.0}%", cpu.unwrap_or(0.0) * 100.0),
+                    load.as_deref(),
+                    colors,
+                );
+                sparkline(
+                    ui,
+                    &self.cpu_history,
+                    self.history_length,
+                    (0.0, 1.0),
+                    colors.primary,
+                    colors,
+                );
+                if !self.core_usage.is_empty() {
+                    ui.add_space(4.0);
+                    core_bars(ui, &self.core_usage, colors);
+                }
+
+                if let Some(memory) = &self.memory {
+                    ui.add_space(8.0);
+                    let swap = (memory.swap_total > 0).then(|| {
+                        format!(
+                            "Swap {} of {}",
+                            files::format_size(memory.swap_used() * 1024),
+                            files::format_size(memory.swap_total * 1024)
+                        )
+                    });
+                    heading(
+                        ui,
+                        MEMORY,
+                        &format!(
+                            "Memory · {} of {}",
+                            files::format_size(memory.used() * 1024),
+                            files::format_size(memory.total * 1024)
+                        ),
+                        swap.as_deref(),
+                        colors,
+                    );
+                    sparkline(
+                        ui,
+                        &self.memory_history,
+                        self.history_length,
+                        (0.0, 1.0),
+                        colors.tertiary,
+                        colors,
+                    );
+                }
+
+                if let Some(temperature) = self.temperature {
+                    ui.add_space(8.0);
+                    let color = if temperature >= 90.0 {
+                        colors.error
+                    } else {
+                        colors.secondary
+                    };
+                    heading(
+                        ui,
+                        THERMOMETER,
+                        &format!("Temperature · {:.0} °C", temperature),
+                        None,
+                        colors,
+                    );
+                    sparkline(
+                        ui,
+                        &self.temperature_history,
+                        self.history_length,
+                        TEMPERATURE_RANGE,
+                        color,
+                        colors,
+                    );
+                }
+            });
+    }
+}
+
+/// Appends to a history, dropping the oldest values past `length`
+fn push(history: &mut VecDeque<f32>, value: f32, length: usize) {
+    history.push_back(value);
+    while history.len() > length {
+        history.pop_front();
+    }
+}
+
+fn heading(ui: &mut egui::Ui, icon: &str, title: &str, subtitle: Option<&str>, colors: &Palette) {
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(icon).size(16.0).color(colors.on_surface));
+        ui.label(RichText::new(title).color(colors.on_surface));
+        if let Some(subtitle) = subtitle {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(
+                    RichText::new(subtitle)
+                        .size(12.0)
+                        .color(colors.on_surface.gamma_multiply(0.7)),
+                );
+            });
+        }
+    });
+}
+
+/// Line graph of `values` with the newest on the right, shaded underneath
+fn sparkline(
+    ui: &mut egui::Ui,
+    values: &VecDeque<f32>,
+    capacity: usize,
+    (min, max): (f32, f32),
+    color: Color32,
+    colors: &Palette,
+) {
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), SPARKLINE_HEIGHT),
+        Sense::hover(),
+    );
+    let painter = ui.painter();
+    painter.rect_filled(rect, 6.0, colors.surface_container_highest);
+    if values.len() < 2 {
+        return;
+    }
+
+    // Keep the line off the rounded corners
+    let area = rect.shrink(3.0);
+    let step = area.width() / (capacity - 1) as f32;
+    let points: Vec<Pos2> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let x = area.right() - (values.len() - 1 - index) as f32 * step;
+            let level = ((value - min) / (max - min)).clamp(0.0, 1.0);
+            Pos2::new(x, area.bottom() - level * area.height())
+        })
+        .collect();
+
+    // One quad per segment keeps every shaded polygon convex
+    let shade = color.gamma_multiply(0.25);
+    for pair in points.windows(2) {
+        painter.add(Shape::convex_polygon(
+            vec![
+                pair[0],
+                pair[1],
+                Pos2::new(pair[1].x, area.bottom()),
+                Pos2::new(pair[0].x, area.bottom()),
+            ],
+            shade,
+            Stroke::NONE,
+        ));
+    }
+    painter.add(Shape::line(points, Stroke::new(1.5, color)));
+}
+
+/// A small bar per core, filled from the bottom by its usage
+fn core_bars(ui: &mut egui::Ui, usage: &[f32], colors: &Palette) {
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), CORE_BARS_HEIGHT),
+        Sense::hover(),
+    );
+    let gap = 2.0;
+    let width = (rect.width() - gap * (usage.len() - 1) as f32) / usage.len() as f32;
+    let painter = ui.painter();
+    for (index, value) in usage.iter().enumerate() {
+        let left = rect.left() + index as f32 * (width + gap);
+        let bar = Rect::from_min_size(Pos2::new(left, rect.top()), Vec2::new(width, rect.height()));
+        painter.rect_filled(bar, 2.0, colors.surface_container_highest);
+        let filled = Rect::from_min_max(
+            Pos2::new(
+                bar.left(),
+                bar.bottom() - value.clamp(0.0, 1.0) * bar.height(),
+            ),
+            bar.max,
+        );
+        painter.rect_filled(filled, 2.0, colors.primary);
+    }
+
+    if let Some(pointer) = response.hover_pos() {
+        let index = ((pointer.x - rect.left()) / (width + gap)) as usize;
+        if let Some(value) = usage.get(index) {
+            response.on_hover_text(format!("Core {} · {:.0}%", index, value * 100.0));
+        }
+    }
+}
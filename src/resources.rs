@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::Path;
+
+/// Jiffies counters for one line of `/proc/stat`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    /// idle + iowait
+    pub idle: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// Busy share (0.0-1.0) between an earlier sample and this one
+    pub fn usage_since(&self, earlier: &CpuTimes) -> f32 {
+        let total = self.total.saturating_sub(earlier.total);
+        if total == 0 {
+            return 0.0;
+        }
+        let idle = self.idle.saturating_sub(earlier.idle);
+        (1.0 - idle as f32 / total as f32).clamp(0.0, 1.0)
+    }
+}
+
+/// The overall "cpu" line and then one per core
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CpuStat {
+    pub total: CpuTimes,
+    pub cores: Vec<CpuTimes>,
+}
+
+/// Memory figures from `/proc/meminfo`, in KiB
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Memory {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+impl Memory {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    pub fn swap_used(&self) -> u64 {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+}
+
+pub fn parse_stat(text: &str) -> CpuStat {
+    let mut stat = CpuStat::default();
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(name) = fields.next().filter(|name| name.starts_with("cpu")) else {
+            continue;
+        };
+        // user nice system idle iowait irq softirq steal; guest time is
+        // already counted in user
+        let values: Vec<u64> = fields.take(8).filter_map(|v| v.parse().ok()).collect();
+        if values.len() < 4 {
+            continue;
+        }
+        let times = CpuTimes {
+            idle: values[3] + values.get(4).copied().unwrap_or(0),
+            total: values.iter().sum(),
+        };
+        if name == "cpu" {
+            stat.total = times;
+        } else {
+            stat.cores.push(times);
+        }
+    }
+    stat
+}
+
+pub fn parse_meminfo(text: &str) -> Memory {
+    let mut memory = Memory::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        match key {
+            "MemTotal" => memory.total = value,
+            "MemAvailable" => memory.available = value,
+            "SwapTotal" => memory.swap_total = value,
+            "SwapFree" => memory.swap_free = value,
+            _ => {}
+        }
+    }
+    memory
+}
+
+/// The 1, 5 and 15 minute load averages
+pub fn parse_loadavg(text: &str) -> Option<[f32; 3]> {
+    let mut fields = text.split_whitespace().map(|v| v.parse().ok());
+    Some([fields.next()??, fields.next()??, fields.next()??])
+}
+
+/// hwmon drivers that report the CPU package temperature
+const CPU_SENSORS: &[&str] = &["coretemp", "k10temp", "zenpower", "cpu_thermal", "acpitz"];
+
+/// Labels of the sensor that stands for the whole package
+const PACKAGE_LABELS: &[&str] = &["Package id 0", "Tctl", "Tdie"];
+
+/// CPU temperature in °C from the first hwmon device with a known CPU
+/// driver, preferring its package sensor
+pub fn read_cpu_temperature(hwmon: &Path) -> Option<f32> {
+    let mut devices: Vec<_> = fs::read_dir(hwmon).ok()?.flatten().collect();
+    devices.sort_by_key(|entry| entry.file_name());
+
+    for sensor in CPU_SENSORS {
+        for device in &devices {
+            let path = device.path();
+            let name = fs::read_to_string(path.join("name")).unwrap_or_default();
+            if name.trim() != *sensor {
+                continue;
+            }
+            let mut inputs: Vec<(String, f32)> = Vec::new();
+            for index in 1..=32 {
+                let Ok(value) = fs::read_to_string(path.join(format!("temp{}_input", index)))
+                else {
+                    continue;
+                };
+                let Ok(millidegrees) = value.trim().parse::<f32>() else {
+                    continue;
+                };
+                let label = fs::read_to_string(path.join(format!("temp{}_label", index)))
+                    .unwrap_or_default();
+                inputs.push((label.trim().to_string(), millidegrees / 1000.0));
+            }
+            let package = inputs
+                .iter()
+                .find(|(label, _)| PACKAGE_LABELS.contains(&label.as_str()));
+            if let Some((_, temperature)) = package.or(inputs.first()) {
+                return Some(*temperature);
+            }
+        }
+    }
+    None
+}
+
+/// One reading of everything the monitor shows
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    pub cpu: Option<CpuStat>,
+    pub memory: Option<Memory>,
+    pub load: Option<[f32; 3]>,
+    pub temperature: Option<f32>,
+}
+
+/// Reads `proc/...` and `sys/class/hwmon` under `root`, normally "/".
+/// Anything missing is left out.
+pub fn sample(root: &Path) -> Sample {
+    let read = |path: &str| fs::read_to_string(root.join(path)).ok();
+    Sample {
+        cpu: read("proc/stat").map(|text| parse_stat(&text)),
+        memory: read("proc/meminfo").map(|text| parse_meminfo(&text)),
+        load: read("proc/loadavg").and_then(|text| parse_loadavg(&text)),
+        temperature: read_cpu_temperature(&root.join("sys/class/hwmon")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `/proc` and `/sys/class/hwmon` from a four-core Intel laptop, which
+    /// also has an ACPI and an NVMe sensor
+    fn root() -> &'static Path {
+        Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/resources"
+        ))
+    }
+
+    #[test]
+    fn samples_fixture_root() {
+        let sample = sample(root());
+        let cpu = sample.cpu.unwrap();
+        assert_eq!(
+            cpu.total,
+            CpuTimes {
+                idle: 46_845_166,
+                total: 60_377_929,
+            }
+        );
+        assert_eq!(cpu.cores.len(), 4);
+        assert_eq!(
+            cpu.cores[0],
+            CpuTimes {
+                idle: 11_711_290,
+                total: 15_100_778,
+            }
+        );
+        assert_eq!(
+            sample.memory,
+            Some(Memory {
+                total: 16_211_968,
+                available: 9_571_636,
+                swap_total: 8_388_604,
+                swap_free: 8_130_556,
+            })
+        );
+        assert_eq!(sample.load, Some([0.52, 0.58, 0.59]));
+    }
+
+    #[test]
+    fn prefers_cpu_package_sensor() {
+        // acpitz comes first on disk, but coretemp is the better source
+        assert_eq!(
+            read_cpu_temperature(&root().join("sys/class/hwmon")),
+            Some(48.0)
+        );
+    }
+
+    #[test]
+    fn missing_files_are_left_out() {
+        let sample = sample(&root().join("nowhere"));
+        assert!(sample.cpu.is_none());
+        assert!(sample.memory.is_none());
+        assert!(sample.load.is_none());
+        assert!(sample.temperature.is_none());
+    }
+
+    #[test]
+    fn usage_between_samples() {
+        let earlier = parse_stat("cpu  100 0 100 700 100 0 0 0 0 0\n");
+        let later = parse_stat("cpu  250 0 150 900 100 0 0 0 0 0\n");
+        assert_eq!(later.total.usage_since(&earlier.total), 0.5);
+        // Counters going backwards (e.g. after a CPU went offline)
+        assert_eq!(earlier.total.usage_since(&later.total), 0.0);
+        assert_eq!(parse_loadavg("0.5 garbage"), None);
+    }
+}
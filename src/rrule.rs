@@ -0,0 +1,438 @@
+//! RFC 5545 recurrence rules, expanded in wall-clock seconds (days since
+//! 1970-01-01 * 86400 + seconds into the day) so the caller decides which
+//! time zone the instances are in.
+
+use crate::local_time::{
+    civil_from_days, days_from_civil, days_in_month, parse_date, parse_time, weekday_from_days,
+};
+
+/// Gives up on rules that never produce an instance in range
+const MAX_PERIODS: i64 = 50_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: i64,
+    pub count: Option<usize>,
+    /// Last allowed start, in wall-clock seconds unless `until_utc`
+    pub until: Option<i64>,
+    pub until_utc: bool,
+    /// (ordinal, weekday): 0 means every such weekday, -1 the last one.
+    /// Weekdays count from Sunday = 0.
+    pub by_day: Vec<(i32, u32)>,
+    /// Negative days count back from the end of the month
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub week_start: u32,
+}
+
+impl RecurrenceRule {
+    /// Parses the value of an RRULE property, e.g. "FREQ=WEEKLY;BYDAY=MO,WE"
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            until_utc: false,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+            week_start: 1,
+        };
+        let mut frequency = None;
+        for part in text.trim().split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Bad rule part {}", part))?;
+            let bad = || format!("Bad {} in {}", key, text);
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("Unsupported frequency {}", other)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value.parse().ok().filter(|i| *i > 0).ok_or_else(bad)?
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| bad())?),
+                "UNTIL" => {
+                    let (seconds, utc) = parse_until(value).ok_or_else(bad)?;
+                    rule.until = Some(seconds);
+                    rule.until_utc = utc;
+                }
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(parse_by_day)
+                        .collect::<Option<_>>()
+                        .ok_or_else(bad)?
+                }
+                "BYMONTHDAY" => rule.by_month_day = parse_list(value).ok_or_else(bad)?,
+                "BYMONTH" => rule.by_month = parse_list(value).ok_or_else(bad)?,
+                "BYSETPOS" => rule.by_set_pos = parse_list(value).ok_or_else(bad)?,
+                "WKST" => rule.week_start = parse_weekday(value).ok_or_else(bad)?,
+                // BYHOUR, BYWEEKNO and friends are rare enough in calendars
+                // people actually use that they're ignored
+                _ => {}
+            }
+        }
+        rule.frequency = frequency.ok_or("Rule without FREQ")?;
+        Ok(rule)
+    }
+
+    /// Instance starts from `start` (which always counts as the first one)
+    /// up to `end`, both in wall-clock seconds. `until` is the rule's UNTIL
+    /// already moved into the same wall clock.
+    pub fn occurrences(&self, start: i64, until: Option<i64>, end: i64) -> Vec<i64> {
+        let first_day = start.div_euclid(86400);
+        let time = start.rem_euclid(86400);
+        let end_day = end.div_euclid(86400);
+        let (year, month, day) = civil_from_days(first_day);
+
+        let mut instances = vec![start];
+        if start > end {
+            return instances;
+        }
+        for period in 0..MAX_PERIODS {
+            let step = period * self.interval;
+            let (period_start, mut days) = match self.frequency {
+                Frequency::Daily => {
+                    let candidate = first_day + step;
+                    let days = if self.day_matches(candidate) {
+                        vec![candidate]
+                    } else {
+                        Vec::new()
+                    };
+                    (candidate, days)
+                }
+                Frequency::Weekly => {
+                    let back = (weekday_from_days(first_day) + 7 - self.week_start) % 7;
+                    let week = first_day - back as i64 + step * 7;
+                    let days = (week..week + 7)
+                        .filter(|day| {
+                            let weekday = weekday_from_days(*day);
+                            let weekday_matches = if self.by_day.is_empty() {
+                                weekday == weekday_from_days(first_day)
+                            } else {
+                                self.by_day.iter().any(|(_, by)| *by == weekday)
+                            };
+                            weekday_matches && self.month_matches(*day)
+                        })
+                        .collect();
+                    (week, days)
+                }
+                Frequency::Monthly => {
+                    let months = (month as i64 - 1) + step;
+                    let year = year + months.div_euclid(12) as i32;
+                    let month = months.rem_euclid(12) as u32 + 1;
+                    let days = if self.by_month.is_empty() || self.by_month.contains(&month) {
+                        self.month_days(year, month, day)
+                    } else {
+                        Vec::new()
+                    };
+                    (days_from_civil(year, month, 1), days)
+                }
+                Frequency::Yearly => {
+                    let year = year + step as i32;
+                    let days = if !self.by_month.is_empty() {
+                        self.by_month
+                            .iter()
+                            .flat_map(|month| self.month_days(year, *month, day))
+                            .collect()
+                    } else if !self.by_day.is_empty() && self.by_month_day.is_empty() {
+                        // e.g. BYDAY=20MO, the 20th Monday of the year
+                        weekday_days(
+                            days_from_civil(year, 1, 1),
+                            days_from_civil(year, 12, 31),
+                            &self.by_day,
+                        )
+                    } else if !self.by_month_day.is_empty() {
+                        (1..=12)
+                            .flat_map(|month| self.month_days(year, month, day))
+                            .collect()
+                    } else {
+                        self.month_days(year, month, day)
+                    };
+                    (days_from_civil(year, 1, 1), days)
+                }
+            };
+            if period_start > end_day {
+                break;
+            }
+
+            days.sort_unstable();
+            days.dedup();
+            if !self.by_set_pos.is_empty() {
+                days = select_positions(&days, &self.by_set_pos);
+            }
+
+            for day in days {
+                let instance = day * 86400 + time;
+                if instance <= start {
+                    continue;
+                }
+                if until.is_some_and(|until| instance > until) || instance > end {
+                    return instances;
+                }
+                instances.push(instance);
+                if self.count.is_some_and(|count| instances.len() >= count) {
+                    return instances;
+                }
+            }
+        }
+        instances
+    }
+
+    fn month_matches(&self, day: i64) -> bool {
+        self.by_month.is_empty() || self.by_month.contains(&civil_from_days(day).1)
+    }
+
+    /// Filters for daily rules, where every BY part only narrows things down
+    fn day_matches(&self, day: i64) -> bool {
+        let (year, month, month_day) = civil_from_days(day);
+        let length = days_in_month(year, month) as i32;
+        let month_day_matches = self.by_month_day.is_empty()
+            || self
+                .by_month_day
+                .iter()
+                .any(|by| resolve_month_day(*by, length) == Some(month_day));
+        let weekday = weekday_from_days(day);
+        let weekday_matches =
+            self.by_day.is_empty() || self.by_day.iter().any(|(_, by)| *by == weekday);
+        self.month_matches(day) && month_day_matches && weekday_matches
+    }
+
+    /// Candidate days within one month, `default_day` being the start's day
+    fn month_days(&self, year: i32, month: u32, default_day: u32) -> Vec<i64> {
+        let first = days_from_civil(year, month, 1);
+        let length = days_in_month(year, month);
+        let mut days: Vec<i64> = if !self.by_month_day.is_empty() {
+            self.by_month_day
+                .iter()
+                .filter_map(|by| resolve_month_day(*by, length as i32))
+                .map(|day| first + day as i64 - 1)
+                .collect()
+        } else if self.by_day.is_empty() {
+            // Months without that day, like the 31st in April, are skipped
+            if default_day <= length {
+                vec![first + default_day as i64 - 1]
+            } else {
+                Vec::new()
+            }
+        } else {
+            Vec::new()
+        };
+        if !self.by_day.is_empty() {
+            let weekdays = weekday_days(first, first + length as i64 - 1, &self.by_day);
+            if self.by_month_day.is_empty() {
+                days = weekdays;
+            } else {
+                days.retain(|day| weekdays.contains(day));
+            }
+        }
+        days
+    }
+}
+
+/// Day of the month for a BYMONTHDAY value, counting back for negatives
+fn resolve_month_day(by: i32, length: i32) -> Option<u32> {
+    let day = if by < 0 { length + by + 1 } else { by };
+    (1..=length).contains(&day).then_some(day as u32)
+}
+
+/// Days between `first` and `last` matching BYDAY entries, where an ordinal
+/// picks the nth such weekday in that span
+fn weekday_days(first: i64, last: i64, by_day: &[(i32, u32)]) -> Vec<i64> {
+    let mut days = Vec::new();
+    for (ordinal, weekday) in by_day {
+        let offset = (*weekday + 7 - weekday_from_days(first)) % 7;
+        let matching: Vec<i64> = (first + offset as i64..=last).step_by(7).collect();
+        match *ordinal {
+            0 => days.extend(matching),
+            n if n > 0 => days.extend(matching.get(n as usize - 1)),
+            n => days.extend(
+                matching
+                    .len()
+                    .checked_sub(n.unsigned_abs() as usize)
+                    .map(|index| matching[index]),
+            ),
+        }
+    }
+    days
+}
+
+fn select_positions(days: &[i64], positions: &[i32]) -> Vec<i64> {
+    let mut selected: Vec<i64> = positions
+        .iter()
+        .filter_map(|position| {
+            let index = if *position > 0 {
+                *position as usize - 1
+            } else {
+                days.len().checked_sub(position.unsigned_abs() as usize)?
+            };
+            days.get(index).copied()
+        })
+        .collect();
+    selected.sort_unstable();
+    selected.dedup();
+    selected
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str) -> Option<Vec<T>> {
+    value
+        .split(',')
+        .map(|item| item.trim().parse().ok())
+        .collect()
+}
+
+fn parse_weekday(text: &str) -> Option<u32> {
+    let weekday = match text.trim().to_ascii_uppercase().as_str() {
+        "SU" => 0,
+        "MO" => 1,
+        "TU" => 2,
+        "WE" => 3,
+        "TH" => 4,
+        "FR" => 5,
+        "SA" => 6,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+/// "MO", "2TU" or "-1SU"
+fn parse_by_day(text: &str) -> Option<(i32, u32)> {
+    let text = text.trim();
+    let split = text.len().checked_sub(2)?;
+    let weekday = parse_weekday(text.get(split..)?)?;
+    let ordinal = match &text[..split] {
+        "" => 0,
+        number => number.trim_start_matches('+').parse().ok()?,
+    };
+    Some((ordinal, weekday))
+}
+
+/// UNTIL as (seconds, is UTC). A bare date lasts until the end of that day.
+fn parse_until(value: &str) -> Option<(i64, bool)> {
+    let value = value.trim();
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let days = parse_date(date)?;
+    match time {
+        Some(time) => {
+            let utc = time.ends_with('Z');
+            Some((days * 86400 + parse_time(time.trim_end_matches('Z'))?, utc))
+        }
+        None => Some((days * 86400 + 86399, false)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: i64) -> i64 {
+        days_from_civil(year, month, day) * 86400 + hour * 3600
+    }
+
+    fn expand(rule: &str, start: i64) -> Vec<i64> {
+        let rule = RecurrenceRule::parse(rule).unwrap();
+        rule.occurrences(start, rule.until, at(2030, 1, 1, 0))
+    }
+
+    #[test]
+    fn needs_a_frequency() {
+        assert!(RecurrenceRule::parse("COUNT=3").is_err());
+        assert!(RecurrenceRule::parse("FREQ=HOURLY").is_err());
+        assert!(RecurrenceRule::parse("FREQ=DAILY;INTERVAL=0").is_err());
+    }
+
+    #[test]
+    fn count_includes_the_start() {
+        let start = at(2024, 1, 30, 9);
+        assert_eq!(
+            expand("FREQ=DAILY;INTERVAL=2;COUNT=3", start),
+            [start, at(2024, 2, 1, 9), at(2024, 2, 3, 9)]
+        );
+    }
+
+    #[test]
+    fn until_date_includes_that_day() {
+        let start = at(2024, 1, 1, 9);
+        assert_eq!(
+            expand("FREQ=WEEKLY;UNTIL=20240115", start),
+            [start, at(2024, 1, 8, 9), at(2024, 1, 15, 9)]
+        );
+        let rule = RecurrenceRule::parse("FREQ=DAILY;UNTIL=20240102T080000Z").unwrap();
+        assert!(rule.until_utc);
+        assert_eq!(rule.until, Some(at(2024, 1, 2, 8)));
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // Tuesday the 2nd, so the Monday of that week is skipped
+        let start = at(2024, 1, 2, 18);
+        assert_eq!(
+            expand("FREQ=WEEKLY;BYDAY=MO,TU,TH;COUNT=5", start),
+            [
+                start,
+                at(2024, 1, 4, 18),
+                at(2024, 1, 8, 18),
+                at(2024, 1, 9, 18),
+                at(2024, 1, 11, 18)
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_by_day_with_ordinals() {
+        let start = at(2024, 1, 26, 12);
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3", start),
+            [start, at(2024, 2, 23, 12), at(2024, 3, 29, 12)]
+        );
+        let start = at(2024, 1, 9, 12);
+        assert_eq!(
+            expand("FREQ=MONTHLY;BYDAY=2TU;COUNT=2", start),
+            [start, at(2024, 2, 13, 12)]
+        );
+    }
+
+    #[test]
+    fn monthly_skips_short_months() {
+        let start = at(2024, 1, 31, 0);
+        assert_eq!(
+            expand("FREQ=MONTHLY;COUNT=3", start),
+            [start, at(2024, 3, 31, 0), at(2024, 5, 31, 0)]
+        );
+    }
+
+    #[test]
+    fn monthly_by_month_and_set_position() {
+        // The last weekday of March and September
+        let start = at(2024, 3, 29, 0);
+        assert_eq!(
+            expand(
+                "FREQ=MONTHLY;BYMONTH=3,9;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1;COUNT=3",
+                start
+            ),
+            [start, at(2024, 9, 30, 0), at(2025, 3, 31, 0)]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::command_runner::CommandRunner;
+use crate::config::ScreenshotConfig;
+use crate::hyprland::{self, Client};
+use crate::local_time::LocalTime;
+
+/// Longest side of the notification thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 256;
+/// Notifications are long gone by then
+const THUMBNAIL_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenshotMode {
+    /// A rectangle picked with slurp
+    Region,
+    /// The window that had focus before the panel
+    Window,
+    /// Every output
+    Screen,
+}
+
+impl ScreenshotMode {
+    pub const ALL: [ScreenshotMode; 3] = [
+        ScreenshotMode::Region,
+        ScreenshotMode::Window,
+        ScreenshotMode::Screen,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ScreenshotMode::Region => "Region",
+            ScreenshotMode::Window => "Window",
+            ScreenshotMode::Screen => "Full screen",
+        }
+    }
+
+    /// Stands in for `{mode}` in the filename template
+    fn name(self) -> &'static str {
+        match self {
+            ScreenshotMode::Region => "region",
+            ScreenshotMode::Window => "window",
+            ScreenshotMode::Screen => "screen",
+        }
+    }
+}
+
+/// A finished screenshot
+#[derive(Debug)]
+pub struct Screenshot {
+    pub path: PathBuf,
+    /// Small copy for the notification, if one could be made
+    pub thumbnail: Option<PathBuf>,
+}
+
+/// Takes screenshots with grim and slurp. Cheap to clone, so a capture can
+/// run on a background thread while the panel is hidden.
+#[derive(Clone)]
+pub struct Screenshotter {
+    runner: Arc<dyn CommandRunner>,
+    config: ScreenshotConfig,
+}
+
+impl Screenshotter {
+    pub fn new(runner: Arc<dyn CommandRunner>, config: ScreenshotConfig) -> Self {
+        Self { runner, config }
+    }
+
+    /// How long to wait after hiding the panel so it's gone from the capture
+    pub fn hide_delay(&self) -> Duration {
+        Duration::from_millis(self.config.hide_delay_ms)
+    }
+
+    /// Captures, saves, copies to the clipboard and makes a thumbnail.
+    /// Errs with "cancelled" when the region selection is aborted.
+    pub fn capture(&self, mode: ScreenshotMode) -> Result<Screenshot, String> {
+        let geometry = match mode {
+            ScreenshotMode::Region => {
+                let selection = self.runner.run("slurp", &[]).map_err(|e| {
+                    // What slurp says on Escape or a right click; anything
+                    // else, like slurp missing, is a real failure
+                    if e.contains("selection cancelled") {
+                        "cancelled".to_string()
+                    } else {
+                        format!("Region selection failed: {}", e)
+                    }
+                })?;
+                Some(selection.trim().to_string())
+            }
+            ScreenshotMode::Window => {
+                let clients = hyprland::clients(self.runner.as_ref())?;
+                let window = previous_window(&clients).ok_or("No window to capture")?;
+                Some(window_geometry(window).ok_or("Window has no geometry")?)
+            }
+            ScreenshotMode::Screen => None,
+        };
+
+        let directory = PathBuf::from(shellexpand::tilde(&self.config.directory).as_ref());
+        fs::create_dir_all(&directory)
+            .map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+        let path = directory.join(file_name(&self.config.filename, mode, &LocalTime::now()));
+        let path_arg = path.to_string_lossy().into_owned();
+
+        let mut args = Vec::new();
+        if let Some(geometry) = &geometry {
+            args.extend(["-g", geometry.as_str()]);
+        }
+        args.push(&path_arg);
+        self.runner.run("grim", &args)?;
+
+        // wl-copy reads the image from stdin
+        if let Err(e) = self.runner.run(
+            "sh",
+            &["-c", "wl-copy --type image/png < \"$1\"", "sh", &path_arg],
+        ) {
+            eprintln!("Failed to copy screenshot: {}", e);
+        }
+
+        let cache =
+            PathBuf::from(shellexpand::tilde("~/.cache/hermit-action-center/thumbnails").as_ref());
+        let thumbnail = match make_thumbnail(&path, &cache) {
+            Ok(thumbnail) => Some(thumbnail),
+            Err(e) => {
+                eprintln!("Failed to make thumbnail: {}", e);
+                None
+            }
+        };
+        Ok(Screenshot { path, thumbnail })
+    }
+}
+
+/// The window focused before the panel took focus. Hyprland numbers focus
+/// history from 0 for the current window, which is the panel itself.
+pub fn previous_window(clients: &[Client]) -> Option<&Client> {
+    clients
+        .iter()
+        .filter(|client| client.mapped && !client.hidden && client.title != "Action Center")
+        .filter(|client| client.focus_history_id >= 0)
+        .min_by_key(|client| client.focus_history_id)
+}
+
+/// grim's "X,Y WxH" for a window
+pub fn window_geometry(client: &Client) -> Option<String> {
+    match (client.at.as_slice(), client.size.as_slice()) {
+        ([x, y], [width, height]) => Some(format!("{},{} {}x{}", x, y, width, height)),
+        _ => None,
+    }
+}
+
+/// Fills in the filename template: strftime fields plus `{mode}`
+pub fn file_name(template: &str, mode: ScreenshotMode, now: &LocalTime) -> String {
+    let name = now.format(&template.replace("{mode}", mode.name()));
+    if name.ends_with(".png") {
+        name
+    } else {
+        format!("{}.png", name)
+    }
+}
+
+/// Scales the screenshot down for the notification into `cache`. Each one
+/// gets its own file, since an earlier notification may still show its
+/// thumbnail; day-old ones are cleared out.
+fn make_thumbnail(path: &Path, cache: &Path) -> Result<PathBuf, String> {
+    let image = image::open(path).map_err(|e| e.to_string())?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+    fs::create_dir_all(cache).map_err(|e| e.to_string())?;
+    remove_old_thumbnails(cache);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let stem = path
+        .file_stem()
+        .map_or("screenshot".into(), |stem| stem.to_string_lossy());
+    let thumbnail_path = cache.join(format!("{}-{}.png", stem, nanos));
+    thumbnail.save(&thumbnail_path).map_err(|e| e.to_string())?;
+    Ok(thumbnail_path)
+}
+
+fn remove_old_thumbnails(cache: &Path) {
+    let Ok(entries) = fs::read_dir(cache) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let old = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > THUMBNAIL_MAX_AGE);
+        if old {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    fn screenshotter(runner: FakeRunner) -> Screenshotter {
+        Screenshotter::new(Arc::new(runner), ScreenshotConfig::default())
+    }
+
+    #[test]
+    fn escape_in_slurp_is_a_cancel() {
+        let runner = FakeRunner::default().failing("slurp", "selection cancelled");
+        assert_eq!(
+            screenshotter(runner).capture(ScreenshotMode::Region).err(),
+            Some("cancelled".to_string())
+        );
+    }
+
+    #[test]
+    fn other_slurp_errors_are_reported() {
+        // FakeRunner doesn't know slurp, like a system without it installed
+        let error = screenshotter(FakeRunner::default())
+            .capture(ScreenshotMode::Region)
+            .unwrap_err();
+        assert_eq!(error, "Region selection failed: unexpected command: slurp");
+    }
+
+    #[test]
+    fn thumbnails_get_their_own_files() {
+        let dir = std::env::temp_dir().join(format!("hermit-screenshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let screenshot = dir.join("shot.png");
+        image::RgbImage::new(1024, 512).save(&screenshot).unwrap();
+
+        let cache = dir.join("thumbnails");
+        let first = make_thumbnail(&screenshot, &cache).unwrap();
+        let second = make_thumbnail(&screenshot, &cache).unwrap();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+        assert_eq!(
+            image::image_dimensions(&second).unwrap(),
+            (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2)
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,329 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use crate::config::SessionConfig;
+use crate::palette::Palette;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionAction {
+    Lock,
+    Logout,
+    Suspend,
+    Hibernate,
+    Reboot,
+    Shutdown,
+}
+
+impl SessionAction {
+    pub const ALL: [SessionAction; 6] = [
+        SessionAction::Lock,
+        SessionAction::Logout,
+        SessionAction::Suspend,
+        SessionAction::Hibernate,
+        SessionAction::Reboot,
+        SessionAction::Shutdown,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SessionAction::Lock => "Lock",
+            SessionAction::Logout => "Log Out",
+            SessionAction::Suspend => "Suspend",
+            SessionAction::Hibernate => "Hibernate",
+            SessionAction::Reboot => "Restart",
+            SessionAction::Shutdown => "Shut Down",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            SessionAction::Lock => LOCK,
+            SessionAction::Logout => SIGN_OUT,
+            SessionAction::Suspend => MOON,
+            SessionAction::Hibernate => SNOWFLAKE,
+            SessionAction::Reboot => ARROWS_CLOCKWISE,
+            SessionAction::Shutdown => POWER,
+        }
+    }
+
+    /// Actions that end the session, and with it any unsaved work
+    fn needs_confirmation(self) -> bool {
+        matches!(
+            self,
+            SessionAction::Logout | SessionAction::Reboot | SessionAction::Shutdown
+        )
+    }
+
+    fn command(self, config: &SessionConfig) -> &str {
+        match self {
+            SessionAction::Lock => &config.lock,
+            SessionAction::Logout => &config.logout,
+            SessionAction::Suspend => &config.suspend,
+            SessionAction::Hibernate => &config.hibernate,
+            SessionAction::Reboot => &config.reboot,
+            SessionAction::Shutdown => &config.shutdown,
+        }
+    }
+}
+
+/// Row of lock / log out / suspend / ... buttons. Actions that end the
+/// session count down in a dialog first so a stray click can be undone.
+pub struct SessionMenu {
+    config: SessionConfig,
+    /// The action waiting for confirmation and when its countdown started
+    pending: Option<(SessionAction, Instant)>,
+}
+
+impl SessionMenu {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            pending: None,
+        }
+    }
+
+    /// Runs the configured command through `sh -c` without waiting for it
+    fn run(&self, action: SessionAction) {
+        let command = action.command(&self.config).to_string();
+        if command.is_empty() {
+            return;
+        }
+        std::thread::spawn(move || {
+            if let Err(e) = Command::new("sh").arg("-c").arg(&command).status() {
+                eprintln!("Failed to run {}: {}", command, e);
+            }
+        });
+    }
+
+    /// Opens the countdown, or returns the action if it can run right away
+    fn start(&mut self, action: SessionAction) -> Option<SessionAction> {
+        if action.needs_confirmation() && self.config.countdown_seconds > 0 {
+            self.pending = Some((action, Instant::now()));
+            None
+        } else {
+            Some(action)
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        if let Some(action) = self.update(ui, colors) {
+            self.run(action);
+        }
+    }
+
+    /// Draws the buttons and the dialog, returning the action to run now
+    fn update(&mut self, ui: &mut egui::Ui, colors: &Palette) -> Option<SessionAction> {
+        let mut picked = None;
+        egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(18.0)
+            .inner_margin(6.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.columns(SessionAction::ALL.len(), |columns| {
+                    for (ui, action) in columns.iter_mut().zip(SessionAction::ALL) {
+                        ui.vertical_centered(|ui| {
+                            if ui
+                                .add(
+                                    Button::new(
+                                        RichText::new(action.icon())
+                                            .size(20.0)
+                                            .color(colors.on_surface),
+                                    )
+                                    .fill(Color32::TRANSPARENT)
+                                    .corner_radius(12.0)
+                                    .min_size(egui::vec2(36.0, 36.0)),
+                                )
+                                .on_hover_text(action.label())
+                                .clicked()
+                            {
+                                picked = Some(action);
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(action) = picked
+            && let Some(action) = self.start(action)
+        {
+            return Some(action);
+        }
+        // The Enter that picked a focused button mustn't confirm it too
+        self.show_confirmation(ui.ctx(), colors, picked.is_some())
+    }
+
+    fn show_confirmation(
+        &mut self,
+        ctx: &egui::Context,
+        colors: &Palette,
+        just_opened: bool,
+    ) -> Option<SessionAction> {
+        let (action, started) = self.pending?;
+
+        let total = Duration::from_secs(self.config.countdown_seconds);
+        let elapsed = started.elapsed();
+        if elapsed >= total {
+            self.pending = None;
+            return Some(action);
+        }
+        let left = (total - elapsed).as_secs() + 1;
+
+        let mut confirm = false;
+        let mut cancel = false;
+        let mut cancel_focused = false;
+        let modal = egui::Modal::new(egui::Id::new("session_confirmation"))
+            .frame(
+                egui::Frame::new()
+                    .fill(colors.surface_container_high)
+                    .corner_radius(18.0)
+                    .inner_margin(16.0),
+            )
+            .show(ctx, |ui| {
+                ui.set_width(240.0);
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(action.icon()).size(24.0).color(colors.error));
+                    ui.label(
+                        RichText::new(format!("{}?", action.label()))
+                            .size(16.0)
+                            .color(colors.on_surface),
+                    );
+                });
+                ui.add_space(8.0);
+                ui.label(
+                    RichText::new(format!("{} automatically in {} s", action.label(), left))
+                        .size(12.0)
+                        .color(colors.on_surface),
+                );
+                ui.add(
+                    egui::ProgressBar::new(1.0 - elapsed.as_secs_f32() / total.as_secs_f32())
+                        .desired_height(4.0)
+                        .fill(colors.error),
+                );
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    confirm = ui
+                        .button(format!("{} Now", action.label()))
+                        .on_hover_text("Enter")
+                        .clicked();
+                    let response = ui.button("Cancel").on_hover_text("Esc");
+                    cancel = response.clicked();
+                    cancel_focused = response.has_focus();
+                });
+            });
+
+        // Enter confirms unless it pressed Cancel, Esc (handled by the
+        // modal) cancels
+        if !just_opened
+            && !cancel_focused
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter))
+        {
+            confirm = true;
+        }
+        if cancel || modal.should_close() {
+            self.pending = None;
+            None
+        } else if confirm {
+            self.pending = None;
+            Some(action)
+        } else {
+            // Keep the countdown ticking
+            ctx.request_repaint_after(Duration::from_millis(100));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, Key, Pos2, RawInput, Rect, vec2};
+
+    fn key(key: Key) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: Default::default(),
+        }
+    }
+
+    /// Runs one frame and returns the action that would run
+    fn frame(
+        ctx: &egui::Context,
+        menu: &mut SessionMenu,
+        events: Vec<Event>,
+    ) -> Option<SessionAction> {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, vec2(400.0, 400.0))),
+            events,
+            ..Default::default()
+        };
+        let mut ran = None;
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ran = menu.update(ui, &Palette::default());
+            });
+        });
+        ran
+    }
+
+    /// Tabs through `count` widgets, one per frame
+    fn tab(ctx: &egui::Context, menu: &mut SessionMenu, count: usize) {
+        for _ in 0..count {
+            assert_eq!(frame(ctx, menu, vec![key(Key::Tab)]), None);
+        }
+    }
+
+    #[test]
+    fn enter_on_a_button_only_opens_the_dialog() {
+        let ctx = egui::Context::default();
+        let mut menu = SessionMenu::new(SessionConfig::default());
+        frame(&ctx, &mut menu, Vec::new());
+        // Lock, Log Out, Suspend, Hibernate, Restart
+        tab(&ctx, &mut menu, 5);
+        assert_eq!(frame(&ctx, &mut menu, vec![key(Key::Enter)]), None);
+        assert!(matches!(menu.pending, Some((SessionAction::Reboot, _))));
+
+        // A later Enter confirms
+        assert_eq!(
+            frame(&ctx, &mut menu, vec![key(Key::Enter)]),
+            Some(SessionAction::Reboot)
+        );
+        assert!(menu.pending.is_none());
+    }
+
+    #[test]
+    fn enter_on_cancel_cancels() {
+        let ctx = egui::Context::default();
+        let mut menu = SessionMenu::new(SessionConfig::default());
+        menu.pending = Some((SessionAction::Shutdown, Instant::now()));
+        frame(&ctx, &mut menu, Vec::new());
+        // Shut Down Now, Cancel
+        tab(&ctx, &mut menu, 2);
+        assert_eq!(frame(&ctx, &mut menu, vec![key(Key::Enter)]), None);
+        assert!(menu.pending.is_none());
+    }
+
+    #[test]
+    fn runs_when_the_countdown_ends() {
+        let ctx = egui::Context::default();
+        let config = SessionConfig {
+            countdown_seconds: 1,
+            ..SessionConfig::default()
+        };
+        let mut menu = SessionMenu::new(config);
+        menu.pending = Some((
+            SessionAction::Logout,
+            Instant::now() - Duration::from_secs(2),
+        ));
+        assert_eq!(
+            frame(&ctx, &mut menu, Vec::new()),
+            Some(SessionAction::Logout)
+        );
+    }
+}
@@ -1,27 +1,47 @@
 use eframe::egui;
-use egui::{Color32, Rect, RichText};
+use egui::{Key, Rect, RichText, Stroke, StrokeKind};
+use egui_phosphor::regular::*;
+use std::ops::RangeInclusive;
 
-use crate::Colors;
+use crate::palette::Palette;
 
-/// Draw a custom colored slider that matches the design in the image
+/// What to draw on top of the slider track
+pub struct SliderDecor<'a> {
+    pub icon: Option<&'a str>,
+    /// Print the value as a percentage of the range at the end of the track
+    pub show_percent: bool,
+    /// Draw the fill faded, e.g. while the output is muted
+    pub dimmed: bool,
+}
+
+/// Responses for the parts of a slider that can be interacted with
+pub struct SliderResponse {
+    pub track: egui::Response,
+    /// The icon's own hit area, present when an icon is drawn
+    pub icon: Option<egui::Response>,
+}
+
+/// Draw a custom colored slider that matches the design in the image.
+/// Pointer clicks and drags move `value` within `range`; the returned responses
+/// let the caller tell drags apart from clicks and handle focus.
 pub fn draw_colored_slider(
     ui: &mut egui::Ui,
     value: &mut f32,
+    range: RangeInclusive<f32>,
     full_width: f32,
-    colors: &Colors,
-    icon: Option<&str>,
-) {
+    colors: &Palette,
+    decor: &SliderDecor,
+) -> SliderResponse {
     let height = 40.0; // Height to match the image
+    let (min, max) = (*range.start(), *range.end());
 
     // Configure colors - based on the image and theme
-    let track_bg = Color32::from_rgb(80, 80, 80); // Medium gray background for the track
-    let filled_color = colors.primary; // Use primary color for the filled portion
-    let thumb_color = Color32::WHITE; // Keep thumb white for contrast
-    let icon_color = colors.background; // Use on_surface color for icon
-
-    // Calculate filled width based on the value
-    let fill_ratio = (*value / 100.0).clamp(0.0, 1.0);
-    let filled_width = full_width * fill_ratio;
+    let track_bg = colors.surface_container_highest; // Raised container tone for the track
+    let mut filled_color = colors.primary; // Use primary color for the filled portion
+    let thumb_color = colors.inverse_surface; // Near-white in dark themes for contrast
+    if decor.dimmed {
+        filled_color = filled_color.gamma_multiply(0.4);
+    }
 
     // Get available space and create a rectangle
     let (rect, response) = ui.allocate_at_least(
@@ -29,14 +49,30 @@ pub fn draw_colored_slider(
         egui::Sense::click_and_drag(),
     );
 
+    // The icon gets its own click target on top of the track
+    let icon_rect = Rect::from_min_size(rect.min, egui::vec2(height + 8.0, height));
+    let icon_response = decor.icon.map(|_| {
+        ui.interact(icon_rect, response.id.with("icon"), egui::Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand)
+    });
+
     // Handle interaction - update value based on mouse click/drag
-    if response.dragged() || response.clicked() {
-        if let Some(mouse_pos) = response.interact_pointer_pos() {
-            let new_ratio = ((mouse_pos.x - rect.min.x) / rect.width()).clamp(0.0, 1.0);
-            *value = new_ratio * 100.0;
-        }
+    if let Some(mouse_pos) = response
+        .interact_pointer_pos()
+        .filter(|_| response.dragged() || response.clicked())
+    {
+        let new_ratio = ((mouse_pos.x - rect.min.x) / rect.width()).clamp(0.0, 1.0);
+        *value = min + new_ratio * (max - min);
     }
 
+    // Calculate filled width based on the value
+    let fill_ratio = if max > min {
+        ((*value - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let filled_width = full_width * fill_ratio;
+
     // Get the painter to draw custom visuals
     let painter = ui.painter();
 
@@ -56,6 +92,16 @@ pub fn draw_colored_slider(
         );
     }
 
+    // Outline the track while it has keyboard focus
+    if response.has_focus() {
+        painter.rect_stroke(
+            rect,
+            height / 2.0,
+            Stroke::new(2.0, colors.outline),
+            StrokeKind::Outside,
+        );
+    }
+
     // Calculate thumb position - make it exactly match the track height
     let thumb_radius = height - 6.0; // Slightly smaller than the track height
 
@@ -75,8 +121,22 @@ pub fn draw_colored_slider(
     painter.circle_filled(thumb_center, thumb_radius / 2.0, thumb_color);
 
     // Draw the icon if provided
-    if let Some(icon_str) = icon {
+    if let Some(icon_str) = decor.icon {
         let icon_pos = rect.min + egui::vec2(20.0, height / 2.0);
+        // The icon sits on the fill once the fill reaches past it, otherwise on the bare track
+        let icon_color = if filled_width >= 40.0 && !decor.dimmed {
+            colors.on_primary
+        } else {
+            colors.on_surface
+        };
+        // Highlight the icon's hit area on hover so it reads as a button
+        if icon_response.as_ref().is_some_and(|r| r.hovered()) {
+            painter.circle_filled(
+                icon_pos + egui::vec2(10.0, 0.0),
+                16.0,
+                icon_color.gamma_multiply(0.15),
+            );
+        }
         painter.text(
             icon_pos,
             egui::Align2::LEFT_CENTER,
@@ -85,6 +145,56 @@ pub fn draw_colored_slider(
             icon_color,
         );
     }
+
+    // Draw the value label at the right end of the track
+    if decor.show_percent {
+        let label = format!("{:.0}%", fill_ratio * 100.0);
+        let label_pos = egui::pos2(rect.max.x - thumb_radius - 12.0, rect.center().y);
+        let label_color = if filled_width >= full_width - thumb_radius - 48.0 && !decor.dimmed {
+            colors.on_primary
+        } else {
+            colors.on_surface
+        };
+        painter.text(
+            label_pos,
+            egui::Align2::RIGHT_CENTER,
+            label,
+            egui::FontId::proportional(13.0),
+            label_color,
+        );
+    }
+
+    SliderResponse {
+        track: response,
+        icon: icon_response,
+    }
+}
+
+/// What happened to an `IconSlider` this frame. Backends can apply `Started`
+/// and `Changed` as a cheap live preview and only persist on `Committed`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SliderEvent {
+    /// A drag began, carrying the value under the pointer
+    Started(f32),
+    /// The value moved during a drag
+    Changed(f32),
+    /// The final value after a drag, click, wheel step or key press
+    Committed(f32),
+    /// The icon was clicked to mute (true) or unmute (false)
+    Muted(bool),
+}
+
+/// Controls which glyphs the icon cycles through and what clicking it does
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliderKind {
+    /// Always shows the icon it was created with, clicking it does nothing
+    Plain,
+    /// Speaker glyphs by level; clicking toggles mute and keeps the level
+    Volume,
+    /// Sun glyphs by level; clicking drops to the minimum and restores after
+    Brightness,
+    /// Microphone glyph, slashed while muted; clicking toggles mute
+    Microphone,
 }
 
 /// A component for displaying a slider with an icon
@@ -92,6 +202,19 @@ pub struct IconSlider {
     pub value: f32,
     pub icon: String,
     pub title: Option<String>,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+    pub show_value: bool,
+    pub kind: SliderKind,
+    pub muted: bool,
+    /// Value to go back to when a `Brightness` slider leaves the minimum
+    restore_value: Option<f32>,
+    /// Wheel movement not yet turned into steps, so a touchpad flick moves
+    /// as far as it scrolled rather than a step per frame
+    wheel_delta: f32,
+    /// Between `Started` and `Committed`, so polling can leave the value alone
+    dragging: bool,
 }
 
 impl IconSlider {
@@ -100,6 +223,70 @@ impl IconSlider {
             value,
             icon,
             title: None,
+            min: 0.0,
+            max: 100.0,
+            step: 5.0,
+            show_value: false,
+            kind: SliderKind::Plain,
+            muted: false,
+            restore_value: None,
+            wheel_delta: 0.0,
+            dragging: false,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub fn with_kind(mut self, kind: SliderKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// The glyph for the current level and mute state
+    pub fn glyph(&self) -> &str {
+        let level = if self.max > self.min {
+            (self.value - self.min) / (self.max - self.min)
+        } else {
+            0.0
+        };
+        match self.kind {
+            SliderKind::Plain => &self.icon,
+            SliderKind::Volume if self.muted => SPEAKER_X,
+            SliderKind::Volume if level <= 0.0 => SPEAKER_NONE,
+            SliderKind::Volume if level < 0.5 => SPEAKER_LOW,
+            SliderKind::Volume => SPEAKER_HIGH,
+            SliderKind::Brightness if self.muted || level < 0.3 => SUN_DIM,
+            SliderKind::Brightness => SUN,
+            SliderKind::Microphone if self.muted => MICROPHONE_SLASH,
+            SliderKind::Microphone => MICROPHONE,
+        }
+    }
+
+    /// Handles a click on the icon: mute/unmute for volume and microphone,
+    /// jump to the minimum and back for brightness
+    fn toggle_mute(&mut self) -> Option<SliderEvent> {
+        match self.kind {
+            SliderKind::Plain => None,
+            SliderKind::Volume | SliderKind::Microphone => {
+                self.muted = !self.muted;
+                Some(SliderEvent::Muted(self.muted))
+            }
+            SliderKind::Brightness => {
+                match self.restore_value.take() {
+                    Some(previous) => {
+                        self.value = previous;
+                        self.muted = false;
+                    }
+                    None => {
+                        self.restore_value = Some(self.value);
+                        self.value = self.min;
+                        self.muted = true;
+                    }
+                }
+                Some(SliderEvent::Committed(self.value))
+            }
         }
     }
 
@@ -108,7 +295,38 @@ impl IconSlider {
         self
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Colors) {
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.min = min;
+        self.max = max;
+        self.value = self.value.clamp(min, max);
+        self
+    }
+
+    /// Amount a wheel notch or arrow key moves the value, and the grid values
+    /// snap to
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Show the value as a percentage of the range at the end of the track
+    pub fn with_value_label(mut self) -> Self {
+        self.show_value = true;
+        self
+    }
+
+    /// `value` rounded to the nearest multiple of the step, within the range.
+    /// The ends stay reachable even when they're off the grid.
+    fn snap(&self, value: f32) -> f32 {
+        let snapped = if self.step > 0.0 {
+            (value / self.step).round() * self.step
+        } else {
+            value
+        };
+        snapped.clamp(self.min, self.max)
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) -> Option<SliderEvent> {
         // If we have a title, show it
         if let Some(title) = &self.title {
             ui.horizontal(|ui| {
@@ -121,21 +339,241 @@ impl IconSlider {
 
         ui.add_space(4.0); // Add slight spacing to match the image
 
-        // Create a container for the slider
-        let height = 40.0;
-
         // Reserve space for the slider
         let available_width = ui.available_width();
 
         // Draw the slider directly with the icon built-in
-        draw_colored_slider(
+        let glyph = self.glyph().to_string();
+        let decor = SliderDecor {
+            icon: Some(&glyph),
+            show_percent: self.show_value,
+            dimmed: self.muted,
+        };
+        let SliderResponse {
+            track: response,
+            icon,
+        } = draw_colored_slider(
             ui,
             &mut self.value,
+            self.min..=self.max,
             available_width,
             colors,
-            Some(&self.icon),
+            &decor,
         );
 
         ui.add_space(4.0); // Add slight spacing after slider
+
+        if response.dragged() || response.clicked() {
+            self.value = self.snap(self.value);
+        }
+
+        if icon.is_some_and(|icon| icon.clicked()) {
+            return self.toggle_mute();
+        }
+
+        let event = self.track_event(ui, &response);
+
+        // Moving a brightness slider away from the minimum by hand forgets the
+        // level the icon would restore. Volume keeps its mute flag, that's
+        // separate from the level.
+        if event.is_some() && self.kind == SliderKind::Brightness {
+            self.muted = false;
+            self.restore_value = None;
+        }
+        match event {
+            Some(SliderEvent::Started(_)) | Some(SliderEvent::Changed(_)) => self.dragging = true,
+            Some(_) => self.dragging = false,
+            None => {}
+        }
+
+        event
+    }
+
+    /// Pointer, wheel and keyboard handling for the track itself
+    fn track_event(&mut self, ui: &egui::Ui, response: &egui::Response) -> Option<SliderEvent> {
+        if response.drag_started() {
+            response.request_focus();
+            return Some(SliderEvent::Started(self.value));
+        }
+        if response.drag_stopped() {
+            return Some(SliderEvent::Committed(self.value));
+        }
+        if response.dragged() {
+            return Some(SliderEvent::Changed(self.value));
+        }
+        if response.clicked() {
+            response.request_focus();
+            return Some(SliderEvent::Committed(self.value));
+        }
+
+        let target = self
+            .wheel_steps(ui, response)
+            .or_else(|| self.key_steps(ui, response));
+        if let Some(target) = target {
+            let new_value = self.snap(target);
+            if new_value != self.value {
+                self.value = new_value;
+                return Some(SliderEvent::Committed(self.value));
+            }
+        }
+
+        None
+    }
+
+    /// Target value from mouse wheel movement over the slider. Movement adds
+    /// up until it's worth a wheel notch, then each notch is one step.
+    fn wheel_steps(&mut self, ui: &egui::Ui, response: &egui::Response) -> Option<f32> {
+        if !response.hovered() {
+            self.wheel_delta = 0.0;
+            return None;
+        }
+
+        let delta = ui.input(|i| i.raw_scroll_delta);
+        let amount = if delta.y != 0.0 { delta.y } else { delta.x };
+        if amount == 0.0 {
+            return None;
+        }
+
+        // Keep the surrounding ScrollArea from scrolling while we adjust the value
+        ui.ctx()
+            .input_mut(|i| i.smooth_scroll_delta = egui::Vec2::ZERO);
+
+        // Turning around drops what was left over from the other direction
+        if self.wheel_delta != 0.0 && self.wheel_delta.signum() != amount.signum() {
+            self.wheel_delta = 0.0;
+        }
+        self.wheel_delta += amount;
+        let notch = ui.ctx().options(|o| o.line_scroll_speed);
+        let steps = (self.wheel_delta / notch).trunc();
+        if steps == 0.0 {
+            return None;
+        }
+        self.wheel_delta -= steps * notch;
+        Some(self.value + steps * self.step)
+    }
+
+    /// Target value from arrow, PageUp/PageDown, Home and End while focused
+    fn key_steps(&self, ui: &egui::Ui, response: &egui::Response) -> Option<f32> {
+        if !response.has_focus() {
+            return None;
+        }
+
+        // Arrows adjust the value instead of moving focus to the next widget
+        ui.memory_mut(|m| {
+            m.set_focus_lock_filter(
+                response.id,
+                egui::EventFilter {
+                    horizontal_arrows: true,
+                    vertical_arrows: true,
+                    ..Default::default()
+                },
+            )
+        });
+
+        let page = (self.max - self.min) / 10.0;
+        ui.input(|i| {
+            if i.key_pressed(Key::Home) {
+                Some(self.min)
+            } else if i.key_pressed(Key::End) {
+                Some(self.max)
+            } else {
+                let up = i.num_presses(Key::ArrowRight) + i.num_presses(Key::ArrowUp);
+                let down = i.num_presses(Key::ArrowLeft) + i.num_presses(Key::ArrowDown);
+                let page_up = i.num_presses(Key::PageUp);
+                let page_down = i.num_presses(Key::PageDown);
+                let delta = (up as f32 - down as f32) * self.step
+                    + (page_up as f32 - page_down as f32) * page;
+                (delta != 0.0).then_some(self.value + delta)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, MouseWheelUnit, Pos2, RawInput, vec2};
+
+    /// Runs one frame with the pointer over the track and returns the event
+    fn frame(
+        ctx: &egui::Context,
+        slider: &mut IconSlider,
+        events: Vec<Event>,
+    ) -> Option<SliderEvent> {
+        let mut pointer = vec![Event::PointerMoved(Pos2::new(200.0, 30.0))];
+        pointer.extend(events);
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, vec2(400.0, 200.0))),
+            events: pointer,
+            ..Default::default()
+        };
+        let mut event = None;
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                event = slider.show(ui, &Palette::default());
+            });
+        });
+        event
+    }
+
+    fn wheel(points: f32) -> Event {
+        Event::MouseWheel {
+            unit: MouseWheelUnit::Point,
+            delta: vec2(0.0, points),
+            modifiers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn snaps_to_the_step_grid() {
+        let slider = IconSlider::new(50.0, SUN.to_string())
+            .with_range(1.0, 100.0)
+            .with_step(5.0);
+        assert_eq!(slider.snap(12.4), 10.0);
+        assert_eq!(slider.snap(13.0), 15.0);
+        // Below the first grid value the minimum is still reachable
+        assert_eq!(slider.snap(1.2), 1.0);
+        assert_eq!(slider.snap(120.0), 100.0);
+    }
+
+    #[test]
+    fn touchpad_scroll_adds_up_to_steps() {
+        let ctx = egui::Context::default();
+        let mut slider = IconSlider::new(50.0, SPEAKER_HIGH.to_string()).with_step(2.0);
+        frame(&ctx, &mut slider, Vec::new());
+
+        // Ten small deltas add up to two notches of 40 points
+        let events: Vec<_> = (0..10)
+            .filter_map(|_| frame(&ctx, &mut slider, vec![wheel(8.0)]))
+            .collect();
+        assert_eq!(
+            events,
+            vec![SliderEvent::Committed(52.0), SliderEvent::Committed(54.0)]
+        );
+
+        // Turning around starts counting afresh
+        assert_eq!(frame(&ctx, &mut slider, vec![wheel(-30.0)]), None);
+        assert_eq!(
+            frame(&ctx, &mut slider, vec![wheel(-10.0)]),
+            Some(SliderEvent::Committed(52.0))
+        );
+    }
+
+    #[test]
+    fn one_wheel_notch_is_one_step() {
+        let ctx = egui::Context::default();
+        let mut slider = IconSlider::new(51.0, SPEAKER_HIGH.to_string()).with_step(5.0);
+        frame(&ctx, &mut slider, Vec::new());
+        let event = frame(
+            &ctx,
+            &mut slider,
+            vec![Event::MouseWheel {
+                unit: MouseWheelUnit::Line,
+                delta: vec2(0.0, -1.0),
+                modifiers: Default::default(),
+            }],
+        );
+        // 51 - 5 lands off the grid and snaps back onto it
+        assert_eq!(event, Some(SliderEvent::Committed(45.0)));
     }
 }
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::Arc;
+
+use crate::command_runner::CommandRunner;
+
+const UDISKS_PREFIX: &str = "/org/freedesktop/UDisks2/";
+
+/// Space on a mounted filesystem, in bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub mount: String,
+    pub total: u64,
+    /// What unprivileged users can still write
+    pub available: u64,
+}
+
+impl DiskUsage {
+    pub fn used_fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        1.0 - self.available as f32 / self.total as f32
+    }
+}
+
+pub fn disk_usage(mount: &str) -> Result<DiskUsage, String> {
+    let path = CString::new(mount).map_err(|e| e.to_string())?;
+    // SAFETY: statvfs only writes to the struct we hand it
+    let stat = unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(path.as_ptr(), &mut stat) != 0 {
+            return Err(format!("{}: {}", mount, std::io::Error::last_os_error()));
+        }
+        stat
+    };
+    let block = stat.f_frsize as u64;
+    Ok(DiskUsage {
+        mount: mount.to_string(),
+        total: stat.f_blocks as u64 * block,
+        available: stat.f_bavail as u64 * block,
+    })
+}
+
+/// Usage of each mount that can be read, logging the rest. statvfs can
+/// hang on a dead network share, so keep this off the UI thread.
+pub fn disk_usages(mounts: &[String]) -> Vec<DiskUsage> {
+    mounts
+        .iter()
+        .filter_map(|mount| match disk_usage(mount) {
+            Ok(usage) => Some(usage),
+            Err(e) => {
+                eprintln!("Failed to read disk usage: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// A filesystem on a removable drive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Volume {
+    pub device: String,
+    pub label: String,
+    pub size: u64,
+    pub mount_points: Vec<String>,
+}
+
+/// A USB stick, SD card or similar, as UDisks2 sees it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemovableDrive {
+    /// UDisks object path relative to /org/freedesktop/UDisks2, e.g.
+    /// "drives/SanDisk_Ultra_4C53"
+    pub object: String,
+    pub name: String,
+    pub can_power_off: bool,
+    pub volumes: Vec<Volume>,
+}
+
+/// Removable drives through `udisksctl`. Cheap to clone, so mounting (which
+/// may wait on a polkit prompt) can run on a background thread.
+#[derive(Clone)]
+pub struct StorageBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl StorageBackend {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    pub fn removable_drives(&self) -> Result<Vec<RemovableDrive>, String> {
+        let output = self.runner.run("udisksctl", &["dump"])?;
+        Ok(removable_drives(&parse_dump(&output)))
+    }
+
+    /// Mounts `device` under /run/media/$USER
+    pub fn mount(&self, device: &str) -> Result<(), String> {
+        self.runner
+            .run("udisksctl", &["mount", "--block-device", device])
+            .map(|_| ())
+    }
+
+    pub fn unmount(&self, device: &str) -> Result<(), String> {
+        self.runner
+            .run("udisksctl", &["unmount", "--block-device", device])
+            .map(|_| ())
+    }
+
+    /// Unmounts whatever is still mounted, then cuts the drive's power so it
+    /// can be pulled out
+    pub fn power_off(&self, drive: &RemovableDrive) -> Result<(), String> {
+        for volume in drive.volumes.iter().filter(|v| !v.mount_points.is_empty()) {
+            self.unmount(&volume.device)?;
+        }
+        self.runner
+            .run("udisksctl", &["power-off", "--object-path", &drive.object])
+            .map(|_| ())
+    }
+}
+
+/// Object path -> interface -> property -> value, from `udisksctl dump`.
+/// Multi-line values (several mount points) are joined with newlines.
+pub type UdisksObjects = HashMap<String, HashMap<String, HashMap<String, String>>>;
+
+pub fn parse_dump(output: &str) -> UdisksObjects {
+    let mut objects = UdisksObjects::new();
+    let mut object: Option<String> = None;
+    let mut interface: Option<String> = None;
+    let mut property: Option<String> = None;
+
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let text = line.trim();
+        match indent {
+            0 => {
+                object = text.strip_suffix(':').map(|path| path.to_string());
+                interface = None;
+                property = None;
+            }
+            2 => {
+                interface = text.strip_suffix(':').map(|name| name.to_string());
+                property = None;
+            }
+            _ => {
+                let (Some(object), Some(interface)) = (&object, &interface) else {
+                    continue;
+                };
+                let properties = objects
+                    .entry(object.clone())
+                    .or_default()
+                    .entry(interface.clone())
+                    .or_default();
+                // Property lines sit at four spaces, continuations further in
+                match text.split_once(':') {
+                    Some((key, value)) if indent == 4 && !key.contains(' ') => {
+                        properties.insert(key.to_string(), value.trim().to_string());
+                        property = Some(key.to_string());
+                    }
+                    _ => {
+                        if let Some(value) = property
+                            .as_ref()
+                            .and_then(|property| properties.get_mut(property))
+                        {
+                            value.push('\n');
+                            value.push_str(text);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    objects
+}
+
+/// udisksctl quotes object paths and some strings
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches('\'')
+}
+
+/// Drives that are removable and not system drives, with their filesystems
+pub fn removable_drives(objects: &UdisksObjects) -> Vec<RemovableDrive> {
+    let mut drives: Vec<RemovableDrive> = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let drive = interfaces.get("org.freedesktop.UDisks2.Drive")?;
+            let flag = |key: &str| drive.get(key).is_some_and(|value| value == "true");
+            let bus = drive.get("ConnectionBus").map(|bus| unquote(bus));
+            let removable = flag("Removable")
+                || flag("MediaRemovable")
+                || flag("Ejectable")
+                || matches!(bus, Some("usb") | Some("sdio"));
+            if !removable {
+                return None;
+            }
+            let name = [drive.get("Vendor"), drive.get("Model")]
+                .into_iter()
+                .flatten()
+                .map(|part| unquote(part))
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(RemovableDrive {
+                object: path.strip_prefix(UDISKS_PREFIX).unwrap_or(path).to_string(),
+                name: if name.is_empty() {
+                    "Removable drive".to_string()
+                } else {
+                    name
+                },
+                can_power_off: flag("CanPowerOff"),
+                volumes: Vec::new(),
+            })
+        })
+        .collect();
+
+    for interfaces in objects.values() {
+        let (Some(block), Some(filesystem)) = (
+            interfaces.get("org.freedesktop.UDisks2.Block"),
+            interfaces.get("org.freedesktop.UDisks2.Filesystem"),
+        ) else {
+            continue;
+        };
+        let hidden = ["HintIgnore", "HintSystem"]
+            .iter()
+            .any(|key| block.get(*key).is_some_and(|value| value == "true"));
+        if hidden {
+            continue;
+        }
+        let drive_path = block
+            .get("Drive")
+            .map(|drive| unquote(drive))
+            .unwrap_or("/");
+        let Some(drive) = drives
+            .iter_mut()
+            .find(|drive| drive_path.strip_prefix(UDISKS_PREFIX) == Some(drive.object.as_str()))
+        else {
+            continue;
+        };
+        let device = block
+            .get("PreferredDevice")
+            .or(block.get("Device"))
+            .map(|device| unquote(device).to_string())
+            .unwrap_or_default();
+        let label = block
+            .get("IdLabel")
+            .map(|label| unquote(label).to_string())
+            .filter(|label| !label.is_empty())
+            .unwrap_or_else(|| device.trim_start_matches("/dev/").to_string());
+        drive.volumes.push(Volume {
+            device,
+            label,
+            size: block
+                .get("Size")
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(0),
+            mount_points: filesystem
+                .get("MountPoints")
+                .map(|points| {
+                    points
+                        .lines()
+                        .map(|point| unquote(point).to_string())
+                        .filter(|point| !point.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        });
+    }
+
+    for drive in &mut drives {
+        drive.volumes.sort_by(|a, b| a.device.cmp(&b.device));
+    }
+    drives.sort_by(|a, b| a.object.cmp(&b.object));
+    drives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    /// A laptop with its NVMe system drive, a snap loop device and a
+    /// two-partition USB stick
+    fn dump() -> String {
+        std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/storage/udisksctl-dump.txt"
+        ))
+        .unwrap()
+    }
+
+    fn stick() -> RemovableDrive {
+        RemovableDrive {
+            object: "drives/SanDisk_Ultra_4C530001230921117283".to_string(),
+            name: "SanDisk Ultra".to_string(),
+            can_power_off: true,
+            volumes: vec![
+                Volume {
+                    device: "/dev/sda1".to_string(),
+                    label: "PHOTOS".to_string(),
+                    size: 21_474_836_480,
+                    mount_points: vec![
+                        "/run/media/alex/PHOTOS".to_string(),
+                        "/mnt/photos".to_string(),
+                    ],
+                },
+                Volume {
+                    device: "/dev/sda2".to_string(),
+                    label: "sda2".to_string(),
+                    size: 9_290_383_360,
+                    mount_points: Vec::new(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn parses_multi_line_values() {
+        let objects = parse_dump(&dump());
+        let manager =
+            &objects["/org/freedesktop/UDisks2/Manager"]["org.freedesktop.UDisks2.Manager"];
+        assert_eq!(manager["SupportedEncryptionTypes"], "luks1\nluks2");
+        assert_eq!(manager["Version"], "2.10.1");
+    }
+
+    #[test]
+    fn lists_only_removable_drives() {
+        let backend = StorageBackend::new(Arc::new(
+            FakeRunner::default().with("udisksctl dump", &dump()),
+        ));
+        assert_eq!(backend.removable_drives().unwrap(), [stick()]);
+    }
+
+    #[test]
+    fn power_off_unmounts_first() {
+        let runner = Arc::new(
+            FakeRunner::default()
+                .with(
+                    "udisksctl unmount --block-device /dev/sda1",
+                    "Unmounted /dev/sda1.\n",
+                )
+                .with(
+                    "udisksctl power-off --object-path drives/SanDisk_Ultra_4C530001230921117283",
+                    "",
+                ),
+        );
+        StorageBackend::new(runner.clone())
+            .power_off(&stick())
+            .unwrap();
+        assert_eq!(
+            runner.calls(),
+            [
+                "udisksctl unmount --block-device /dev/sda1",
+                "udisksctl power-off --object-path drives/SanDisk_Ultra_4C530001230921117283",
+            ]
+        );
+    }
+
+    #[test]
+    fn power_off_stops_when_unmount_fails() {
+        let runner = Arc::new(FakeRunner::default().failing(
+            "udisksctl unmount --block-device /dev/sda1",
+            "Error unmounting /dev/sda1: target is busy",
+        ));
+        let result = StorageBackend::new(runner.clone()).power_off(&stick());
+        assert_eq!(
+            result,
+            Err("Error unmounting /dev/sda1: target is busy".to_string())
+        );
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[test]
+    fn reads_disk_usage() {
+        let usage = disk_usages(&["/".to_string(), "/no/such/mount".to_string()]);
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].mount, "/");
+        assert!(usage[0].total > 0 && usage[0].available <= usage[0].total);
+        assert!((0.0..=1.0).contains(&usage[0].used_fraction()));
+    }
+}
@@ -0,0 +1,312 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::time::{Duration, Instant};
+
+use crate::config::StorageConfig;
+use crate::files::format_size;
+use crate::notify::{self, Urgency};
+use crate::palette::Palette;
+use crate::storage::{self, DiskUsage, RemovableDrive, StorageBackend, Volume};
+use crate::task::BackgroundTask;
+
+/// udisksctl dump takes a moment, and drives come and go by hand anyway
+const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bars turn red past this share used
+const FULL_WARNING: f32 = 0.9;
+
+/// One background rescan
+struct Scan {
+    usage: Vec<DiskUsage>,
+    drives: Result<Vec<RemovableDrive>, String>,
+}
+
+enum DriveAction {
+    Mount(String),
+    Unmount(String),
+    PowerOff(RemovableDrive),
+}
+
+/// Space left on the configured mounts, plus removable drives to mount,
+/// unmount and power off
+pub struct StorageSection {
+    backend: StorageBackend,
+    mounts: Vec<String>,
+    notify: bool,
+    usage: Vec<DiskUsage>,
+    drives: Vec<RemovableDrive>,
+    /// (object, name) of the drives seen last time, `None` until the first
+    /// scan so drives present at startup don't notify
+    known: Option<Vec<(String, String)>>,
+    refresh: Option<BackgroundTask<Scan>>,
+    /// Device or drive being worked on, and the work
+    busy: Option<(String, BackgroundTask<Result<(), String>>)>,
+    error: Option<String>,
+    last_update: Option<Instant>,
+}
+
+impl StorageSection {
+    pub fn new(backend: StorageBackend, config: &StorageConfig) -> Self {
+        Self {
+            backend,
+            mounts: config
+                .mounts
+                .iter()
+                .map(|mount| shellexpand::tilde(mount).into_owned())
+                .collect(),
+            notify: config.notify,
+            usage: Vec::new(),
+            drives: Vec::new(),
+            known: None,
+            refresh: None,
+            busy: None,
+            error: None,
+            last_update: None,
+        }
+    }
+
+    /// Rescans on a timer even while the section isn't drawn, so plugging a
+    /// drive in notifies from a detail page too
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        if let Some(scan) = self.refresh.as_ref().and_then(BackgroundTask::poll) {
+            self.refresh = None;
+            self.usage = scan.usage;
+            match scan.drives {
+                Ok(drives) => {
+                    self.announce(&drives);
+                    self.drives = drives;
+                }
+                Err(e) => eprintln!("Failed to list drives: {}", e),
+            }
+        }
+        if let Some(result) = self.busy.as_ref().and_then(|(_, task)| task.poll()) {
+            self.busy = None;
+            self.error = result.err();
+            self.last_update = None;
+        }
+
+        if self.refresh.is_none()
+            && self
+                .last_update
+                .is_none_or(|last| last.elapsed() >= UPDATE_INTERVAL)
+        {
+            self.last_update = Some(Instant::now());
+            let backend = self.backend.clone();
+            let mounts = self.mounts.clone();
+            self.refresh = Some(BackgroundTask::spawn(ctx, move || Scan {
+                usage: storage::disk_usages(&mounts),
+                drives: backend.removable_drives(),
+            }));
+        }
+        ctx.request_repaint_after(UPDATE_INTERVAL);
+    }
+
+    /// Notifies about drives that appeared or went away since the last scan
+    fn announce(&mut self, drives: &[RemovableDrive]) {
+        let current: Vec<(String, String)> = drives
+            .iter()
+            .map(|drive| (drive.object.clone(), drive.name.clone()))
+            .collect();
+        if let Some(known) = self.known.replace(current.clone())
+            && self.notify
+        {
+            for (_, name) in current.iter().filter(|drive| !known.contains(drive)) {
+                notify::send(
+                    "Drive connected",
+                    name,
+                    Some("drive-removable-media"),
+                    Urgency::Normal,
+                );
+            }
+            for (_, name) in known.iter().filter(|drive| !current.contains(drive)) {
+                notify::send(
+                    "Drive removed",
+                    name,
+                    Some("drive-removable-media"),
+                    Urgency::Normal,
+                );
+            }
+        }
+    }
+
+    fn run(&mut self, ctx: &egui::Context, action: DriveAction) {
+        let backend = self.backend.clone();
+        let (target, task) = match action {
+            DriveAction::Mount(device) => (
+                device.clone(),
+                BackgroundTask::spawn(ctx, move || backend.mount(&device)),
+            ),
+            DriveAction::Unmount(device) => (
+                device.clone(),
+                BackgroundTask::spawn(ctx, move || backend.unmount(&device)),
+            ),
+            DriveAction::PowerOff(drive) => (
+                drive.object.clone(),
+                BackgroundTask::spawn(ctx, move || backend.power_off(&drive)),
+            ),
+        };
+        self.error = None;
+        self.busy = Some((target, task));
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        for usage in &self.usage {
+            usage_row(ui, usage, colors);
+        }
+
+        if let Some(error) = &self.error {
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.label(RichText::new(error).size(12.0).color(colors.error));
+            });
+        }
+
+        let busy = self.busy.as_ref().map(|(target, _)| target.as_str());
+        let mut picked = None;
+        for drive in &self.drives {
+            if let Some(action) = drive_row(ui, drive, busy, colors) {
+                picked = Some(action);
+            }
+        }
+        if let Some(action) = picked
+            && self.busy.is_none()
+        {
+            self.run(ui.ctx(), action);
+        }
+    }
+}
+
+fn usage_row(ui: &mut egui::Ui, usage: &DiskUsage, colors: &Palette) {
+    let used = usage.used_fraction();
+    let level_color = if used >= FULL_WARNING {
+        colors.error
+    } else {
+        colors.primary
+    };
+    egui::Frame::new()
+        .fill(colors.surface)
+        .corner_radius(12.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(HARD_DRIVE)
+                        .size(20.0)
+                        .color(colors.on_surface),
+                );
+                ui.vertical(|ui| {
+                    ui.label(RichText::new(&usage.mount).color(colors.on_surface));
+                    ui.label(
+                        RichText::new(format!(
+                            "{} free of {}",
+                            format_size(usage.available),
+                            format_size(usage.total)
+                        ))
+                        .size(12.0)
+                        .color(colors.on_surface.gamma_multiply(0.7)),
+                    );
+                });
+            });
+            ui.add_space(4.0);
+            ui.add(
+                egui::ProgressBar::new(used)
+                    .desired_height(4.0)
+                    .fill(level_color),
+            );
+        });
+    ui.add_space(4.0);
+}
+
+/// A removable drive with its volumes; returns what the user clicked
+fn drive_row(
+    ui: &mut egui::Ui,
+    drive: &RemovableDrive,
+    busy: Option<&str>,
+    colors: &Palette,
+) -> Option<DriveAction> {
+    let mut action = None;
+    egui::Frame::new()
+        .fill(colors.surface)
+        .corner_radius(12.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(USB).size(20.0).color(colors.on_surface));
+                ui.label(RichText::new(&drive.name).color(colors.on_surface));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if busy == Some(drive.object.as_str()) {
+                        ui.spinner();
+                    } else if drive.can_power_off
+                        && icon_button(ui, POWER, colors)
+                            .on_hover_text("Unmount and power off")
+                            .clicked()
+                    {
+                        action = Some(DriveAction::PowerOff(drive.clone()));
+                    }
+                });
+            });
+            for volume in &drive.volumes {
+                if let Some(picked) = volume_row(ui, volume, busy, colors) {
+                    action = Some(picked);
+                }
+            }
+        });
+    ui.add_space(4.0);
+    action
+}
+
+fn volume_row(
+    ui: &mut egui::Ui,
+    volume: &Volume,
+    busy: Option<&str>,
+    colors: &Palette,
+) -> Option<DriveAction> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        ui.add_space(28.0);
+        ui.vertical(|ui| {
+            ui.label(
+                RichText::new(format!("{} · {}", volume.label, format_size(volume.size)))
+                    .size(13.0)
+                    .color(colors.on_surface),
+            );
+            let status = match volume.mount_points.first() {
+                Some(mount) => mount.clone(),
+                None => "Not mounted".to_string(),
+            };
+            ui.label(
+                RichText::new(status)
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+        });
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if busy == Some(volume.device.as_str()) {
+                ui.spinner();
+            } else if volume.mount_points.is_empty() {
+                if icon_button(ui, FOLDER_OPEN, colors)
+                    .on_hover_text("Mount")
+                    .clicked()
+                {
+                    action = Some(DriveAction::Mount(volume.device.clone()));
+                }
+            } else if icon_button(ui, EJECT, colors)
+                .on_hover_text("Unmount")
+                .clicked()
+            {
+                action = Some(DriveAction::Unmount(volume.device.clone()));
+            }
+        });
+    });
+    action
+}
+
+fn icon_button(ui: &mut egui::Ui, icon: &str, colors: &Palette) -> egui::Response {
+    ui.add(
+        Button::new(RichText::new(icon).size(18.0).color(colors.on_surface))
+            .fill(Color32::TRANSPARENT),
+    )
+}
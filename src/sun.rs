@@ -0,0 +1,183 @@
+//! Sunrise and sunset, worked out offline with the sunrise equation from
+//! the Almanac for Computers. Good to a minute or two, plenty for turning a
+//! night light on.
+
+/// Sun's centre 50' below the horizon, accounting for refraction and its radius
+const ZENITH: f64 = 90.833;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SunTimes {
+    /// Minutes after UTC midnight
+    Normal { sunrise: f64, sunset: f64 },
+    /// Midnight sun
+    AlwaysUp,
+    /// Polar night
+    AlwaysDown,
+}
+
+/// When the sun rises and sets on `day_of_year` (1-366) at the given spot.
+/// Latitude is positive north, longitude positive east.
+pub fn sun_times(day_of_year: u32, latitude: f64, longitude: f64) -> SunTimes {
+    let rising = event_time(day_of_year, latitude, longitude, true);
+    let setting = event_time(day_of_year, latitude, longitude, false);
+    match (rising, setting) {
+        (Ok(sunrise), Ok(sunset)) => SunTimes::Normal { sunrise, sunset },
+        (Err(always_up), _) | (_, Err(always_up)) => {
+            if always_up {
+                SunTimes::AlwaysUp
+            } else {
+                SunTimes::AlwaysDown
+            }
+        }
+    }
+}
+
+/// Minutes after UTC midnight of the sunrise (or sunset). Errs with whether
+/// the sun stays up (true) or down (false) all day instead.
+fn event_time(day_of_year: u32, latitude: f64, longitude: f64, rising: bool) -> Result<f64, bool> {
+    let lng_hour = longitude / 15.0;
+    let approx = if rising { 6.0 } else { 18.0 };
+    let t = day_of_year as f64 + (approx - lng_hour) / 24.0;
+
+    // Sun's mean anomaly and true longitude
+    let mean_anomaly = 0.9856 * t - 3.289;
+    let true_longitude = (mean_anomaly
+        + 1.916 * sin_deg(mean_anomaly)
+        + 0.020 * sin_deg(2.0 * mean_anomaly)
+        + 282.634)
+        .rem_euclid(360.0);
+
+    // Right ascension, moved into the same quadrant as the longitude
+    let mut right_ascension = atan_deg(0.91764 * tan_deg(true_longitude)).rem_euclid(360.0);
+    right_ascension +=
+        (true_longitude / 90.0).floor() * 90.0 - (right_ascension / 90.0).floor() * 90.0;
+    let right_ascension = right_ascension / 15.0;
+
+    let sin_declination = 0.39782 * sin_deg(true_longitude);
+    let cos_declination = sin_declination.asin().cos();
+
+    let cos_hour_angle = (cos_deg(ZENITH) - sin_declination * sin_deg(latitude))
+        / (cos_declination * cos_deg(latitude));
+    if cos_hour_angle > 1.0 {
+        return Err(false);
+    }
+    if cos_hour_angle < -1.0 {
+        return Err(true);
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    let hour_angle = if rising {
+        360.0 - hour_angle
+    } else {
+        hour_angle
+    } / 15.0;
+
+    let local_mean_time = hour_angle + right_ascension - 0.06571 * t - 6.622;
+    let utc_hours = (local_mean_time - lng_hour).rem_euclid(24.0);
+    Ok(utc_hours * 60.0)
+}
+
+fn sin_deg(degrees: f64) -> f64 {
+    degrees.to_radians().sin()
+}
+
+fn cos_deg(degrees: f64) -> f64 {
+    degrees.to_radians().cos()
+}
+
+fn tan_deg(degrees: f64) -> f64 {
+    degrees.to_radians().tan()
+}
+
+fn atan_deg(value: f64) -> f64 {
+    value.atan().to_degrees()
+}
+
+/// Whether a daily window from `start` to `end` (minutes since midnight,
+/// possibly wrapping past midnight) covers `now`, and the minute it next
+/// switches
+pub fn window_state(now: u32, start: u32, end: u32) -> (bool, u32) {
+    let inside = if start <= end {
+        now >= start && now < end
+    } else {
+        now >= start || now < end
+    };
+    (inside, if inside { end } else { start })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Within five minutes of a published time, given as UTC "HH:MM"
+    fn assert_near(minutes: f64, expected: &str) {
+        let (hour, minute) = expected.split_once(':').unwrap();
+        let expected = hour.parse::<f64>().unwrap() * 60.0 + minute.parse::<f64>().unwrap();
+        // Compare around the clock, 23:58 is close to 00:01
+        let difference = (minutes - expected).rem_euclid(1440.0);
+        assert!(
+            difference.min(1440.0 - difference) <= 5.0,
+            "{:.0} min is not near {}",
+            minutes,
+            expected
+        );
+    }
+
+    fn normal(times: SunTimes) -> (f64, f64) {
+        match times {
+            SunTimes::Normal { sunrise, sunset } => (sunrise, sunset),
+            other => panic!("expected a sunrise and sunset, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn london_midsummer() {
+        // 21 June: 04:43 and 21:21 BST
+        let (sunrise, sunset) = normal(sun_times(172, 51.5074, -0.1278));
+        assert_near(sunrise, "03:43");
+        assert_near(sunset, "20:21");
+    }
+
+    #[test]
+    fn new_york_midwinter() {
+        // 21 December: 07:17 and 16:32 EST
+        let (sunrise, sunset) = normal(sun_times(355, 40.7128, -74.0060));
+        assert_near(sunrise, "12:17");
+        assert_near(sunset, "21:32");
+    }
+
+    #[test]
+    fn tokyo_sunrise_is_the_utc_day_before() {
+        // 21 June: 04:25 and 19:00 JST, so sunrise is 19:25 UTC
+        let (sunrise, sunset) = normal(sun_times(172, 35.6762, 139.6503));
+        assert_near(sunrise, "19:25");
+        assert_near(sunset, "10:00");
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        // Tromsø
+        assert_eq!(sun_times(172, 69.6492, 18.9553), SunTimes::AlwaysUp);
+        assert_eq!(sun_times(355, 69.6492, 18.9553), SunTimes::AlwaysDown);
+        // And the other way round in the south
+        assert_eq!(sun_times(172, -75.0, 0.0), SunTimes::AlwaysDown);
+    }
+
+    #[test]
+    fn window_within_a_day() {
+        assert_eq!(window_state(8 * 60, 9 * 60, 17 * 60), (false, 9 * 60));
+        assert_eq!(window_state(9 * 60, 9 * 60, 17 * 60), (true, 17 * 60));
+        assert_eq!(window_state(17 * 60, 9 * 60, 17 * 60), (false, 9 * 60));
+    }
+
+    #[test]
+    fn window_wrapping_past_midnight() {
+        let (start, end) = (20 * 60, 7 * 60);
+        assert_eq!(window_state(19 * 60 + 59, start, end), (false, start));
+        assert_eq!(window_state(20 * 60, start, end), (true, end));
+        assert_eq!(window_state(23 * 60 + 59, start, end), (true, end));
+        assert_eq!(window_state(0, start, end), (true, end));
+        assert_eq!(window_state(6 * 60 + 59, start, end), (true, end));
+        assert_eq!(window_state(7 * 60, start, end), (false, start));
+    }
+}
@@ -0,0 +1,86 @@
+use eframe::egui;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Work running on its own thread, for commands too slow to run inside a
+/// frame (scans, connecting, pairing). The UI polls it every frame.
+pub struct BackgroundTask<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T: Send + 'static> BackgroundTask<T> {
+    /// Runs `work` on a new thread and repaints `ctx` when it's done
+    pub fn spawn(ctx: &egui::Context, work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(work());
+            ctx.request_repaint();
+        });
+        Self { receiver }
+    }
+
+    /// The result once the work has finished. A task whose thread panicked
+    /// never finishes, callers treat it like one still running.
+    pub fn poll(&self) -> Option<T> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+type Write = Box<dyn FnOnce() -> Result<(), String> + Send>;
+
+/// Settings written through slow commands, like a volume while its slider is
+/// dragged. Only the newest write per key waits, so a fast drag doesn't queue
+/// up a command per frame, and one batch runs at a time.
+pub struct WriteQueue<K> {
+    pending: Vec<(K, Write)>,
+    running: Option<BackgroundTask<Result<(), String>>>,
+}
+
+impl<K: PartialEq> Default for WriteQueue<K> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            running: None,
+        }
+    }
+}
+
+impl<K: PartialEq> WriteQueue<K> {
+    /// Queues `write`, dropping an older one for the same key that hasn't
+    /// started yet. It runs on the next `poll`.
+    pub fn push(&mut self, key: K, write: impl FnOnce() -> Result<(), String> + Send + 'static) {
+        self.pending.retain(|(pending, _)| *pending != key);
+        self.pending.push((key, Box::new(write)));
+    }
+
+    /// Whether writes are waiting or running, so reading the setting back
+    /// now could return an old value
+    pub fn is_busy(&self) -> bool {
+        self.running.is_some() || !self.pending.is_empty()
+    }
+
+    /// Starts the waiting writes when nothing is running. Returns the result
+    /// of a batch that has finished, the first error if any failed.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<Result<(), String>> {
+        let finished = self.running.as_ref().and_then(BackgroundTask::poll);
+        if finished.is_some() {
+            self.running = None;
+        }
+        if self.running.is_none() && !self.pending.is_empty() {
+            let writes: Vec<Write> = std::mem::take(&mut self.pending)
+                .into_iter()
+                .map(|(_, write)| write)
+                .collect();
+            self.running = Some(BackgroundTask::spawn(ctx, move || {
+                writes
+                    .into_iter()
+                    .map(|write| write())
+                    .fold(Ok(()), Result::and)
+            }));
+        }
+        finished
+    }
+}
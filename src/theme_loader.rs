@@ -0,0 +1,443 @@
+use egui::Color32;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// The theme file formats we know how to read
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThemeFormat {
+    /// GTK CSS with `@define-color name value;` lines, as written by Matugen
+    Css,
+    /// Matugen's `--json hex` output
+    MatugenJson,
+    /// pywal's `~/.cache/wal/colors.json`
+    Pywal,
+}
+
+impl ThemeFormat {
+    pub fn from_name(name: &str) -> Option<ThemeFormat> {
+        match name {
+            "css" => Some(ThemeFormat::Css),
+            "matugen" => Some(ThemeFormat::MatugenJson),
+            "pywal" => Some(ThemeFormat::Pywal),
+            _ => None,
+        }
+    }
+}
+
+/// A problem with a theme file, pointing at the file and line that caused it
+#[derive(Debug)]
+pub struct ThemeError {
+    pub path: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path, self.line, self.message)
+    }
+}
+
+/// Parsed theme tokens (Matugen token name -> color)
+pub type Tokens = HashMap<String, Color32>;
+
+/// Reads a theme file and returns its color tokens. The format is detected
+/// from the contents unless `format` forces one. `scheme` picks the light or
+/// dark variant from Matugen JSON, which contains both.
+pub fn load(path: &str, format: Option<ThemeFormat>, scheme: &str) -> Result<Tokens, ThemeError> {
+    let contents = fs::read_to_string(path).map_err(|e| ThemeError {
+        path: path.to_string(),
+        line: 0,
+        message: e.to_string(),
+    })?;
+
+    let format = match format {
+        Some(format) => format,
+        None => detect_format(path, &contents)?,
+    };
+
+    match format {
+        ThemeFormat::Css => Ok(parse_css(path, &contents)),
+        ThemeFormat::MatugenJson => parse_matugen_json(path, &contents, scheme),
+        ThemeFormat::Pywal => parse_pywal(path, &contents),
+    }
+}
+
+fn detect_format(path: &str, contents: &str) -> Result<ThemeFormat, ThemeError> {
+    if contents.trim_start().starts_with('{') {
+        let json = parse_json(path, contents)?;
+        if json.get("special").is_some() && json.get("colors").is_some() {
+            return Ok(ThemeFormat::Pywal);
+        }
+        if json.get("colors").is_some_and(Value::is_object) {
+            return Ok(ThemeFormat::MatugenJson);
+        }
+        return Err(ThemeError {
+            path: path.to_string(),
+            line: 1,
+            message: "JSON theme is neither Matugen nor pywal output".to_string(),
+        });
+    }
+
+    if contents.contains("@define-color")
+        || Path::new(path).extension().is_some_and(|ext| ext == "css")
+    {
+        return Ok(ThemeFormat::Css);
+    }
+
+    // Point at the first line with actual content so the user knows what we saw
+    let line = contents
+        .lines()
+        .position(|line| !line.trim().is_empty())
+        .map_or(1, |index| index + 1);
+    Err(ThemeError {
+        path: path.to_string(),
+        line,
+        message: "unknown theme format".to_string(),
+    })
+}
+
+/// Parses `@define-color name value;` lines. Bad lines are reported and
+/// skipped so one typo doesn't throw away the whole theme.
+pub fn parse_css(path: &str, contents: &str) -> Tokens {
+    let mut tokens = Tokens::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let Some(rest) = line.trim().strip_prefix("@define-color") else {
+            continue;
+        };
+
+        // Name and value can be separated by any run of spaces or tabs
+        let rest = rest.trim().trim_end_matches(';').trim();
+        let Some((key, value)) = rest.split_once(char::is_whitespace) else {
+            eprintln!("{}:{}: missing color value", path, index + 1);
+            continue;
+        };
+
+        match parse_color(value.trim(), &tokens) {
+            Ok(color) => {
+                tokens.insert(key.to_string(), color);
+            }
+            Err(e) => eprintln!("{}:{}: {} for {}", path, index + 1, e, key),
+        }
+    }
+
+    tokens
+}
+
+/// Parses Matugen JSON. Older Matugen versions group by scheme
+/// (`colors.dark.primary`), newer ones group by token (`colors.primary.dark`).
+pub fn parse_matugen_json(path: &str, contents: &str, scheme: &str) -> Result<Tokens, ThemeError> {
+    let json = parse_json(path, contents)?;
+    let colors = json.get("colors").and_then(Value::as_object);
+    let Some(colors) = colors else {
+        return Err(ThemeError {
+            path: path.to_string(),
+            line: 1,
+            message: "missing \"colors\" object".to_string(),
+        });
+    };
+
+    let entries: Vec<(&String, &Value)> = match colors.get(scheme).and_then(Value::as_object) {
+        Some(by_scheme) => by_scheme.iter().collect(),
+        None => colors
+            .iter()
+            .filter_map(|(key, value)| value.get(scheme).map(|value| (key, value)))
+            .collect(),
+    };
+
+    if entries.is_empty() {
+        return Err(ThemeError {
+            path: path.to_string(),
+            line: 1,
+            message: format!("no colors for the \"{}\" scheme", scheme),
+        });
+    }
+
+    let mut tokens = Tokens::new();
+    for (key, value) in entries {
+        match value.as_str().map(|value| parse_color(value, &tokens)) {
+            Some(Ok(color)) => {
+                tokens.insert(key.clone(), color);
+            }
+            Some(Err(e)) => eprintln!("{}: {} for {}", path, e, key),
+            None => eprintln!("{}: expected a color string for {}", path, key),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses pywal's colors.json. pywal only has sixteen terminal colors plus
+/// background/foreground, so the Material roles are derived from those. The
+/// raw `color0`..`color15` tokens are kept as well so the role mapping in the
+/// config can point at them directly.
+pub fn parse_pywal(path: &str, contents: &str) -> Result<Tokens, ThemeError> {
+    let json = parse_json(path, contents)?;
+    let mut raw = Tokens::new();
+
+    for section in ["special", "colors"] {
+        let Some(entries) = json.get(section).and_then(Value::as_object) else {
+            return Err(ThemeError {
+                path: path.to_string(),
+                line: 1,
+                message: format!("missing \"{}\" object", section),
+            });
+        };
+        for (key, value) in entries {
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+            match parse_color(value, &raw) {
+                Ok(color) => {
+                    raw.insert(key.clone(), color);
+                }
+                Err(e) => eprintln!("{}: {} for {}", path, e, key),
+            }
+        }
+    }
+
+    let derived = [
+        ("background", "background"),
+        ("surface", "background"),
+        ("surface_dim", "background"),
+        ("surface_container_lowest", "background"),
+        ("surface_container_low", "color0"),
+        ("surface_container", "color0"),
+        ("surface_container_high", "color8"),
+        ("surface_container_highest", "color8"),
+        ("surface_bright", "color8"),
+        ("on_background", "foreground"),
+        ("on_surface", "foreground"),
+        ("on_surface_variant", "color7"),
+        ("inverse_surface", "foreground"),
+        ("inverse_on_surface", "background"),
+        ("outline", "color8"),
+        ("outline_variant", "color0"),
+        ("primary", "color4"),
+        ("primary_fixed_dim", "color4"),
+        ("on_primary", "background"),
+        ("on_primary_fixed", "background"),
+        ("secondary", "color5"),
+        ("secondary_fixed_dim", "color5"),
+        ("on_secondary", "background"),
+        ("on_secondary_fixed", "background"),
+        ("tertiary", "color6"),
+        ("on_tertiary", "background"),
+        ("error", "color1"),
+        ("on_error", "background"),
+    ];
+
+    let mut tokens = raw.clone();
+    for (token, source) in derived {
+        if let Some(color) = raw.get(source) {
+            tokens.insert(token.to_string(), *color);
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_json(path: &str, contents: &str) -> Result<Value, ThemeError> {
+    serde_json::from_str(contents).map_err(|e| ThemeError {
+        path: path.to_string(),
+        line: e.line(),
+        message: e.to_string(),
+    })
+}
+
+/// Parses a single GTK color value: `#rgb`, `#rrggbb`, `#rrggbbaa`,
+/// `rgb()`, `rgba()`, `alpha(color, factor)` or an `@name` reference to an
+/// earlier token.
+pub fn parse_color(value: &str, tokens: &Tokens) -> Result<Color32, String> {
+    let value = value.trim();
+
+    if let Some(name) = value.strip_prefix('@') {
+        return tokens
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("reference to undefined color @{}", name));
+    }
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(|| format!("invalid hex color #{}", hex));
+    }
+
+    let Some((function, args)) = value
+        .strip_suffix(')')
+        .and_then(|value| value.split_once('('))
+    else {
+        return Err(format!("unsupported color value {}", value));
+    };
+    let args = split_args(args);
+
+    match (function.trim(), args.as_slice()) {
+        ("rgb", [r, g, b]) => Ok(Color32::from_rgb(
+            parse_channel(r)?,
+            parse_channel(g)?,
+            parse_channel(b)?,
+        )),
+        ("rgba", [r, g, b, a]) => Ok(Color32::from_rgba_unmultiplied(
+            parse_channel(r)?,
+            parse_channel(g)?,
+            parse_channel(b)?,
+            parse_alpha(a)?,
+        )),
+        ("alpha", [color, factor]) => {
+            let [r, g, b, a] = parse_color(color, tokens)?.to_srgba_unmultiplied();
+            let factor = parse_number(factor)?.clamp(0.0, 1.0);
+            Ok(Color32::from_rgba_unmultiplied(
+                r,
+                g,
+                b,
+                (a as f32 * factor).round() as u8,
+            ))
+        }
+        _ => Err(format!("unsupported color value {}", value)),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color32> {
+    let expanded: String = match hex.len() {
+        // #rgb shorthand doubles every digit
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    Color32::from_hex(&format!("#{}", expanded)).ok()
+}
+
+/// Splits function arguments on top-level commas, so `alpha(rgb(1, 2, 3), 0.5)`
+/// yields two arguments rather than four.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+fn parse_number(value: &str) -> Result<f32, String> {
+    value
+        .trim()
+        .parse::<f32>()
+        .map_err(|_| format!("invalid number {}", value.trim()))
+}
+
+/// A color channel, either 0-255 or a percentage
+fn parse_channel(value: &str) -> Result<u8, String> {
+    let value = value.trim();
+    let channel = match value.strip_suffix('%') {
+        Some(percent) => parse_number(percent)? / 100.0 * 255.0,
+        None => parse_number(value)?,
+    };
+    Ok(channel.round().clamp(0.0, 255.0) as u8)
+}
+
+/// An alpha value, either 0.0-1.0 or a percentage
+fn parse_alpha(value: &str) -> Result<u8, String> {
+    let value = value.trim();
+    let alpha = match value.strip_suffix('%') {
+        Some(percent) => parse_number(percent)? / 100.0,
+        None => parse_number(value)?,
+    };
+    Ok((alpha.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        format!(
+            "{}/tests/fixtures/theme/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        )
+    }
+
+    #[test]
+    fn css_fixture() {
+        let tokens = load(&fixture("colors.css"), None, "dark").unwrap();
+        assert_eq!(tokens["background"], Color32::from_rgb(0x10, 0x14, 0x18));
+        // Tabs and runs of spaces between name and value
+        assert_eq!(tokens["on_surface"], Color32::from_rgb(0xdf, 0xe3, 0xe7));
+        assert_eq!(tokens["primary"], Color32::from_rgb(0x8d, 0xcf, 0xf2));
+        assert_eq!(
+            tokens["surface"],
+            Color32::from_rgba_unmultiplied(16, 20, 24, 204)
+        );
+        assert_eq!(tokens["outline"], Color32::from_rgb(255, 0, 128));
+        assert_eq!(
+            tokens["scrim"],
+            Color32::from_rgba_unmultiplied(0x10, 0x14, 0x18, 128)
+        );
+        assert_eq!(tokens["shadow"], Color32::from_rgb(0xaa, 0xbb, 0xcc));
+        // Bad lines are skipped, not fatal
+        assert!(!tokens.contains_key("broken"));
+        assert!(!tokens.contains_key("missing"));
+    }
+
+    #[test]
+    fn matugen_fixture_by_scheme() {
+        let path = fixture("matugen.json");
+        let dark = load(&path, None, "dark").unwrap();
+        assert_eq!(dark.len(), 3);
+        assert_eq!(dark["primary"], Color32::from_rgb(0x8d, 0xcf, 0xf2));
+        let light = load(&path, Some(ThemeFormat::MatugenJson), "light").unwrap();
+        assert_eq!(light["primary"], Color32::from_rgb(0x18, 0x65, 0x84));
+
+        let error = load(&path, None, "sepia").unwrap_err();
+        assert!(error.message.contains("sepia"), "{}", error);
+    }
+
+    #[test]
+    fn matugen_fixture_by_token() {
+        let tokens = load(&fixture("matugen_by_token.json"), None, "light").unwrap();
+        assert_eq!(tokens["background"], Color32::from_rgb(0xf6, 0xfa, 0xfe));
+        assert_eq!(tokens["error"], Color32::from_rgb(0xba, 0x1a, 0x1a));
+        // A non-string value is skipped
+        let dark = load(&fixture("matugen_by_token.json"), None, "dark").unwrap();
+        assert!(!dark.contains_key("error"));
+        assert_eq!(dark["primary"], Color32::from_rgb(0x8d, 0xcf, 0xf2));
+    }
+
+    #[test]
+    fn pywal_fixture() {
+        let tokens = load(&fixture("pywal.json"), None, "dark").unwrap();
+        assert_eq!(tokens["color4"], Color32::from_rgb(0x5f, 0x81, 0x9d));
+        assert_eq!(tokens["primary"], tokens["color4"]);
+        assert_eq!(tokens["surface"], Color32::from_rgb(0x1d, 0x1f, 0x21));
+        assert_eq!(tokens["on_surface"], Color32::from_rgb(0xc5, 0xc8, 0xc6));
+        assert_eq!(tokens["error"], tokens["color1"]);
+    }
+
+    #[test]
+    fn unknown_format_points_at_first_line() {
+        let path = fixture("unknown.conf");
+        let error = load(&path, None, "dark").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert!(error.to_string().starts_with(&format!("{}:3:", path)));
+    }
+
+    #[test]
+    fn broken_json_reports_line() {
+        let error = parse_matugen_json("theme.json", "{\n  \"colors\": {\n    oops\n}", "dark")
+            .unwrap_err();
+        assert_eq!(error.line, 3);
+    }
+}
@@ -0,0 +1,413 @@
+//! Turning wall-clock times from calendar files into Unix timestamps, for
+//! zones described by a VTIMEZONE block or found in the system zoneinfo.
+
+use std::fs;
+use std::path::Path;
+
+use crate::local_time::{self, civil_from_days, days_from_civil, days_in_month, weekday_from_days};
+use crate::rrule::RecurrenceRule;
+
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+#[derive(Clone, Debug)]
+pub enum Zone {
+    Utc,
+    /// The system time zone, for floating times and unknown TZIDs
+    Local,
+    /// STANDARD/DAYLIGHT blocks from a VTIMEZONE
+    Observances(Vec<Observance>),
+    Tzif(Tzif),
+}
+
+impl Zone {
+    /// Looks a TZID up in the system zoneinfo, e.g. "Europe/Berlin"
+    pub fn system(tzid: &str) -> Option<Zone> {
+        // Some producers prefix the Olson name, e.g. "/mozilla.org/.../Europe/Berlin"
+        let name = tzid.trim().trim_start_matches('/');
+        if name.is_empty() || name.split('/').any(|part| part == "..") {
+            return None;
+        }
+        if matches!(name, "UTC" | "GMT" | "Etc/UTC" | "Z") {
+            return Some(Zone::Utc);
+        }
+        let data = fs::read(Path::new(ZONEINFO).join(name)).ok()?;
+        Tzif::parse(&data).map(Zone::Tzif)
+    }
+
+    /// Seconds east of UTC at wall-clock time `wall` in this zone
+    fn offset_at_wall(&self, wall: i64) -> i64 {
+        match self {
+            Zone::Utc => 0,
+            Zone::Local => wall - local_time::local_to_unix(wall),
+            Zone::Observances(observances) => {
+                let mut latest: Option<(i64, i64)> = None;
+                for observance in observances {
+                    if let Some(onset) = observance.last_onset(wall)
+                        && latest.is_none_or(|(at, _)| onset > at)
+                    {
+                        latest = Some((onset, observance.offset_to));
+                    }
+                }
+                match latest {
+                    Some((_, offset)) => offset,
+                    // Before the first onset
+                    None => observances
+                        .iter()
+                        .min_by_key(|observance| observance.start)
+                        .map_or(0, |observance| observance.offset_from),
+                }
+            }
+            Zone::Tzif(tzif) => {
+                // The offset is looked up by UTC time, which we only know
+                // once we have the offset; twice is enough to settle it
+                let guess = wall - tzif.offset_at(wall);
+                tzif.offset_at(wall - tzif.offset_at(guess))
+            }
+        }
+    }
+
+    pub fn to_utc(&self, wall: i64) -> i64 {
+        wall - self.offset_at_wall(wall)
+    }
+
+    /// The wall-clock time at a UTC timestamp, close enough for comparing
+    /// against UNTIL
+    pub fn to_wall(&self, utc: i64) -> i64 {
+        let guess = utc + self.offset_at_wall(utc);
+        utc + self.offset_at_wall(guess)
+    }
+}
+
+/// One STANDARD or DAYLIGHT block
+#[derive(Clone, Debug)]
+pub struct Observance {
+    /// First onset, in the wall time before the change
+    pub start: i64,
+    pub offset_from: i64,
+    pub offset_to: i64,
+    pub rule: Option<RecurrenceRule>,
+    /// Extra onsets from RDATE
+    pub dates: Vec<i64>,
+}
+
+impl Observance {
+    /// The last time this observance took effect at or before `wall`
+    fn last_onset(&self, wall: i64) -> Option<i64> {
+        let from_rule = self.rule.as_ref().and_then(|rule| {
+            // UNTIL in a VTIMEZONE is UTC
+            let until = rule.until.map(|until| {
+                if rule.until_utc {
+                    until + self.offset_from
+                } else {
+                    until
+                }
+            });
+            rule.occurrences(self.start, until, wall).pop()
+        });
+        std::iter::once(self.start)
+            .chain(from_rule)
+            .chain(self.dates.iter().copied())
+            .filter(|onset| *onset <= wall)
+            .max()
+    }
+}
+
+/// A compiled zoneinfo file: the transition table plus the POSIX rule for
+/// times past its end
+#[derive(Clone, Debug)]
+pub struct Tzif {
+    transitions: Vec<i64>,
+    /// Offset in effect from the transition with the same index
+    offsets: Vec<i64>,
+    initial: i64,
+    rule: Option<PosixRule>,
+}
+
+impl Tzif {
+    fn parse(data: &[u8]) -> Option<Tzif> {
+        if data.get(0..4)? != b"TZif" {
+            return None;
+        }
+        let version = *data.get(4)?;
+        let counts = read_counts(data, 0)?;
+        if version < b'2' {
+            return Self::parse_block(data, 0, 4, counts).map(|(tzif, _)| tzif);
+        }
+        // Skip the 32-bit block to the 64-bit one, which has the footer
+        let second = 44 + block_size(counts, 4);
+        let counts = read_counts(data, second)?;
+        let (mut tzif, end) = Self::parse_block(data, second, 8, counts)?;
+        let footer = data.get(end..)?;
+        if let Some(footer) = std::str::from_utf8(footer)
+            .ok()
+            .and_then(|text| text.trim_matches('\n').lines().next())
+        {
+            tzif.rule = PosixRule::parse(footer);
+        }
+        Some(tzif)
+    }
+
+    fn parse_block(
+        data: &[u8],
+        header: usize,
+        time_size: usize,
+        counts: [usize; 6],
+    ) -> Option<(Tzif, usize)> {
+        let [is_ut, is_std, leap, time, types, chars] = counts;
+        let mut at = header + 44;
+        let mut transitions = Vec::with_capacity(time);
+        for _ in 0..time {
+            let bytes = data.get(at..at + time_size)?;
+            transitions.push(if time_size == 8 {
+                i64::from_be_bytes(bytes.try_into().ok()?)
+            } else {
+                i32::from_be_bytes(bytes.try_into().ok()?) as i64
+            });
+            at += time_size;
+        }
+        let indices = data.get(at..at + time)?.to_vec();
+        at += time;
+        let mut type_offsets = Vec::with_capacity(types);
+        for _ in 0..types {
+            let bytes = data.get(at..at + 4)?;
+            type_offsets.push(i32::from_be_bytes(bytes.try_into().ok()?) as i64);
+            at += 6;
+        }
+        at += chars + leap * (time_size + 4) + is_std + is_ut;
+        let offsets = indices
+            .iter()
+            .map(|index| type_offsets.get(*index as usize).copied())
+            .collect::<Option<Vec<_>>>()?;
+        let tzif = Tzif {
+            transitions,
+            offsets,
+            initial: type_offsets.first().copied().unwrap_or(0),
+            rule: None,
+        };
+        Some((tzif, at))
+    }
+
+    fn offset_at(&self, utc: i64) -> i64 {
+        if let Some(rule) = &self.rule
+            && self.transitions.last().is_none_or(|last| utc >= *last)
+        {
+            return rule.offset_at(utc);
+        }
+        match self.transitions.partition_point(|at| *at <= utc) {
+            0 => self.initial,
+            index => self.offsets[index - 1],
+        }
+    }
+}
+
+/// The six counts in a TZif header starting at `at`
+fn read_counts(data: &[u8], at: usize) -> Option<[usize; 6]> {
+    let mut counts = [0; 6];
+    for (index, count) in counts.iter_mut().enumerate() {
+        let start = at + 20 + index * 4;
+        *count = u32::from_be_bytes(data.get(start..start + 4)?.try_into().ok()?) as usize;
+    }
+    Some(counts)
+}
+
+fn block_size(counts: [usize; 6], time_size: usize) -> usize {
+    let [is_ut, is_std, leap, time, types, chars] = counts;
+    time * time_size + time + types * 6 + chars + leap * (time_size + 4) + is_std + is_ut
+}
+
+/// A POSIX TZ string like "CET-1CEST,M3.5.0,M10.5.0/3"
+#[derive(Clone, Debug)]
+struct PosixRule {
+    std_offset: i64,
+    dst: Option<DaylightRule>,
+}
+
+#[derive(Clone, Debug)]
+struct DaylightRule {
+    offset: i64,
+    /// (month, week, weekday, seconds after local midnight)
+    start: (u32, u32, u32, i64),
+    end: (u32, u32, u32, i64),
+}
+
+impl PosixRule {
+    fn parse(text: &str) -> Option<PosixRule> {
+        let mut rest = skip_zone_name(text)?;
+        let (std_offset, after) = parse_offset(rest)?;
+        // POSIX offsets are west of UTC
+        let std_offset = -std_offset;
+        rest = after;
+        if rest.is_empty() {
+            return Some(PosixRule {
+                std_offset,
+                dst: None,
+            });
+        }
+        rest = skip_zone_name(rest)?;
+        let offset = match parse_offset(rest) {
+            Some((offset, after)) => {
+                rest = after;
+                -offset
+            }
+            _ => std_offset + 3600,
+        };
+        let (start, end) = match rest.strip_prefix(',') {
+            Some(rules) => {
+                let (start, end) = rules.split_once(',')?;
+                (parse_rule_date(start)?, parse_rule_date(end)?)
+            }
+            // The US rules are the documented default
+            None => ((3, 2, 0, 7200), (11, 1, 0, 7200)),
+        };
+        Some(PosixRule {
+            std_offset,
+            dst: Some(DaylightRule { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, utc: i64) -> i64 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+        let (year, _, _) = civil_from_days((utc + self.std_offset).div_euclid(86400));
+        // The start is given in standard time, the end in daylight time
+        let start = rule_day(year, dst.start) * 86400 + dst.start.3 - self.std_offset;
+        let end = rule_day(year, dst.end) * 86400 + dst.end.3 - dst.offset;
+        let in_dst = if start < end {
+            start <= utc && utc < end
+        } else {
+            // Southern hemisphere, daylight time spans the new year
+            !(end <= utc && utc < start)
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+}
+
+/// Day of "Mm.w.d": weekday d of week w (5 = last) in month m
+fn rule_day(year: i32, (month, week, weekday, _): (u32, u32, u32, i64)) -> i64 {
+    let first = days_from_civil(year, month, 1);
+    let last = first + days_in_month(year, month) as i64 - 1;
+    let mut day = first + ((weekday + 7 - weekday_from_days(first)) % 7) as i64;
+    day += (week as i64 - 1) * 7;
+    while day > last {
+        day -= 7;
+    }
+    day
+}
+
+fn skip_zone_name(text: &str) -> Option<&str> {
+    if let Some(quoted) = text.strip_prefix('<') {
+        return quoted.split_once('>').map(|(_, rest)| rest);
+    }
+    let length = text
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    (length >= 3).then(|| &text[length..])
+}
+
+/// "[+-]hh[:mm[:ss]]" in seconds, and what follows it
+fn parse_offset(text: &str) -> Option<(i64, &str)> {
+    let (sign, text) = match text.as_bytes().first()? {
+        b'-' => (-1, &text[1..]),
+        b'+' => (1, &text[1..]),
+        _ => (1, text),
+    };
+    let length = text
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(text.len());
+    if length == 0 {
+        return None;
+    }
+    let mut seconds = 0;
+    for (index, part) in text[..length].split(':').enumerate().take(3) {
+        let value: i64 = part.parse().ok()?;
+        seconds += value * [3600, 60, 1][index];
+    }
+    Some((sign * seconds, &text[length..]))
+}
+
+/// "Mm.w.d[/time]"; the Julian-day forms are rare enough to leave out
+fn parse_rule_date(text: &str) -> Option<(u32, u32, u32, i64)> {
+    let (date, time) = match text.split_once('/') {
+        Some((date, time)) => (date, parse_offset(time)?.0),
+        None => (text, 7200),
+    };
+    let mut parts = date.strip_prefix('M')?.split('.');
+    let month = parts.next()?.parse().ok()?;
+    let week = parts.next()?.parse().ok()?;
+    let weekday = parts.next()?.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=5).contains(&week) && weekday < 7)
+        .then_some((month, week, weekday, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: i64) -> i64 {
+        days_from_civil(year, month, day) * 86400 + hour * 3600
+    }
+
+    /// Europe/Berlin as most calendar apps write its VTIMEZONE
+    fn berlin() -> Zone {
+        Zone::Observances(vec![
+            Observance {
+                start: at(1970, 3, 29, 2),
+                offset_from: 3600,
+                offset_to: 7200,
+                rule: RecurrenceRule::parse("FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU").ok(),
+                dates: Vec::new(),
+            },
+            Observance {
+                start: at(1970, 10, 25, 3),
+                offset_from: 7200,
+                offset_to: 3600,
+                rule: RecurrenceRule::parse("FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU").ok(),
+                dates: Vec::new(),
+            },
+        ])
+    }
+
+    #[test]
+    fn observances_follow_dst() {
+        let zone = berlin();
+        // 2024 switches on March 31st and October 27th
+        assert_eq!(zone.to_utc(at(2024, 3, 31, 1)), at(2024, 3, 31, 0));
+        assert_eq!(zone.to_utc(at(2024, 3, 31, 3)), at(2024, 3, 31, 1));
+        assert_eq!(zone.to_utc(at(2024, 7, 1, 12)), at(2024, 7, 1, 10));
+        assert_eq!(zone.to_utc(at(2024, 10, 27, 4)), at(2024, 10, 27, 3));
+        assert_eq!(zone.to_wall(at(2024, 7, 1, 10)), at(2024, 7, 1, 12));
+        assert_eq!(zone.to_wall(at(2024, 12, 1, 10)), at(2024, 12, 1, 11));
+    }
+
+    #[test]
+    fn before_the_first_onset_uses_offset_from() {
+        assert_eq!(berlin().to_utc(at(1960, 1, 1, 12)), at(1960, 1, 1, 11));
+    }
+
+    #[test]
+    fn posix_rule_for_times_past_the_table() {
+        let rule = PosixRule::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(rule.offset_at(at(2031, 1, 15, 12)), 3600);
+        assert_eq!(rule.offset_at(at(2031, 7, 15, 12)), 7200);
+        // Changes at 01:00 UTC on the last Sunday of March, the 30th
+        assert_eq!(rule.offset_at(at(2031, 3, 30, 0)), 3600);
+        assert_eq!(rule.offset_at(at(2031, 3, 30, 1)), 7200);
+
+        // Daylight time over the new year
+        let rule = PosixRule::parse("<+13>-13<+14>,M9.5.0/3,M4.1.0/4").unwrap();
+        assert_eq!(rule.offset_at(at(2031, 1, 1, 0)), 14 * 3600);
+        assert_eq!(rule.offset_at(at(2031, 6, 1, 0)), 13 * 3600);
+
+        let rule = PosixRule::parse("JST-9").unwrap();
+        assert_eq!(rule.offset_at(at(2031, 7, 1, 0)), 9 * 3600);
+    }
+
+    #[test]
+    fn system_zone_names() {
+        assert!(matches!(Zone::system("UTC"), Some(Zone::Utc)));
+        assert!(matches!(Zone::system("/Etc/UTC"), Some(Zone::Utc)));
+        assert!(Zone::system("../../etc/passwd").is_none());
+        assert!(Zone::system("").is_none());
+    }
+}
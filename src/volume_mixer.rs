@@ -0,0 +1,357 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioBackend, PlaybackStream};
+use crate::icons::IconCache;
+use crate::palette::Palette;
+use crate::slider_controls::{IconSlider, SliderEvent, SliderKind};
+use crate::task::{BackgroundTask, WriteQueue};
+
+/// How often the stream list is refreshed while the mixer is open
+const OPEN_INTERVAL: Duration = Duration::from_secs(1);
+/// Collapsed, we only need the stream count for the header
+const CLOSED_INTERVAL: Duration = Duration::from_secs(5);
+
+struct StreamRow {
+    stream: PlaybackStream,
+    slider: IconSlider,
+    /// Set while the user drags, so refreshes don't yank the slider back
+    dragging: bool,
+}
+
+/// A slider change waiting to be sent to pactl
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum StreamChange {
+    Volume(u32, f32),
+    Mute(u32, bool),
+}
+
+impl StreamChange {
+    /// Which setting of which stream it changes; a newer change with the
+    /// same key replaces one still waiting
+    fn key(self) -> (u32, bool) {
+        match self {
+            StreamChange::Volume(index, _) => (index, false),
+            StreamChange::Mute(index, _) => (index, true),
+        }
+    }
+
+    fn apply(self, backend: &AudioBackend) -> Result<(), String> {
+        match self {
+            StreamChange::Volume(index, percent) => backend.set_stream_volume(index, percent),
+            StreamChange::Mute(index, muted) => backend.set_stream_mute(index, muted),
+        }
+    }
+}
+
+/// Expandable per-application volume list shown under the Sound slider
+pub struct VolumeMixer {
+    backend: AudioBackend,
+    icons: IconCache,
+    expanded: bool,
+    rows: Vec<StreamRow>,
+    last_update: Option<Instant>,
+    refresh_task: Option<BackgroundTask<Result<Vec<PlaybackStream>, String>>>,
+    writes: WriteQueue<(u32, bool)>,
+    error: Option<String>,
+}
+
+impl VolumeMixer {
+    pub fn new(backend: AudioBackend) -> Self {
+        Self {
+            backend,
+            icons: IconCache::default(),
+            expanded: false,
+            rows: Vec::new(),
+            last_update: None,
+            refresh_task: None,
+            writes: WriteQueue::default(),
+            error: None,
+        }
+    }
+
+    /// Starts re-reading the playback streams, unless that's already running
+    pub fn refresh(&mut self, ctx: &egui::Context) {
+        if self.refresh_task.is_some() {
+            return;
+        }
+        let backend = self.backend.clone();
+        self.refresh_task = Some(BackgroundTask::spawn(ctx, move || {
+            backend.playback_streams()
+        }));
+    }
+
+    /// Takes in finished refreshes and sends the next batch of changes
+    fn poll_tasks(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.refresh_task.as_ref().and_then(BackgroundTask::poll) {
+            self.refresh_task = None;
+            match result {
+                Ok(streams) => {
+                    self.error = None;
+                    self.update_rows(streams);
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        if let Some(Err(e)) = self.writes.poll(ctx) {
+            eprintln!("Failed to update application volume: {}", e);
+        }
+    }
+
+    fn queue(&mut self, change: StreamChange) {
+        // A listing started before the change would undo it on the slider
+        self.refresh_task = None;
+        let backend = self.backend.clone();
+        self.writes
+            .push(change.key(), move || change.apply(&backend));
+    }
+
+    /// Takes in a new stream list, keeping the sliders of streams that are
+    /// still around so an in-progress drag isn't interrupted
+    fn update_rows(&mut self, streams: Vec<PlaybackStream>) {
+        let mut old_rows = std::mem::take(&mut self.rows);
+        for stream in streams {
+            let row = match old_rows
+                .iter()
+                .position(|row| row.stream.index == stream.index)
+            {
+                Some(position) => {
+                    let mut row = old_rows.swap_remove(position);
+                    if !row.dragging {
+                        row.slider.value = stream.volume.min(row.slider.max);
+                        row.slider.muted = stream.muted;
+                    }
+                    row.stream = stream;
+                    row
+                }
+                None => {
+                    let mut slider =
+                        IconSlider::new(stream.volume.min(100.0), SPEAKER_HIGH.to_string())
+                            .with_kind(SliderKind::Volume)
+                            .with_step(2.0)
+                            .with_value_label();
+                    slider.muted = stream.muted;
+                    StreamRow {
+                        stream,
+                        slider,
+                        dragging: false,
+                    }
+                }
+            };
+            self.rows.push(row);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        self.poll_tasks(ui.ctx());
+        let interval = if self.expanded {
+            OPEN_INTERVAL
+        } else {
+            CLOSED_INTERVAL
+        };
+        if !self.writes.is_busy()
+            && self
+                .last_update
+                .is_none_or(|last| last.elapsed() >= interval)
+        {
+            self.refresh(ui.ctx());
+            self.last_update = Some(Instant::now());
+        }
+        // Keep polling even when nothing else triggers a repaint, so streams
+        // show up and go away on their own
+        ui.ctx().request_repaint_after(interval);
+
+        ui.horizontal(|ui| {
+            ui.add_space(8.0);
+            let caret = if self.expanded {
+                CARET_DOWN
+            } else {
+                CARET_RIGHT
+            };
+            let label = format!("{}  Applications ({})", caret, self.rows.len());
+            if ui
+                .add(
+                    Button::new(RichText::new(label).size(12.0).color(colors.on_surface))
+                        .fill(Color32::TRANSPARENT),
+                )
+                .clicked()
+            {
+                self.expanded = !self.expanded;
+                self.last_update = None;
+            }
+        });
+
+        if !self.expanded {
+            return;
+        }
+
+        if let Some(error) = &self.error {
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.label(RichText::new(error).size(12.0).color(colors.error));
+            });
+        } else if self.rows.is_empty() {
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.label(
+                    RichText::new("Nothing is playing")
+                        .size(12.0)
+                        .color(colors.on_surface.gamma_multiply(0.7)),
+                );
+            });
+        }
+
+        let mut changes = Vec::new();
+        for row in &mut self.rows {
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                self.icons.show(
+                    ui,
+                    row.stream.icon_name.as_deref(),
+                    "apps",
+                    APP_WINDOW,
+                    24.0,
+                    colors.on_surface,
+                );
+                ui.vertical(|ui| {
+                    ui.label(RichText::new(&row.stream.app_name).color(colors.on_surface));
+                    if !row.stream.media_title.is_empty() {
+                        ui.label(
+                            RichText::new(&row.stream.media_title)
+                                .size(12.0)
+                                .color(colors.on_surface.gamma_multiply(0.7)),
+                        );
+                    }
+                });
+            });
+
+            let index = row.stream.index;
+            match row.slider.show(ui, colors) {
+                Some(SliderEvent::Started(value)) | Some(SliderEvent::Changed(value)) => {
+                    row.dragging = true;
+                    changes.push(StreamChange::Volume(index, value));
+                }
+                Some(SliderEvent::Committed(value)) => {
+                    row.dragging = false;
+                    changes.push(StreamChange::Volume(index, value));
+                }
+                Some(SliderEvent::Muted(muted)) => changes.push(StreamChange::Mute(index, muted)),
+                None => {}
+            }
+        }
+        for change in changes {
+            self.queue(change);
+        }
+        if self.writes.is_busy() {
+            self.poll_tasks(ui.ctx());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+    use std::sync::Arc;
+
+    const SINK_INPUTS: &str = r#"[
+        {"index": 7, "mute": false,
+         "volume": {"front-left": {"value_percent": "40%"}, "front-right": {"value_percent": "40%"}},
+         "properties": {"application.name": "Firefox", "media.name": "Video"}},
+        {"index": 9, "mute": true,
+         "volume": {"mono": {"value_percent": "120%"}},
+         "properties": {"application.process.binary": "mpv"}}
+    ]"#;
+
+    fn mixer(runner: FakeRunner) -> (VolumeMixer, Arc<FakeRunner>) {
+        let runner = Arc::new(runner);
+        (VolumeMixer::new(AudioBackend::new(runner.clone())), runner)
+    }
+
+    /// Polls until both background tasks are done
+    fn settle(mixer: &mut VolumeMixer, ctx: &egui::Context) {
+        for _ in 0..200 {
+            mixer.poll_tasks(ctx);
+            if mixer.refresh_task.is_none() && !mixer.writes.is_busy() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("background task didn't finish");
+    }
+
+    #[test]
+    fn refresh_runs_in_the_background() {
+        let ctx = egui::Context::default();
+        let (mut mixer, _) =
+            mixer(FakeRunner::default().with("pactl -f json list sink-inputs", SINK_INPUTS));
+        mixer.refresh(&ctx);
+        assert!(mixer.rows.is_empty());
+        settle(&mut mixer, &ctx);
+
+        assert_eq!(mixer.error, None);
+        assert_eq!(mixer.rows.len(), 2);
+        assert_eq!(mixer.rows[0].stream.app_name, "Firefox");
+        assert_eq!(mixer.rows[0].slider.value, 40.0);
+        // Over-amplified streams are capped to the slider's range
+        assert_eq!(mixer.rows[1].slider.value, 100.0);
+        assert!(mixer.rows[1].slider.muted);
+    }
+
+    #[test]
+    fn refresh_error_is_shown() {
+        let ctx = egui::Context::default();
+        let (mut mixer, _) = mixer(
+            FakeRunner::default().failing("pactl -f json list sink-inputs", "Connection refused"),
+        );
+        mixer.refresh(&ctx);
+        settle(&mut mixer, &ctx);
+        assert_eq!(mixer.error.as_deref(), Some("Connection refused"));
+    }
+
+    #[test]
+    fn refresh_keeps_dragged_slider() {
+        let ctx = egui::Context::default();
+        let (mut mixer, _) =
+            mixer(FakeRunner::default().with("pactl -f json list sink-inputs", SINK_INPUTS));
+        mixer.refresh(&ctx);
+        settle(&mut mixer, &ctx);
+        mixer.rows[0].dragging = true;
+        mixer.rows[0].slider.value = 80.0;
+
+        mixer.refresh(&ctx);
+        settle(&mut mixer, &ctx);
+        assert_eq!(mixer.rows[0].slider.value, 80.0);
+    }
+
+    #[test]
+    fn only_newest_change_per_stream_is_sent() {
+        let ctx = egui::Context::default();
+        let (mut mixer, runner) = mixer(
+            FakeRunner::default()
+                .with("pactl set-sink-input-volume 7 60%", "")
+                .with("pactl set-sink-input-volume 9 30%", "")
+                .with("pactl set-sink-input-mute 7 1", ""),
+        );
+        for percent in [20.0, 40.0, 60.0] {
+            mixer.queue(StreamChange::Volume(7, percent));
+        }
+        mixer.queue(StreamChange::Mute(7, true));
+        mixer.queue(StreamChange::Volume(9, 30.0));
+        assert!(runner.calls().is_empty());
+
+        settle(&mut mixer, &ctx);
+        assert_eq!(
+            runner.calls(),
+            [
+                "pactl set-sink-input-volume 7 60%",
+                "pactl set-sink-input-mute 7 1",
+                "pactl set-sink-input-volume 9 30%",
+            ]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use crate::command_runner::CommandRunner;
+use crate::nmcli::split_terse;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VpnKind {
+    /// A NetworkManager VPN plugin connection (OpenVPN, IPsec, ...)
+    Vpn,
+    WireGuard,
+}
+
+impl VpnKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            VpnKind::Vpn => "VPN",
+            VpnKind::WireGuard => "WireGuard",
+        }
+    }
+}
+
+/// A saved VPN or WireGuard connection
+#[derive(Clone, Debug, PartialEq)]
+pub struct VpnConnection {
+    pub name: String,
+    pub uuid: String,
+    pub kind: VpnKind,
+    pub active: bool,
+}
+
+/// VPN activation through `nmcli`. Cheap to clone, so the slow calls can run
+/// on a background thread.
+#[derive(Clone)]
+pub struct VpnBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl VpnBackend {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// Saved VPN and WireGuard connections, active ones first
+    pub fn connections(&self) -> Result<Vec<VpnConnection>, String> {
+        let output = self.runner.run(
+            "nmcli",
+            &["-t", "-f", "NAME,UUID,TYPE,ACTIVE", "connection", "show"],
+        )?;
+        Ok(parse_connections(&output))
+    }
+
+    /// Brings `connection` up or down. Going up fails with nmcli's message
+    /// when secrets are missing, since there's no agent to ask for them.
+    pub fn set_active(&self, connection: &VpnConnection, active: bool) -> Result<(), String> {
+        let action = if active { "up" } else { "down" };
+        self.runner
+            .run("nmcli", &["connection", action, "uuid", &connection.uuid])
+            .map(|_| ())
+    }
+
+    /// Brings every active connection down. One failing doesn't stop the
+    /// rest; the first error is returned once all were tried.
+    pub fn disconnect_all(&self) -> Result<(), String> {
+        let mut result = Ok(());
+        for connection in self.connections()?.iter().filter(|c| c.active) {
+            if let Err(e) = self.set_active(connection, false) {
+                result = result.and(Err(format!("{}: {}", connection.name, e)));
+            }
+        }
+        result
+    }
+}
+
+/// Parses `nmcli -t -f NAME,UUID,TYPE,ACTIVE connection show`, keeping only
+/// VPN and WireGuard connections
+pub fn parse_connections(output: &str) -> Vec<VpnConnection> {
+    let mut connections: Vec<VpnConnection> = output
+        .lines()
+        .filter_map(|line| {
+            let fields = split_terse(line);
+            let [name, uuid, kind, active] = fields.as_slice() else {
+                return None;
+            };
+            let kind = match kind.as_str() {
+                "vpn" => VpnKind::Vpn,
+                "wireguard" => VpnKind::WireGuard,
+                _ => return None,
+            };
+            Some(VpnConnection {
+                name: name.clone(),
+                uuid: uuid.clone(),
+                kind,
+                active: active == "yes",
+            })
+        })
+        .collect();
+    connections.sort_by_key(|connection| std::cmp::Reverse(connection.active));
+    connections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    const LIST: &str = "nmcli -t -f NAME,UUID,TYPE,ACTIVE connection show";
+
+    /// Recorded from a machine with one WireGuard tunnel and one OpenVPN
+    /// connection up; the colon in the name comes escaped
+    const CONNECTIONS: &str = "\
+travel:1e2d3c4b-5a69-4788-9a0b-1c2d3e4f5a6b:vpn:no
+office\\:vpn:3c1e5a8f-2d4b-4e6a-9f0c-7b8d9e0f1a2b:vpn:yes
+Home:5f0c8a4e-0b5e-4c1a-9a3e-1c2b3d4e5f60:802-11-wireless:yes
+wg0:8a7b6c5d-4e3f-4a1b-9c8d-7e6f5a4b3c2d:wireguard:yes
+";
+
+    #[test]
+    fn lists_tunnels_active_first() {
+        let connections = parse_connections(CONNECTIONS);
+        let summary: Vec<(&str, VpnKind, bool)> = connections
+            .iter()
+            .map(|c| (c.name.as_str(), c.kind, c.active))
+            .collect();
+        // Wi-Fi is left out
+        assert_eq!(
+            summary,
+            [
+                ("office:vpn", VpnKind::Vpn, true),
+                ("wg0", VpnKind::WireGuard, true),
+                ("travel", VpnKind::Vpn, false),
+            ]
+        );
+        assert_eq!(connections[0].uuid, "3c1e5a8f-2d4b-4e6a-9f0c-7b8d9e0f1a2b");
+    }
+
+    #[test]
+    fn disconnect_brings_active_tunnels_down() {
+        let runner = Arc::new(
+            FakeRunner::default()
+                .with(LIST, CONNECTIONS)
+                .failing(
+                    "nmcli connection down uuid 3c1e5a8f-2d4b-4e6a-9f0c-7b8d9e0f1a2b",
+                    "Error: 'office:vpn' is not an active connection.",
+                )
+                .with(
+                    "nmcli connection down uuid 8a7b6c5d-4e3f-4a1b-9c8d-7e6f5a4b3c2d",
+                    "",
+                ),
+        );
+        let result = VpnBackend::new(runner.clone()).disconnect_all();
+        assert_eq!(
+            result,
+            Err("office:vpn: Error: 'office:vpn' is not an active connection.".to_string())
+        );
+        // One failing doesn't stop the rest, and Wi-Fi is left alone
+        assert_eq!(
+            runner.calls(),
+            [
+                LIST,
+                "nmcli connection down uuid 3c1e5a8f-2d4b-4e6a-9f0c-7b8d9e0f1a2b",
+                "nmcli connection down uuid 8a7b6c5d-4e3f-4a1b-9c8d-7e6f5a4b3c2d",
+            ]
+        );
+    }
+}
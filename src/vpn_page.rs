@@ -0,0 +1,197 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+
+use crate::palette::Palette;
+use crate::task::BackgroundTask;
+use crate::vpn::{VpnBackend, VpnConnection};
+
+/// Where switching a connection stands
+enum SwitchState {
+    Running(BackgroundTask<Result<(), String>>),
+    Failed(String),
+}
+
+/// Detail page listing VPN and WireGuard connections, opened from the VPN row
+pub struct VpnPage {
+    backend: VpnBackend,
+    connections: Vec<VpnConnection>,
+    load: Option<BackgroundTask<Result<Vec<VpnConnection>, String>>>,
+    load_error: Option<String>,
+    /// UUID and state of the connection being switched
+    switch: Option<(String, SwitchState)>,
+}
+
+impl VpnPage {
+    pub fn new(backend: VpnBackend) -> Self {
+        Self {
+            backend,
+            connections: Vec::new(),
+            load: None,
+            load_error: None,
+            switch: None,
+        }
+    }
+
+    /// Re-reads the connection list unless that's already happening
+    pub fn refresh(&mut self, ctx: &egui::Context) {
+        if self.load.is_some() {
+            return;
+        }
+        let backend = self.backend.clone();
+        self.load = Some(BackgroundTask::spawn(ctx, move || backend.connections()));
+    }
+
+    fn toggle(&mut self, ctx: &egui::Context, connection: VpnConnection) {
+        let backend = self.backend.clone();
+        let uuid = connection.uuid.clone();
+        let task = BackgroundTask::spawn(ctx, move || {
+            backend.set_active(&connection, !connection.active)
+        });
+        self.switch = Some((uuid, SwitchState::Running(task)));
+    }
+
+    fn poll_tasks(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.load.as_ref().and_then(BackgroundTask::poll) {
+            self.load = None;
+            match result {
+                Ok(connections) => {
+                    self.connections = connections;
+                    self.load_error = None;
+                }
+                Err(e) => self.load_error = Some(e),
+            }
+        }
+
+        if let Some((_, SwitchState::Running(task))) = &self.switch {
+            match task.poll() {
+                Some(Ok(())) => {
+                    self.switch = None;
+                    self.refresh(ctx);
+                }
+                Some(Err(e)) => {
+                    if let Some((_, state)) = &mut self.switch {
+                        *state = SwitchState::Failed(e);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Returns true when the user asked to go back to the main page
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) -> bool {
+        self.poll_tasks(ui.ctx());
+        let mut back = false;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    Button::new(
+                        RichText::new(ARROW_LEFT)
+                            .size(18.0)
+                            .color(colors.on_surface),
+                    )
+                    .fill(Color32::TRANSPARENT),
+                )
+                .on_hover_text("Back")
+                .clicked()
+            {
+                back = true;
+            }
+            ui.label(RichText::new("VPN").size(18.0).color(colors.on_surface));
+            if self.load.is_some() {
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.spinner();
+                });
+            }
+        });
+        ui.add_space(8.0);
+
+        if let Some(error) = &self.load_error {
+            ui.label(RichText::new(error).size(12.0).color(colors.error));
+        }
+        if self.connections.is_empty() && self.load.is_none() {
+            ui.label(
+                RichText::new("No VPN connections set up")
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+        }
+
+        let running = matches!(self.switch, Some((_, SwitchState::Running(_))));
+        let mut clicked = None;
+        for connection in &self.connections {
+            let state = self
+                .switch
+                .as_ref()
+                .filter(|(uuid, _)| *uuid == connection.uuid)
+                .map(|(_, state)| state);
+            if connection_row(ui, connection, state, colors).clicked() && !running {
+                clicked = Some(connection.clone());
+            }
+        }
+
+        if let Some(connection) = clicked {
+            self.toggle(ui.ctx(), connection);
+        }
+
+        back
+    }
+}
+
+fn connection_row(
+    ui: &mut egui::Ui,
+    connection: &VpnConnection,
+    state: Option<&SwitchState>,
+    colors: &Palette,
+) -> egui::Response {
+    let (fill, text_color) = if connection.active {
+        (colors.secondary_container, colors.on_secondary_container)
+    } else {
+        (colors.surface, colors.on_surface)
+    };
+
+    let response = egui::Frame::new()
+        .fill(fill)
+        .corner_radius(12.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                let icon = if connection.active {
+                    SHIELD_CHECK
+                } else {
+                    SHIELD
+                };
+                ui.label(RichText::new(icon).size(20.0).color(text_color));
+                ui.vertical(|ui| {
+                    ui.label(RichText::new(&connection.name).color(text_color));
+                    let status = match state {
+                        Some(SwitchState::Running(_)) if connection.active => "Disconnecting…",
+                        Some(SwitchState::Running(_)) => "Connecting…",
+                        _ if connection.active => "Connected",
+                        _ => "Off",
+                    };
+                    ui.label(
+                        RichText::new(format!("{} · {}", connection.kind.label(), status))
+                            .size(12.0)
+                            .color(text_color.gamma_multiply(0.7)),
+                    );
+                    // Missing secrets and the like show up right on the row
+                    if let Some(SwitchState::Failed(e)) = state {
+                        ui.label(RichText::new(e).size(11.0).color(colors.error));
+                    }
+                });
+                if matches!(state, Some(SwitchState::Running(_))) {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.spinner();
+                    });
+                }
+            });
+        })
+        .response;
+    ui.add_space(4.0);
+
+    response.interact(egui::Sense::click())
+}
@@ -0,0 +1,348 @@
+use std::sync::Arc;
+
+use crate::command_runner::CommandRunner;
+use crate::nmcli::split_terse;
+
+/// A network from a Wi-Fi scan
+#[derive(Clone, Debug, PartialEq)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    /// Signal strength, 0-100
+    pub signal: u8,
+    /// e.g. "WPA2" or "WPA1 WPA2"; empty for open networks
+    pub security: String,
+    /// UUID of a saved NetworkManager connection for this SSID
+    pub connection: Option<String>,
+    pub in_use: bool,
+}
+
+impl WifiNetwork {
+    pub fn is_open(&self) -> bool {
+        self.security.is_empty()
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.connection.is_some()
+    }
+}
+
+/// A saved Wi-Fi connection. Its name is only a label and often differs
+/// from the SSID (renamed, or "Home 1" after a duplicate).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedConnection {
+    pub uuid: String,
+    pub ssid: String,
+}
+
+/// Wi-Fi scanning and connecting through `nmcli`. Cheap to clone, so the slow
+/// calls can run on a background thread.
+#[derive(Clone)]
+pub struct WifiBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl WifiBackend {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// Whether the Wi-Fi radio is switched on
+    pub fn radio_enabled(&self) -> Result<bool, String> {
+        let output = self.runner.run("nmcli", &["radio", "wifi"])?;
+        Ok(output.trim() == "enabled")
+    }
+
+    pub fn set_radio(&self, enabled: bool) -> Result<(), String> {
+        let state = if enabled { "on" } else { "off" };
+        self.runner
+            .run("nmcli", &["radio", "wifi", state])
+            .map(|_| ())
+    }
+
+    /// Name of the connection up on a Wi-Fi device, if any
+    pub fn active_network(&self) -> Result<Option<String>, String> {
+        let output = self.runner.run(
+            "nmcli",
+            &["-t", "-f", "NAME,DEVICE", "connection", "show", "--active"],
+        )?;
+        Ok(parse_active_wifi(&output))
+    }
+
+    /// Saved Wi-Fi connections with the SSID each one is for
+    pub fn saved_connections(&self) -> Result<Vec<SavedConnection>, String> {
+        let output = self
+            .runner
+            .run("nmcli", &["-t", "-f", "UUID,TYPE", "connection", "show"])?;
+        let mut saved = Vec::new();
+        for uuid in parse_wifi_uuids(&output) {
+            let ssid = self.runner.run(
+                "nmcli",
+                &[
+                    "-g",
+                    "802-11-wireless.ssid",
+                    "connection",
+                    "show",
+                    "uuid",
+                    &uuid,
+                ],
+            )?;
+            if let Some(ssid) = parse_ssid_value(&ssid) {
+                saved.push(SavedConnection { uuid, ssid });
+            }
+        }
+        Ok(saved)
+    }
+
+    /// Scans for nearby networks, strongest first with the active one on top
+    pub fn scan(&self) -> Result<Vec<WifiNetwork>, String> {
+        let saved = self.saved_connections()?;
+        let list = self.runner.run(
+            "nmcli",
+            &[
+                "-t",
+                "-f",
+                "IN-USE,SSID,SIGNAL,SECURITY",
+                "device",
+                "wifi",
+                "list",
+                "--rescan",
+                "yes",
+            ],
+        )?;
+        Ok(parse_wifi_list(&list, &saved))
+    }
+
+    /// Connects to `network`. Saved networks are brought up by UUID, new ones
+    /// are created with `password` if they need one. The password goes to
+    /// nmcli's `--ask` prompt on stdin, never onto the command line where
+    /// other users could read it.
+    pub fn connect(&self, network: &WifiNetwork, password: Option<&str>) -> Result<(), String> {
+        let ssid = network.ssid.as_str();
+        match (&network.connection, password) {
+            (Some(uuid), None) => self
+                .runner
+                .run("nmcli", &["connection", "up", "uuid", uuid])
+                .map(|_| ()),
+            (_, None) => self
+                .runner
+                .run("nmcli", &["device", "wifi", "connect", ssid])
+                .map(|_| ()),
+            (_, Some(password)) => self
+                .runner
+                .run_with_input(
+                    "nmcli",
+                    &["--ask", "device", "wifi", "connect", ssid],
+                    &format!("{}\n", password),
+                )
+                .map(|_| ()),
+        }
+    }
+}
+
+/// The first connection on a Wi-Fi device (wlan0, wlp2s0, ...) from
+/// `nmcli -t -f NAME,DEVICE connection show --active`
+pub fn parse_active_wifi(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let fields = split_terse(line);
+        match fields.as_slice() {
+            [name, device] if device.starts_with("wl") => Some(name.clone()),
+            _ => None,
+        }
+    })
+}
+
+/// UUIDs of Wi-Fi connections from `nmcli -t -f UUID,TYPE connection show`
+pub fn parse_wifi_uuids(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = split_terse(line);
+            match fields.as_slice() {
+                [uuid, kind] if kind == "802-11-wireless" => Some(uuid.clone()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The SSID from `nmcli -g 802-11-wireless.ssid connection show ...`, which
+/// is escaped like terse output
+pub fn parse_ssid_value(output: &str) -> Option<String> {
+    let line = output.lines().next()?;
+    let ssid = split_terse(line).concat();
+    (!ssid.is_empty()).then_some(ssid)
+}
+
+/// Parses `nmcli -t -f IN-USE,SSID,SIGNAL,SECURITY device wifi list`. Hidden
+/// networks are dropped and access points sharing an SSID are merged into
+/// the strongest one.
+pub fn parse_wifi_list(output: &str, saved: &[SavedConnection]) -> Vec<WifiNetwork> {
+    let mut networks: Vec<WifiNetwork> = Vec::new();
+
+    for line in output.lines() {
+        let fields = split_terse(line);
+        let [in_use, ssid, signal, security] = fields.as_slice() else {
+            continue;
+        };
+        if ssid.is_empty() {
+            continue;
+        }
+
+        let network = WifiNetwork {
+            ssid: ssid.clone(),
+            signal: signal.parse().unwrap_or(0),
+            security: if security == "--" {
+                String::new()
+            } else {
+                security.trim().to_string()
+            },
+            connection: saved
+                .iter()
+                .find(|connection| connection.ssid == *ssid)
+                .map(|connection| connection.uuid.clone()),
+            in_use: in_use.trim() == "*",
+        };
+
+        match networks
+            .iter_mut()
+            .find(|existing| existing.ssid == network.ssid)
+        {
+            Some(existing) => {
+                existing.in_use |= network.in_use;
+                if network.signal > existing.signal {
+                    existing.signal = network.signal;
+                    existing.security = network.security;
+                }
+            }
+            None => networks.push(network),
+        }
+    }
+
+    networks.sort_by(|a, b| b.in_use.cmp(&a.in_use).then(b.signal.cmp(&a.signal)));
+    networks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    const CONNECTIONS: &str = "\
+5f0c8a4e-0b5e-4c1a-9a3e-1c2b3d4e5f60:802-11-wireless
+0d7e2c11-7a44-4f8e-b1d2-9e8f7a6b5c4d:802-3-ethernet
+a1b2c3d4-e5f6-4711-8899-aabbccddeeff:802-11-wireless
+";
+    const SCAN: &str = "\
+*:HomeNet:82:WPA2
+ :HomeNet:40:WPA2
+ :Cafe\\:Guest:55:--
+ ::70:WPA2
+ :Neighbour:91:WPA1 WPA2
+";
+
+    fn runner() -> FakeRunner {
+        FakeRunner::default()
+            .with("nmcli -t -f UUID,TYPE connection show", CONNECTIONS)
+            .with(
+                "nmcli -g 802-11-wireless.ssid connection show uuid 5f0c8a4e-0b5e-4c1a-9a3e-1c2b3d4e5f60",
+                "HomeNet\n",
+            )
+            .with(
+                "nmcli -g 802-11-wireless.ssid connection show uuid a1b2c3d4-e5f6-4711-8899-aabbccddeeff",
+                "Cafe\\:Guest\n",
+            )
+            .with(
+                "nmcli -t -f IN-USE,SSID,SIGNAL,SECURITY device wifi list --rescan yes",
+                SCAN,
+            )
+    }
+
+    #[test]
+    fn scan_matches_saved_connections_by_ssid() {
+        let networks = WifiBackend::new(Arc::new(runner())).scan().unwrap();
+        assert_eq!(
+            networks,
+            [
+                WifiNetwork {
+                    ssid: "HomeNet".to_string(),
+                    signal: 82,
+                    security: "WPA2".to_string(),
+                    connection: Some("5f0c8a4e-0b5e-4c1a-9a3e-1c2b3d4e5f60".to_string()),
+                    in_use: true,
+                },
+                WifiNetwork {
+                    ssid: "Neighbour".to_string(),
+                    signal: 91,
+                    security: "WPA1 WPA2".to_string(),
+                    connection: None,
+                    in_use: false,
+                },
+                WifiNetwork {
+                    ssid: "Cafe:Guest".to_string(),
+                    signal: 55,
+                    security: String::new(),
+                    connection: Some("a1b2c3d4-e5f6-4711-8899-aabbccddeeff".to_string()),
+                    in_use: false,
+                },
+            ]
+        );
+        assert!(networks[2].is_open());
+    }
+
+    #[test]
+    fn known_network_comes_up_by_uuid() {
+        let runner = Arc::new(runner().with(
+            "nmcli connection up uuid a1b2c3d4-e5f6-4711-8899-aabbccddeeff",
+            "",
+        ));
+        let backend = WifiBackend::new(runner.clone());
+        let networks = backend.scan().unwrap();
+        backend.connect(&networks[2], None).unwrap();
+        assert_eq!(
+            runner.calls().last().unwrap(),
+            "nmcli connection up uuid a1b2c3d4-e5f6-4711-8899-aabbccddeeff"
+        );
+    }
+
+    #[test]
+    fn password_goes_to_stdin() {
+        let runner = Arc::new(runner().with("nmcli --ask device wifi connect Neighbour", ""));
+        let backend = WifiBackend::new(runner.clone());
+        let networks = backend.scan().unwrap();
+        backend.connect(&networks[1], Some("hunter2")).unwrap();
+
+        assert!(runner.calls().iter().all(|call| !call.contains("hunter2")));
+        assert_eq!(runner.inputs(), ["hunter2\n"]);
+    }
+
+    #[test]
+    fn connect_error_is_returned() {
+        let runner = runner().failing(
+            "nmcli --ask device wifi connect Neighbour",
+            "Error: Connection activation failed: Secrets were required, but not provided.",
+        );
+        let backend = WifiBackend::new(Arc::new(runner));
+        let networks = backend.scan().unwrap();
+        assert!(
+            backend
+                .connect(&networks[1], Some("wrong"))
+                .unwrap_err()
+                .contains("Secrets were required")
+        );
+    }
+
+    #[test]
+    fn active_network_is_the_one_on_a_wifi_device() {
+        let runner = runner().with("nmcli radio wifi", "enabled\n").with(
+            "nmcli -t -f NAME,DEVICE connection show --active",
+            "Wired connection 1:enp3s0\nCafe\\: Guest:wlp2s0\nlo:lo\n",
+        );
+        let backend = WifiBackend::new(Arc::new(runner));
+        assert!(backend.radio_enabled().unwrap());
+        assert_eq!(
+            backend.active_network().unwrap(),
+            Some("Cafe: Guest".to_string())
+        );
+        assert_eq!(parse_active_wifi("Wired connection 1:enp3s0\n"), None);
+    }
+}
@@ -0,0 +1,312 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+
+use crate::palette::Palette;
+use crate::task::BackgroundTask;
+use crate::wifi::{WifiBackend, WifiNetwork};
+
+/// Where a connection attempt stands
+enum ConnectState {
+    Connecting(BackgroundTask<Result<(), String>>),
+    Failed(String),
+}
+
+/// Password entry for a network we have no saved connection for
+struct PasswordPrompt {
+    network: WifiNetwork,
+    password: String,
+}
+
+/// Detail page listing nearby Wi-Fi networks, opened from the Wi-Fi row
+pub struct WifiPage {
+    backend: WifiBackend,
+    networks: Vec<WifiNetwork>,
+    scan: Option<BackgroundTask<Result<Vec<WifiNetwork>, String>>>,
+    scan_error: Option<String>,
+    /// SSID and state of the network being connected to
+    connect: Option<(String, ConnectState)>,
+    prompt: Option<PasswordPrompt>,
+}
+
+impl WifiPage {
+    pub fn new(backend: WifiBackend) -> Self {
+        Self {
+            backend,
+            networks: Vec::new(),
+            scan: None,
+            scan_error: None,
+            connect: None,
+            prompt: None,
+        }
+    }
+
+    /// Starts a scan unless one is already running
+    pub fn rescan(&mut self, ctx: &egui::Context) {
+        if self.scan.is_some() {
+            return;
+        }
+        let backend = self.backend.clone();
+        self.scan = Some(BackgroundTask::spawn(ctx, move || backend.scan()));
+    }
+
+    fn connect(&mut self, ctx: &egui::Context, network: WifiNetwork, password: Option<String>) {
+        let backend = self.backend.clone();
+        let ssid = network.ssid.clone();
+        let task =
+            BackgroundTask::spawn(ctx, move || backend.connect(&network, password.as_deref()));
+        self.connect = Some((ssid, ConnectState::Connecting(task)));
+    }
+
+    /// Picks up finished scans and connection attempts
+    fn poll_tasks(&mut self, ctx: &egui::Context) {
+        if let Some(result) = self.scan.as_ref().and_then(BackgroundTask::poll) {
+            self.scan = None;
+            match result {
+                Ok(networks) => {
+                    self.networks = networks;
+                    self.scan_error = None;
+                }
+                Err(e) => self.scan_error = Some(e),
+            }
+        }
+
+        if let Some((_, ConnectState::Connecting(task))) = &self.connect {
+            match task.poll() {
+                Some(Ok(())) => {
+                    self.connect = None;
+                    // Re-read the list so the new network shows as connected
+                    self.rescan(ctx);
+                }
+                Some(Err(e)) => {
+                    if let Some((_, state)) = &mut self.connect {
+                        *state = ConnectState::Failed(e);
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Returns true when the user asked to go back to the main page
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) -> bool {
+        self.poll_tasks(ui.ctx());
+        let mut back = false;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    Button::new(
+                        RichText::new(ARROW_LEFT)
+                            .size(18.0)
+                            .color(colors.on_surface),
+                    )
+                    .fill(Color32::TRANSPARENT),
+                )
+                .on_hover_text("Back")
+                .clicked()
+            {
+                back = true;
+            }
+            ui.label(RichText::new("Wi-Fi").size(18.0).color(colors.on_surface));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if self.scan.is_some() {
+                    ui.spinner();
+                } else if ui
+                    .add(
+                        Button::new(
+                            RichText::new(ARROW_CLOCKWISE)
+                                .size(18.0)
+                                .color(colors.on_surface),
+                        )
+                        .fill(Color32::TRANSPARENT),
+                    )
+                    .on_hover_text("Scan again")
+                    .clicked()
+                {
+                    self.rescan(ui.ctx());
+                }
+            });
+        });
+        ui.add_space(8.0);
+
+        if let Some(error) = &self.scan_error {
+            ui.label(RichText::new(error).size(12.0).color(colors.error));
+        }
+        if self.networks.is_empty() && self.scan.is_some() {
+            ui.label(
+                RichText::new("Scanning…")
+                    .size(12.0)
+                    .color(colors.on_surface.gamma_multiply(0.7)),
+            );
+        }
+
+        let mut clicked = None;
+        for index in 0..self.networks.len() {
+            let network = &self.networks[index];
+            let state = self
+                .connect
+                .as_ref()
+                .filter(|(ssid, _)| *ssid == network.ssid)
+                .map(|(_, state)| state);
+            if network_row(ui, network, state, colors).clicked() && !network.in_use {
+                clicked = Some(network.clone());
+            }
+
+            // The password prompt opens right under the network it belongs to
+            let ssid = &self.networks[index].ssid;
+            if self
+                .prompt
+                .as_ref()
+                .is_some_and(|prompt| prompt.network.ssid == *ssid)
+            {
+                self.show_prompt(ui, colors);
+            }
+        }
+
+        if let Some(network) = clicked {
+            if network.is_known() || network.is_open() {
+                self.prompt = None;
+                self.connect(ui.ctx(), network, None);
+            } else {
+                self.prompt = Some(PasswordPrompt {
+                    network,
+                    password: String::new(),
+                });
+            }
+        }
+
+        back
+    }
+
+    fn show_prompt(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        let mut submit = false;
+        let mut cancel = false;
+        if let Some(prompt) = &mut self.prompt {
+            egui::Frame::new()
+                .fill(colors.surface_container_high)
+                .corner_radius(12.0)
+                .inner_margin(8.0)
+                .show(ui, |ui| {
+                    ui.set_min_width(ui.available_width());
+                    ui.label(
+                        RichText::new("Password")
+                            .size(12.0)
+                            .color(colors.on_surface),
+                    );
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut prompt.password)
+                            .password(true)
+                            .desired_width(f32::INFINITY),
+                    );
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        submit = true;
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Connect").clicked() {
+                            submit = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            cancel = true;
+                        }
+                    });
+                });
+        }
+
+        if cancel {
+            self.prompt = None;
+        } else if let Some(prompt) = self.prompt.take_if(|_| submit) {
+            self.connect(ui.ctx(), prompt.network, Some(prompt.password));
+        }
+    }
+}
+
+/// Signal bars glyph for a 0-100 signal strength
+fn signal_icon(signal: u8) -> &'static str {
+    match signal {
+        0..=24 => WIFI_NONE,
+        25..=49 => WIFI_LOW,
+        50..=74 => WIFI_MEDIUM,
+        _ => WIFI_HIGH,
+    }
+}
+
+fn network_row(
+    ui: &mut egui::Ui,
+    network: &WifiNetwork,
+    state: Option<&ConnectState>,
+    colors: &Palette,
+) -> egui::Response {
+    let fill = if network.in_use {
+        colors.secondary_container
+    } else {
+        colors.surface
+    };
+    let text_color = if network.in_use {
+        colors.on_secondary_container
+    } else {
+        colors.on_surface
+    };
+
+    let response = egui::Frame::new()
+        .fill(fill)
+        .corner_radius(12.0)
+        .inner_margin(8.0)
+        .show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(signal_icon(network.signal))
+                        .size(20.0)
+                        .color(text_color),
+                );
+                ui.vertical(|ui| {
+                    ui.label(RichText::new(&network.ssid).color(text_color));
+
+                    let mut details = Vec::new();
+                    match state {
+                        Some(ConnectState::Connecting(_)) => {
+                            details.push("Connecting…".to_string())
+                        }
+                        Some(ConnectState::Failed(_)) => {
+                            details.push("Failed to connect".to_string())
+                        }
+                        None if network.in_use => details.push("Connected".to_string()),
+                        None => {}
+                    }
+                    if network.is_known() && !network.in_use {
+                        details.push("Saved".to_string());
+                    }
+                    details.push(if network.is_open() {
+                        "Open".to_string()
+                    } else {
+                        format!("{} {}", LOCK_SIMPLE, network.security)
+                    });
+
+                    let detail_color = if matches!(state, Some(ConnectState::Failed(_))) {
+                        colors.error
+                    } else {
+                        text_color.gamma_multiply(0.7)
+                    };
+                    ui.label(
+                        RichText::new(details.join(" · "))
+                            .size(12.0)
+                            .color(detail_color),
+                    );
+                    if let Some(ConnectState::Failed(e)) = state {
+                        ui.label(RichText::new(e).size(11.0).color(colors.error));
+                    }
+                });
+                if matches!(state, Some(ConnectState::Connecting(_))) {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.spinner();
+                    });
+                }
+            });
+        })
+        .response;
+    ui.add_space(4.0);
+
+    response.interact(egui::Sense::click())
+}
@@ -0,0 +1,341 @@
+use eframe::egui;
+use egui::{Button, Color32, RichText};
+use egui_phosphor::regular::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::command_runner::CommandRunner;
+use crate::hyprland::{self, Client};
+use crate::icons::IconCache;
+use crate::palette::Palette;
+
+/// How often the window list is refreshed while the section is open
+const OPEN_INTERVAL: Duration = Duration::from_secs(2);
+/// Collapsed, we only need the window count for the header
+const CLOSED_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Workspaces offered in the "Move to" menu besides the ones in use
+const WORKSPACE_COUNT: i32 = 10;
+
+#[derive(Clone, Copy)]
+enum WindowAction {
+    Focus,
+    Close,
+    ToggleFloating,
+    Pin,
+    MoveTo(i32),
+}
+
+/// Expandable "Open windows" list, grouped by workspace
+pub struct WindowSwitcher {
+    runner: Arc<dyn CommandRunner>,
+    icons: IconCache,
+    expanded: bool,
+    clients: Vec<Client>,
+    last_update: Option<Instant>,
+    /// Listing the windows failed
+    error: Option<String>,
+    /// The last focus/close/move failed. Kept apart from `error` so the
+    /// refresh that follows doesn't wipe it.
+    action_error: Option<String>,
+}
+
+impl WindowSwitcher {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self {
+            runner,
+            icons: IconCache::default(),
+            expanded: false,
+            clients: Vec::new(),
+            last_update: None,
+            error: None,
+            action_error: None,
+        }
+    }
+
+    /// Re-reads the windows, leaving out the panel itself and ones that
+    /// aren't on screen
+    pub fn refresh(&mut self) {
+        match hyprland::clients(self.runner.as_ref()) {
+            Ok(clients) => {
+                self.clients = clients
+                    .into_iter()
+                    .filter(|client| client.mapped && client.title != "Action Center")
+                    .collect();
+                self.clients.sort_by(|a, b| {
+                    a.workspace
+                        .id
+                        .cmp(&b.workspace.id)
+                        .then(a.focus_history_id.cmp(&b.focus_history_id))
+                });
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+        self.last_update = Some(Instant::now());
+    }
+
+    fn run(&mut self, client: &Client, action: WindowAction) {
+        let address = format!("address:{}", client.address);
+        let result = match action {
+            WindowAction::Focus => {
+                hyprland::dispatch(self.runner.as_ref(), "focuswindow", &address)
+            }
+            WindowAction::Close => {
+                hyprland::dispatch(self.runner.as_ref(), "closewindow", &address)
+            }
+            WindowAction::ToggleFloating => {
+                hyprland::dispatch(self.runner.as_ref(), "togglefloating", &address)
+            }
+            WindowAction::Pin => {
+                // Only floating windows can be pinned
+                if client.floating {
+                    Ok(())
+                } else {
+                    hyprland::dispatch(self.runner.as_ref(), "setfloating", &address)
+                }
+                .and_then(|_| hyprland::dispatch(self.runner.as_ref(), "pin", &address))
+            }
+            WindowAction::MoveTo(workspace) => hyprland::dispatch(
+                self.runner.as_ref(),
+                "movetoworkspacesilent",
+                &format!("{},{}", workspace, address),
+            ),
+        };
+        self.action_error = result.err();
+        self.refresh();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, colors: &Palette) {
+        let interval = if self.expanded {
+            OPEN_INTERVAL
+        } else {
+            CLOSED_INTERVAL
+        };
+        if self
+            .last_update
+            .is_none_or(|last| last.elapsed() >= interval)
+        {
+            self.refresh();
+        }
+        ui.ctx().request_repaint_after(interval);
+
+        ui.horizontal(|ui| {
+            ui.add_space(8.0);
+            let caret = if self.expanded {
+                CARET_DOWN
+            } else {
+                CARET_RIGHT
+            };
+            let label = format!("{}  Open windows ({})", caret, self.clients.len());
+            if ui
+                .add(
+                    Button::new(RichText::new(label).size(12.0).color(colors.on_surface))
+                        .fill(Color32::TRANSPARENT),
+                )
+                .clicked()
+            {
+                self.expanded = !self.expanded;
+                self.last_update = None;
+            }
+        });
+
+        if !self.expanded {
+            return;
+        }
+
+        for error in [&self.error, &self.action_error].into_iter().flatten() {
+            ui.horizontal(|ui| {
+                ui.add_space(8.0);
+                ui.label(RichText::new(error).size(12.0).color(colors.error));
+            });
+        }
+
+        // Workspaces in use first, then the usual numbered ones
+        let mut workspaces: Vec<i32> = self
+            .clients
+            .iter()
+            .map(|client| client.workspace.id)
+            .filter(|id| *id > 0)
+            .chain(1..=WORKSPACE_COUNT)
+            .collect();
+        workspaces.sort_unstable();
+        workspaces.dedup();
+
+        let mut picked = None;
+        let mut current_workspace = None;
+        for client in &self.clients {
+            if current_workspace != Some(client.workspace.id) {
+                current_workspace = Some(client.workspace.id);
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    ui.add_space(8.0);
+                    ui.label(
+                        RichText::new(format!("Workspace {}", client.workspace.name))
+                            .size(12.0)
+                            .color(colors.on_surface.gamma_multiply(0.7)),
+                    );
+                });
+            }
+            if let Some(action) = Self::window_row(&mut self.icons, ui, client, &workspaces, colors)
+            {
+                picked = Some((client.clone(), action));
+            }
+        }
+
+        if let Some((client, action)) = picked {
+            self.run(&client, action);
+        }
+    }
+
+    /// Draws one window and returns the action the user picked, if any
+    fn window_row(
+        icons: &mut IconCache,
+        ui: &mut egui::Ui,
+        client: &Client,
+        workspaces: &[i32],
+        colors: &Palette,
+    ) -> Option<WindowAction> {
+        let mut action = None;
+        let frame = egui::Frame::new()
+            .fill(colors.surface)
+            .corner_radius(12.0)
+            .inner_margin(6.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    icons.show(
+                        ui,
+                        Some(&client.class.to_lowercase()),
+                        "apps",
+                        APP_WINDOW,
+                        24.0,
+                        colors.on_surface,
+                    );
+                    ui.vertical(|ui| {
+                        ui.add(
+                            egui::Label::new(RichText::new(&client.title).color(colors.on_surface))
+                                .truncate(),
+                        );
+                        let mut badges = vec![client.class.clone()];
+                        if client.floating {
+                            badges.push("Floating".to_string());
+                        }
+                        if client.pinned {
+                            badges.push("Pinned".to_string());
+                        }
+                        if client.fullscreen > 0 {
+                            badges.push("Fullscreen".to_string());
+                        }
+                        if client.xwayland {
+                            badges.push("XWayland".to_string());
+                        }
+                        ui.label(
+                            RichText::new(badges.join(" · "))
+                                .size(11.0)
+                                .color(colors.on_surface.gamma_multiply(0.7)),
+                        );
+                    });
+                });
+            });
+
+        let response = frame
+            .response
+            .interact(egui::Sense::click())
+            .on_hover_text("Click to focus, right-click for more");
+        if response.clicked() {
+            action = Some(WindowAction::Focus);
+        }
+        response.context_menu(|ui| {
+            let floating = if client.floating { "Tile" } else { "Float" };
+            let pin = if client.pinned { "Unpin" } else { "Pin" };
+            let items = [
+                (ARROW_SQUARE_OUT, "Focus", WindowAction::Focus),
+                (SQUARES_FOUR, floating, WindowAction::ToggleFloating),
+                (PUSH_PIN, pin, WindowAction::Pin),
+            ];
+            for (icon, label, picked) in items {
+                if ui.button(format!("{}  {}", icon, label)).clicked() {
+                    action = Some(picked);
+                    ui.close_menu();
+                }
+            }
+            ui.menu_button(format!("{}  Move to", ARROW_RIGHT), |ui| {
+                for workspace in workspaces {
+                    if *workspace != client.workspace.id
+                        && ui.button(format!("Workspace {}", workspace)).clicked()
+                    {
+                        action = Some(WindowAction::MoveTo(*workspace));
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.separator();
+            if ui.button(format!("{}  Close", X)).clicked() {
+                action = Some(WindowAction::Close);
+                ui.close_menu();
+            }
+        });
+        ui.add_space(4.0);
+
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::FakeRunner;
+
+    fn runner() -> FakeRunner {
+        let clients = std::fs::read_to_string(format!(
+            "{}/tests/fixtures/hyprland/clients.json",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .unwrap();
+        FakeRunner::default().with("hyprctl clients -j", &clients)
+    }
+
+    #[test]
+    fn lists_windows_by_workspace() {
+        let mut switcher = WindowSwitcher::new(Arc::new(runner()));
+        switcher.refresh();
+        let titles: Vec<&str> = switcher.clients.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, ["~/src", "Hyprland Wiki — Mozilla Firefox"]);
+    }
+
+    #[test]
+    fn pinning_floats_first() {
+        let runner = Arc::new(
+            runner()
+                .with("hyprctl dispatch setfloating address:0x55d1c4c0b8e0", "ok")
+                .with("hyprctl dispatch pin address:0x55d1c4c0b8e0", "ok"),
+        );
+        let mut switcher = WindowSwitcher::new(runner.clone());
+        switcher.refresh();
+        let kitty = switcher.clients[0].clone();
+        switcher.run(&kitty, WindowAction::Pin);
+        assert_eq!(switcher.action_error, None);
+        assert_eq!(
+            runner.calls()[1..3],
+            [
+                "hyprctl dispatch setfloating address:0x55d1c4c0b8e0",
+                "hyprctl dispatch pin address:0x55d1c4c0b8e0",
+            ]
+        );
+    }
+
+    #[test]
+    fn action_error_survives_refresh() {
+        let runner = runner().with(
+            "hyprctl dispatch closewindow address:0x55d1c4a2e0f0",
+            "Window not found",
+        );
+        let mut switcher = WindowSwitcher::new(Arc::new(runner));
+        switcher.refresh();
+        let firefox = switcher.clients[1].clone();
+        switcher.run(&firefox, WindowAction::Close);
+        assert_eq!(switcher.error, None);
+        assert_eq!(switcher.action_error.as_deref(), Some("Window not found"));
+    }
+}
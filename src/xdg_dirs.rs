@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME`, falling back to ~/.config
+pub fn config_home() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| home().join(".config"))
+}
+
+/// `$XDG_DATA_HOME`, falling back to ~/.local/share
+pub fn data_home() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| home().join(".local/share"))
+}
+
+pub fn home() -> PathBuf {
+    PathBuf::from(shellexpand::tilde("~").as_ref())
+}
+
+/// The user dirs from `user-dirs.dirs`, keyed by name ("DOWNLOAD",
+/// "DOCUMENTS", ...), in the order the file lists them
+pub fn user_dirs() -> Vec<(String, PathBuf)> {
+    let text = std::fs::read_to_string(config_home().join("user-dirs.dirs")).unwrap_or_default();
+    parse_user_dirs(&text, &home())
+}
+
+/// One user dir, with the usual English name when it isn't configured
+pub fn user_dir(name: &str, fallback: &str) -> PathBuf {
+    let dirs: HashMap<String, PathBuf> = user_dirs().into_iter().collect();
+    dirs.get(name)
+        .cloned()
+        .unwrap_or_else(|| home().join(fallback))
+}
+
+/// Parses lines like `XDG_DOWNLOAD_DIR="$HOME/Downloads"`. Only `$HOME`
+/// prefixed and absolute paths are allowed by the format; a dir set to
+/// `$HOME/` itself means it's disabled.
+pub fn parse_user_dirs(text: &str, home: &Path) -> Vec<(String, PathBuf)> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            let name = key.trim().strip_prefix("XDG_")?.strip_suffix("_DIR")?;
+            let value = value.trim().trim_matches('"');
+            let path = match value.strip_prefix("$HOME") {
+                Some(rest) => {
+                    let rest = rest.trim_start_matches('/');
+                    if rest.is_empty() {
+                        return None;
+                    }
+                    home.join(rest)
+                }
+                None if value.starts_with('/') => PathBuf::from(value),
+                None => return None,
+            };
+            Some((name.to_string(), path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_user_dirs() {
+        let text = std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/xdg/user-dirs.dirs"
+        ))
+        .unwrap();
+        let dirs = parse_user_dirs(&text, Path::new("/home/alex"));
+        let expected = [
+            ("DESKTOP", "/home/alex/Desktop"),
+            ("DOWNLOAD", "/home/alex/Téléchargements"),
+            // TEMPLATES and PUBLICSHARE point at $HOME itself, so they're
+            // disabled, and MUSIC is relative, which the format doesn't allow
+            ("DOCUMENTS", "/srv/documents"),
+            ("PICTURES", "/home/alex/Pictures/Camera"),
+            ("VIDEOS", "/home/alex/Videos"),
+        ];
+        assert_eq!(
+            dirs,
+            expected.map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+        );
+    }
+}